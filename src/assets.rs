@@ -4,7 +4,10 @@ use bevy_common_assets::ron::RonAssetPlugin;
 use bevy_seedling::prelude::AudioSample;
 use serde::{Deserialize, Serialize};
 
-use crate::{AppState, ratspinner::RatScriptAsset};
+use crate::{
+    AppState,
+    ratspinner::{RatScriptAsset, RatSpeakers},
+};
 
 pub struct AssetsPlugin;
 
@@ -37,6 +40,8 @@ pub struct GameAssets {
     pub items: Vec<Handle<ItemMeta>>,
     #[asset(key = "ratspinner.scripts", collection(typed))]
     pub rat_scripts: Vec<Handle<RatScriptAsset>>,
+    #[asset(key = "ratspinner.speakers")]
+    pub rat_speakers: Handle<RatSpeakers>,
     #[asset(key = "font.pixel")]
     pub font_pixel: Handle<Font>,
     #[asset(key = "font.body")]
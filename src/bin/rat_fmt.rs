@@ -0,0 +1,115 @@
+//! rewrites dialogue scripts in canonical form, or converts them
+//!
+//! usage: `cargo run --bin rat-fmt -- [--check] [--to rat|ron] [--assets dir] <files..>`
+//!
+//! files are formatted in place. `--to` writes the converted script next to
+//! the original (`npc.default.rat` -> `npc.default.ron`), `--check` only lists
+//! the files that would change
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use feverish::ratspinner::{
+    RatSpeakers, SPEAKERS_PATH, parse_script_file, read_speakers, write_rat, write_ron,
+};
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut to: Option<String> = None;
+    let mut assets_root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let mut files: Vec<PathBuf> = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--to" => match args.next() {
+                Some(ext) if ext == "rat" || ext == "ron" => to = Some(ext),
+                _ => return usage("`--to` takes `rat` or `ron`"),
+            },
+            "--assets" => match args.next() {
+                Some(dir) => assets_root = PathBuf::from(dir),
+                None => return usage("`--assets` takes a directory"),
+            },
+            _ if arg.starts_with("--") => return usage(&format!("unknown flag '{arg}'")),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    if files.is_empty() {
+        return usage("no files given");
+    }
+
+    let speakers = match read_speakers(&assets_root.join(SPEAKERS_PATH)) {
+        Ok(speakers) => speakers,
+        Err(error) => {
+            eprintln!("rat-fmt: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = false;
+    for path in &files {
+        let (out_path, text) = match format_file(path, to.as_deref(), &speakers) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                eprintln!("{}: {error}", path.display());
+                failed = true;
+                continue;
+            }
+        };
+        let current = std::fs::read_to_string(&out_path).unwrap_or_default();
+        if current == text {
+            continue;
+        }
+        if check {
+            eprintln!("would rewrite {}", out_path.display());
+            failed = true;
+        } else if let Err(error) = std::fs::write(&out_path, text) {
+            eprintln!("{}: failed to write: {error}", out_path.display());
+            failed = true;
+        } else {
+            eprintln!("rewrote {}", out_path.display());
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// where the result goes and what it should contain
+fn format_file(
+    path: &Path,
+    to: Option<&str>,
+    speakers: &RatSpeakers,
+) -> Result<(PathBuf, String), String> {
+    let bytes = std::fs::read(path).map_err(|error| format!("failed to read: {error}"))?;
+    let scripts = parse_script_file(&bytes, path, speakers)?;
+    let extension = path
+        .extension()
+        .and_then(|value| value.to_str())
+        .unwrap_or_default();
+    let target = to.unwrap_or(extension);
+    let text = match (target, scripts.as_slice()) {
+        ("rat", _) => write_rat(&scripts, speakers),
+        ("ron", [script]) => write_ron(script, speakers)?,
+        ("ron", _) => {
+            return Err(format!(
+                "holds {} scripts, a .ron file takes one",
+                scripts.len()
+            ));
+        }
+        _ => return Err(format!("can't write .{target} files, pass `--to`")),
+    };
+    Ok((path.with_extension(target), text))
+}
+
+fn usage(error: &str) -> ExitCode {
+    eprintln!("rat-fmt: {error}");
+    eprintln!("usage: rat-fmt [--check] [--to rat|ron] [--assets dir] <files..>");
+    ExitCode::FAILURE
+}
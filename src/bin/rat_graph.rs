@@ -0,0 +1,86 @@
+//! draws the dialogue scripts as a graph for narrative reviews
+//!
+//! usage: `cargo run --bin rat-graph -- [--mermaid] [--script pattern].. [--assets dir] [files..]`
+//!
+//! without files every script in `default.assets.ron` goes in. `--script`
+//! keeps the ones matching a `*` glob (`npc.*`), the graph goes to stdout:
+//! `cargo run --bin rat-graph | dot -Tsvg > dialogue.svg`
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use feverish::ratspinner::{
+    RatGraphFormat, RatScript, SPEAKERS_PATH, hook_matches, parse_script_file, read_scripts,
+    read_speakers, write_graph,
+};
+
+fn main() -> ExitCode {
+    let mut format = RatGraphFormat::Dot;
+    let mut patterns: Vec<String> = Vec::new();
+    let mut assets_root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let mut files: Vec<PathBuf> = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mermaid" => format = RatGraphFormat::Mermaid,
+            "--dot" => format = RatGraphFormat::Dot,
+            "--script" => match args.next() {
+                Some(pattern) => patterns.push(pattern),
+                None => return usage("`--script` takes a script id or pattern"),
+            },
+            "--assets" => match args.next() {
+                Some(dir) => assets_root = PathBuf::from(dir),
+                None => return usage("`--assets` takes a directory"),
+            },
+            _ if arg.starts_with("--") => return usage(&format!("unknown flag '{arg}'")),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let scripts = if files.is_empty() {
+        read_scripts(&assets_root)
+    } else {
+        read_files(&files, &assets_root)
+    };
+    let mut scripts = match scripts {
+        Ok(scripts) => scripts,
+        Err(error) => {
+            eprintln!("rat-graph: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if !patterns.is_empty() {
+        scripts.retain(|script| {
+            patterns
+                .iter()
+                .any(|pattern| hook_matches(pattern, &script.id))
+        });
+    }
+    if scripts.is_empty() {
+        eprintln!("rat-graph: no scripts to draw");
+        return ExitCode::FAILURE;
+    }
+
+    print!("{}", write_graph(&scripts, format));
+    ExitCode::SUCCESS
+}
+
+fn read_files(files: &[PathBuf], assets_root: &Path) -> Result<Vec<RatScript>, String> {
+    let speakers = read_speakers(&assets_root.join(SPEAKERS_PATH))?;
+    let mut scripts = Vec::new();
+    for path in files {
+        let bytes = std::fs::read(path)
+            .map_err(|error| format!("{}: failed to read: {error}", path.display()))?;
+        scripts.extend(parse_script_file(&bytes, path, &speakers)?);
+    }
+    Ok(scripts)
+}
+
+fn usage(error: &str) -> ExitCode {
+    eprintln!("rat-graph: {error}");
+    eprintln!("usage: rat-graph [--mermaid] [--script pattern].. [--assets dir] [files..]");
+    ExitCode::FAILURE
+}
@@ -0,0 +1,30 @@
+//! checks the dialogue scripts without booting the game
+//!
+//! usage: `cargo run --bin rat-lint [assets_dir]`
+
+use std::{path::PathBuf, process::ExitCode};
+
+use feverish::ratspinner::lint_assets;
+
+fn main() -> ExitCode {
+    let assets_root = std::env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets"));
+
+    let report = lint_assets(&assets_root);
+    for issue in &report.issues {
+        eprintln!("{issue}");
+    }
+    eprintln!(
+        "rat-lint: {} error(s), {} warning(s)",
+        report.errors(),
+        report.warnings()
+    );
+
+    if report.has_errors() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::{
+    ecs::{lifecycle::HookContext, world::DeferredWorld},
+    prelude::*,
+};
+use bevy_trenchbroom::prelude::*;
+use rand::RngExt;
+
+use crate::{
+    gameplay::PlayerRoot,
+    ratspinner::{RatCommand, RatStart},
+    ui::dialogue::UiDialogueState,
+};
+
+/// quiet time after any bark, so two speakers never cut each other off
+const BARK_GAP_SECS: f32 = 4.0;
+
+/// ambient one-liners said when the player walks by. npcs get these from their
+/// `bark_*` keys, `BarkPoint` puts them anywhere else
+#[derive(Component, Debug, Clone)]
+pub(crate) struct Barks {
+    script_id: String,
+    /// nodes to pick from, the script's entry when empty
+    nodes: Vec<String>,
+    radius: f32,
+    cooldown_secs: f32,
+    cooldown_left: f32,
+    last: Option<usize>,
+}
+
+impl Barks {
+    /// `nodes` is a comma separated list, as typed in trenchbroom
+    pub(crate) fn new(
+        script_id: impl Into<String>,
+        nodes: &str,
+        radius: f32,
+        cooldown_secs: f32,
+    ) -> Self {
+        Self {
+            script_id: script_id.into(),
+            nodes: nodes
+                .split(',')
+                .map(str::trim)
+                .filter(|node| !node.is_empty())
+                .map(str::to_string)
+                .collect(),
+            radius,
+            cooldown_secs,
+            cooldown_left: 0.0,
+            last: None,
+        }
+    }
+
+    /// a random node, never the same one twice in a row
+    fn next_start(&mut self, speaker: Entity) -> RatStart {
+        let start = RatStart::new(self.script_id.clone()).target(speaker);
+        let count = self.nodes.len();
+        if count == 0 {
+            return start;
+        }
+        let index = match self.last {
+            Some(last) if count > 1 => {
+                let index = rand::rng().random_range(0..count - 1);
+                if index >= last { index + 1 } else { index }
+            }
+            _ => rand::rng().random_range(0..count),
+        };
+        self.last = Some(index);
+        start.entry(self.nodes[index].clone())
+    }
+}
+
+/// a bark pool without a body, for voices behind doors or on the radio
+#[point_class(
+    group("sound"),
+    classname("bark"),
+    base(Transform),
+    iconsprite({ path: "sprites/audio_emitter.png", scale: 0.125 }),
+)]
+#[derive(Clone)]
+#[component(on_add=Self::on_add_hook)]
+pub struct BarkPoint {
+    #[class(must_set)]
+    pub(crate) script_id: String,
+    /// comma separated node ids, the script's entry when empty
+    pub(crate) nodes: String,
+    pub(crate) radius: f32,
+    pub(crate) cooldown: f32,
+}
+
+impl Default for BarkPoint {
+    fn default() -> Self {
+        Self {
+            script_id: Default::default(),
+            nodes: Default::default(),
+            radius: 4.0,
+            cooldown: 20.0,
+        }
+    }
+}
+
+impl BarkPoint {
+    fn on_add_hook(mut world: DeferredWorld, hook: HookContext) {
+        if world.is_scene_world() {
+            return;
+        }
+        let point = world.get::<Self>(hook.entity).unwrap();
+        let barks = Barks::new(
+            point.script_id.clone(),
+            &point.nodes,
+            point.radius,
+            point.cooldown,
+        );
+        world.commands().entity(hook.entity).insert(barks);
+    }
+}
+
+/// the closest speaker in range with its cooldown done says something. nobody
+/// barks over an open dialogue or right after one
+pub(crate) fn trigger_barks(
+    time: Res<Time>,
+    dialogue: Res<UiDialogueState>,
+    player: Query<&GlobalTransform, With<PlayerRoot>>,
+    mut speakers: Query<(Entity, &GlobalTransform, &mut Barks)>,
+    mut rat_commands: MessageWriter<RatCommand>,
+    mut gap_left: Local<f32>,
+) {
+    let delta = time.delta_secs();
+    for (_, _, mut barks) in &mut speakers {
+        barks.cooldown_left -= delta;
+    }
+    if dialogue.active {
+        *gap_left = BARK_GAP_SECS;
+        return;
+    }
+    *gap_left -= delta;
+    if *gap_left > 0.0 {
+        return;
+    }
+    let Ok(player) = player.single() else {
+        return;
+    };
+
+    let player_pos = player.translation();
+    let closest = speakers
+        .iter_mut()
+        .map(|(entity, transform, barks)| {
+            (
+                entity,
+                transform.translation().distance_squared(player_pos),
+                barks,
+            )
+        })
+        .filter(|(_, distance_sq, barks)| {
+            barks.cooldown_left <= 0.0 && *distance_sq <= barks.radius.powi(2)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    let Some((entity, _, mut barks)) = closest else {
+        return;
+    };
+
+    barks.cooldown_left = barks.cooldown_secs;
+    *gap_left = BARK_GAP_SECS;
+    rat_commands.write(RatCommand::Bark(barks.next_start(entity)));
+}
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use bevy_trenchbroom::prelude::*;
+
+use crate::{
+    gameplay::{PlayerRoot, npc::Npc},
+    ratspinner::{RatCommand, RatStart},
+    ui::dialogue::UiDialogueState,
+};
+
+/// starts a conversation between the npcs when the player comes close, each
+/// line said by the npc whose `speaker` the node names
+#[point_class(
+    group("npc"),
+    classname("conversation"),
+    base(Transform),
+    iconsprite({ path: "sprites/audio_emitter.png", scale: 0.125 }),
+)]
+#[derive(Clone)]
+pub struct ConversationPoint {
+    #[class(must_set)]
+    pub(crate) script_id: String,
+    pub(crate) radius: f32,
+    /// plays once per level, otherwise again after `cooldown`
+    pub(crate) once: bool,
+    pub(crate) cooldown: f32,
+    #[class(ignore)]
+    pub(crate) played: bool,
+    #[class(ignore)]
+    pub(crate) cooldown_left: f32,
+}
+
+impl Default for ConversationPoint {
+    fn default() -> Self {
+        Self {
+            script_id: Default::default(),
+            radius: 6.0,
+            once: true,
+            cooldown: 60.0,
+            played: false,
+            cooldown_left: 0.0,
+        }
+    }
+}
+
+pub(crate) fn trigger_conversations(
+    time: Res<Time>,
+    dialogue: Res<UiDialogueState>,
+    player: Query<&GlobalTransform, With<PlayerRoot>>,
+    mut points: Query<(Entity, &GlobalTransform, &mut ConversationPoint)>,
+    npcs: Query<(Entity, &Npc)>,
+    mut rat_commands: MessageWriter<RatCommand>,
+) {
+    for (_, _, mut point) in &mut points {
+        point.cooldown_left -= time.delta_secs();
+    }
+    if dialogue.active {
+        return;
+    }
+    let Ok(player) = player.single() else {
+        return;
+    };
+
+    for (entity, transform, mut point) in &mut points {
+        let ready = if point.once {
+            !point.played
+        } else {
+            point.cooldown_left <= 0.0
+        };
+        if !ready
+            || transform
+                .translation()
+                .distance_squared(player.translation())
+                > point.radius.powi(2)
+        {
+            continue;
+        }
+
+        // speakers.ron ids are one per npc, so every npc with one is cast
+        let mut start = RatStart::new(point.script_id.clone()).target(entity);
+        for (npc_entity, npc) in &npcs {
+            if let Some(speaker) = &npc.speaker {
+                start = start.cast(speaker.clone(), npc_entity);
+            }
+        }
+        point.played = true;
+        point.cooldown_left = point.cooldown;
+        rat_commands.write(RatCommand::Overhear(start));
+        // the runtime plays one conversation at a time
+        return;
+    }
+}
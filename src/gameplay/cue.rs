@@ -0,0 +1,165 @@
+use std::iter;
+
+use bevy::prelude::*;
+use bevy_seedling::sample::{PlaybackSettings, SamplePlayer};
+use bevy_trenchbroom::prelude::*;
+
+use crate::{
+    audio::mixer::WorldSfxPool,
+    gameplay::{
+        PlayerRoot,
+        npc::{Navigator, Npc},
+        props::AnimationControls,
+    },
+    ratspinner::RatCueTriggered,
+};
+
+/// same turn rate as walking
+const LOOK_TURN_SPEED: f32 = 3.0;
+/// roughly mouth height, so `sfx:` comes from the speaker and not their feet
+const CUE_SFX_HEIGHT: f32 = 1.5;
+
+/// an `anim:` clip in progress, idle comes back once it ran out
+#[derive(Component, Debug, Clone)]
+pub(crate) struct CueAnimation(String);
+
+/// keeps turning towards the entity until `look: none` or a walk starts
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) struct LookAt(Entity);
+
+/// acts out a node's `anim:`, `look:` and `sfx:` on whoever says it
+pub(crate) fn apply_rat_cues(
+    mut cmd: Commands,
+    mut cues: MessageReader<RatCueTriggered>,
+    assets: Res<AssetServer>,
+    speakers: Query<(), With<Transform>>,
+    player: Query<Entity, With<PlayerRoot>>,
+    targetables: Query<(Entity, &Targetable)>,
+    controls: Query<&AnimationControls>,
+    children: Query<&Children>,
+) {
+    for cue in cues.read() {
+        let Some(speaker) = cue.target.filter(|target| speakers.contains(*target)) else {
+            continue;
+        };
+
+        if let Some(clip) = &cue.cues.anim {
+            // `transition_to_animation_one_shot` panics on clips the model lacks
+            let has_clip = iter::once(speaker)
+                .chain(children.iter_descendants(speaker))
+                .filter_map(|child| controls.get(child).ok())
+                .any(|controls| controls.animations.contains_key(clip.as_str()));
+            if has_clip {
+                cmd.run_system_cached_with(
+                    Npc::transition_to_animation_one_shot,
+                    (speaker, clip.clone(), false),
+                );
+                cmd.entity(speaker).insert(CueAnimation(clip.clone()));
+            } else {
+                warn!(
+                    "{}:{} anim '{clip}' is not an animation of its speaker",
+                    cue.script_id, cue.node_id
+                );
+            }
+        }
+
+        if let Some(look) = &cue.cues.look {
+            let at = match look.as_str() {
+                "none" => None,
+                "player" => player.single().ok(),
+                name => {
+                    let found = targetables
+                        .iter()
+                        .find(|(_, targetable)| targetable.targetname.0 == name)
+                        .map(|(entity, _)| entity);
+                    if found.is_none() {
+                        warn!(
+                            "{}:{} look '{name}' matches no targetname",
+                            cue.script_id, cue.node_id
+                        );
+                    }
+                    found
+                }
+            };
+            match at {
+                Some(at) => cmd.entity(speaker).insert(LookAt(at)),
+                None => cmd.entity(speaker).remove::<LookAt>(),
+            };
+        }
+
+        if let Some(sfx) = &cue.cues.sfx {
+            cmd.entity(speaker).with_child((
+                Name::new("cue_sfx"),
+                SamplePlayer::new(assets.load(format!("audio/{sfx}.ogg"))),
+                PlaybackSettings::default().despawn(),
+                WorldSfxPool,
+                Transform::from_translation(Vec3::Y * CUE_SFX_HEIGHT),
+            ));
+        }
+    }
+}
+
+/// back to idle once an `anim:` clip is done, unless a walk took over
+pub(crate) fn finish_cue_animations(
+    mut cmd: Commands,
+    npcs: Query<(Entity, &Npc, &Navigator, &CueAnimation)>,
+    animators: Query<(&AnimationControls, &AnimationPlayer)>,
+    children: Query<&Children>,
+) {
+    for (entity, npc, navigator, cue) in &npcs {
+        let finished = iter::once(entity)
+            .chain(children.iter_descendants(entity))
+            .filter_map(|child| animators.get(child).ok())
+            .all(|(controls, animator)| {
+                controls
+                    .animations
+                    .get(cue.0.as_str())
+                    .and_then(|node| animator.animation(*node))
+                    .is_none_or(|active| active.is_finished())
+            });
+        if !finished {
+            continue;
+        }
+        cmd.entity(entity).remove::<CueAnimation>();
+        if navigator.queue.is_empty()
+            && let Some(idle) = &npc.idle_animation
+        {
+            cmd.run_system_cached_with(
+                Npc::transition_to_animation_one_shot,
+                (entity, idle.clone(), true),
+            );
+        }
+    }
+}
+
+pub(crate) fn turn_to_look_targets(
+    mut cmd: Commands,
+    time: Res<Time>,
+    mut lookers: Query<(
+        Entity,
+        &LookAt,
+        Option<&Navigator>,
+        &mut Transform,
+        &GlobalTransform,
+    )>,
+    targets: Query<&GlobalTransform>,
+) {
+    for (entity, look, navigator, mut transform, global_transform) in &mut lookers {
+        let walking = navigator.is_some_and(|navigator| !navigator.queue.is_empty());
+        let target = targets.get(look.0).ok().filter(|_| !walking);
+        let Some(target) = target else {
+            cmd.entity(entity).remove::<LookAt>();
+            continue;
+        };
+        // only turn around the up axis, nobody tilts their whole body
+        let mut at = target.translation();
+        at.y = global_transform.translation().y;
+        if at.distance_squared(global_transform.translation()) < 0.01 {
+            continue;
+        }
+        let facing = global_transform.compute_transform().looking_at(at, Vec3::Y);
+        transform.rotation = transform
+            .rotation
+            .slerp(facing.rotation, LOOK_TURN_SPEED * time.delta_secs());
+    }
+}
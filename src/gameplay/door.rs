@@ -120,13 +120,7 @@ impl DoorRotatingBase {
         // Send signals
         if signal_counter.0 == 0 || maybe_signal.hook_repeat {
             if let Some(ref signal) = maybe_signal.hook {
-                signal_messages.write(RatHookTriggered {
-                    hook: signal.clone(),
-                    script_id: Default::default(),
-                    node_id: Default::default(),
-                    option_id: None,
-                    target: None,
-                });
+                signal_messages.write(RatHookTriggered::new(signal));
             }
             signal_counter.0 += 1;
         }
@@ -254,30 +248,21 @@ impl EndDoor {
     }
 }
 
-pub(crate) fn open_doors_on_hooks(
-    mut hooks: MessageReader<RatHookTriggered>,
+/// `door.open(targetname)` opens the named door if it is closed
+pub(crate) fn open_door_on_hook(
+    In(hook): In<RatHookTriggered>,
     mut cmd: Commands,
     doors: Query<(Entity, &Targetable, &DoorBase), With<DoorBase>>,
 ) {
-    let door_with_name = |name: String| -> Option<Entity> {
-        doors
-            .iter()
-            .find(|(_, t, door)| t.targetname.0 == name && !door.open)
-            .map(|x| x.0)
+    let Some(name) = hook.arg(0) else {
+        warn!("door.open hook needs a door targetname");
+        return;
     };
-
-    for hook in hooks.read() {
-        match hook.hook.as_str() {
-            "game.bathroom_door.open" => {
-                if let Some(door) = door_with_name("bathroom_door".into()) {
-                    cmd.entity(door).trigger(Use);
-                }
-            }
-            "game.front_door.open" =>
-                if let Some(door) = door_with_name("front_door".into()) {
-                    cmd.entity(door).trigger(Use);
-                },
-            _ => (),
-        }
+    let door = doors
+        .iter()
+        .find(|(_, t, door)| t.targetname.0 == name && !door.open)
+        .map(|x| x.0);
+    if let Some(door) = door {
+        cmd.entity(door).trigger(Use);
     }
 }
@@ -1,3 +1,6 @@
+mod bark;
+mod conversation;
+mod cue;
 mod door;
 mod focus_fx;
 mod inventory;
@@ -37,7 +40,7 @@ use crate::{
     input::{Use, UseRaycaster},
     map::{LevelToPrepare, PendingLevelTransition},
     psx::{PsxCamera, PsxConfig},
-    ratspinner::RatHookTriggered,
+    ratspinner::{RatHookAppExt, RatHookTriggered, RatVariables},
     ui::{
         DiscoveryEntry, EndingUiRoot, SpawnDroppedItem, UiDiscoveryCommand, UiDiscoveryDb,
         UiDiscoveryDbSnapshot, UiEndingCommandsExt, UiEndingPayload, UiHintCommand, UiHintRequest,
@@ -55,6 +58,12 @@ impl Plugin for GameplayPlugin {
             .init_resource::<EliminationCount>()
             .init_resource::<ObjectiveHintRuntime>()
             .add_message::<SpawnDroppedItem>()
+            .add_rat_hook("game.start", start_main_phase_on_hook)
+            .add_rat_hook("game.kill", handle_debug_elimination)
+            .add_rat_hook("game.kill", npc::kill_npc_on_hook)
+            .add_rat_hook("game.lure", npc::lure_npc_on_hook)
+            .add_rat_hook("game.spare", npc::spare_npc_on_hook)
+            .add_rat_hook("door.open", door::open_door_on_hook)
             .add_systems(OnEnter(GameState::Main), setup_endings)
             .add_systems(
                 Update,
@@ -64,15 +73,21 @@ impl Plugin for GameplayPlugin {
                     (sound::detect_footstep_surface, sound::handle_footsteps).chain(),
                     door::rotate_doors,
                     focus_fx::handle_focus_effect,
-                    handle_debug_elimination,
-                    handle_world_messages,
+                    bark::trigger_barks,
+                    conversation::trigger_conversations,
+                    (
+                        cue::apply_rat_cues,
+                        cue::finish_cue_animations,
+                        cue::turn_to_look_targets,
+                    )
+                        .chain(),
                     handle_game_phases,
-                    door::open_doors_on_hooks,
-                    npc::handle_elimination_triggers,
                     npc::tick_walkback_timers,
                     npc::npc_navigation,
+                    npc::interrupt_distant_dialogue,
                     npc::build_nav_paths,
                     npc::handle_despawn_timers,
+                    npc::move_npc_mouths,
                     spawn_dropped_item,
                     drive_objective_hints,
                     reset_game_on_ending,
@@ -318,7 +333,7 @@ pub(crate) struct EliminationCount(usize);
 pub(crate) struct HookCounter(usize);
 
 fn handle_debug_elimination(
-    mut hooks: MessageReader<RatHookTriggered>,
+    In(event): In<RatHookTriggered>,
     mut cmd: Commands,
     mut count: ResMut<EliminationCount>,
     mut discovery_db: ResMut<UiDiscoveryDb>,
@@ -328,73 +343,69 @@ fn handle_debug_elimination(
     npcs: Query<(&Npc, &GlobalTransform)>,
     mut targetable_doors: Query<(Entity, &Targetable, &mut DoorBase)>,
 ) {
-    for event in hooks.read() {
-        if event.hook == "game.kill" {
-            let office_key_meta = assets
-                .get_handle("items/office_key.item.meta")
-                .and_then(|handle| items.get(&handle))
-                .unwrap();
-            let apartment_key_meta = assets
-                .get_handle("items/apartment_key.item.meta")
-                .and_then(|handle| items.get(&handle))
-                .unwrap();
-            if let Some(target) = event.target {
-                info!("eliminating npc: {:?}", target);
-                if let Ok((npc, transform)) = npcs.get(target)
-                    && let Some(marcus) = marcus_type_for_npc(npc)
-                {
-                    sky_commands.write(sky::SkyCommand::ActivateConstellation(marcus));
-
-                    // Entity has `Suspect` value in `Npc` component which can be
-                    // queried for UI/gameplay updates
-
-                    // Give the player a key (or play win/lose state), based on how many kills have
-                    // progressed
-                    count.0 += 1;
-
-                    const KEY_PATH: &str = "models/key/key.gltf#Scene0";
-                    const OFFICE_DOOR_ID: &str = "office_door";
-                    const APARTMENT_DOOR_ID: &str = "apartment_door";
-
-                    match count.0 {
-                        1 => {
-                            cmd.run_system_cached_with(
-                                spawn_key,
-                                KeySettings {
-                                    translation: transform.translation(),
-                                    metadata: "items/office_key.item.meta".into(),
-                                    door: OFFICE_DOOR_ID.into(),
-                                },
-                            );
-                        }
-                        2 => {
-                            cmd.run_system_cached_with(
-                                spawn_key,
-                                KeySettings {
-                                    translation: transform.translation(),
-                                    metadata: "items/apartment_key.item.meta".into(),
-                                    door: APARTMENT_DOOR_ID.into(),
-                                },
-                            );
-                        }
-                        3 => {
-                            // Play the game win/lose scenario
-                            if npcs
-                                .iter()
-                                .any(|(npc, _)| matches!(npc.suspect, Some(SuspectType::Human)))
-                            {
-                                // win
-                                dbg!("you guessed correctly! you win!");
-                                cmd.set_state(Phase::Win);
-                            } else {
-                                // lose
-                                dbg!("you guessed incorrectly :(");
-                                cmd.set_state(Phase::Lose);
-                            }
-                        }
-                        _ => {}
+    let office_key_meta = assets
+        .get_handle("items/office_key.item.meta")
+        .and_then(|handle| items.get(&handle))
+        .unwrap();
+    let apartment_key_meta = assets
+        .get_handle("items/apartment_key.item.meta")
+        .and_then(|handle| items.get(&handle))
+        .unwrap();
+    if let Some(target) = event.target {
+        info!("eliminating npc: {:?}", target);
+        if let Ok((npc, transform)) = npcs.get(target)
+            && let Some(marcus) = marcus_type_for_npc(npc)
+        {
+            sky_commands.write(sky::SkyCommand::ActivateConstellation(marcus));
+
+            // Entity has `Suspect` value in `Npc` component which can be
+            // queried for UI/gameplay updates
+
+            // Give the player a key (or play win/lose state), based on how many kills have
+            // progressed
+            count.0 += 1;
+
+            const KEY_PATH: &str = "models/key/key.gltf#Scene0";
+            const OFFICE_DOOR_ID: &str = "office_door";
+            const APARTMENT_DOOR_ID: &str = "apartment_door";
+
+            match count.0 {
+                1 => {
+                    cmd.run_system_cached_with(
+                        spawn_key,
+                        KeySettings {
+                            translation: transform.translation(),
+                            metadata: "items/office_key.item.meta".into(),
+                            door: OFFICE_DOOR_ID.into(),
+                        },
+                    );
+                }
+                2 => {
+                    cmd.run_system_cached_with(
+                        spawn_key,
+                        KeySettings {
+                            translation: transform.translation(),
+                            metadata: "items/apartment_key.item.meta".into(),
+                            door: APARTMENT_DOOR_ID.into(),
+                        },
+                    );
+                }
+                3 => {
+                    // Play the game win/lose scenario
+                    if npcs
+                        .iter()
+                        .any(|(npc, _)| matches!(npc.suspect, Some(SuspectType::Human)))
+                    {
+                        // win
+                        dbg!("you guessed correctly! you win!");
+                        cmd.set_state(Phase::Win);
+                    } else {
+                        // lose
+                        dbg!("you guessed incorrectly :(");
+                        cmd.set_state(Phase::Lose);
                     }
                 }
+                _ => {}
             }
         }
     }
@@ -511,12 +522,8 @@ fn marcus_type_for_npc(npc: &Npc) -> Option<sky::MarcusType> {
     }
 }
 
-fn handle_world_messages(mut hooks: MessageReader<RatHookTriggered>, mut cmd: Commands) {
-    for event in hooks.read() {
-        if event.hook == "game.start" {
-            cmd.set_state(Phase::Main);
-        }
-    }
+fn start_main_phase_on_hook(_: In<RatHookTriggered>, mut cmd: Commands) {
+    cmd.set_state(Phase::Main);
 }
 
 const HINT_VISIBLE_SECS: f32 = 15.0;
@@ -832,11 +839,13 @@ fn reset_game_on_ending(
     mut hints_runtime: ResMut<ObjectiveHintRuntime>,
     mut level_to_prepare: ResMut<LevelToPrepare>,
     mut pending_transition: ResMut<PendingLevelTransition>,
+    mut rat_variables: ResMut<RatVariables>,
     mut cmd: Commands,
     player_root: Single<Entity, With<PlayerRoot>>,
 ) {
     for _event in reader.read() {
         elims.0 = 0;
+        rat_variables.clear();
         hints_runtime.leave_building.stop();
         hints_runtime.pickup_key.stop();
         level_to_prepare.level = None;
@@ -1,9 +1,12 @@
 use std::{collections::VecDeque, iter, time::Duration};
 
-use avian3d::prelude::{ColliderConstructor, CollisionLayers, RigidBody};
+use avian3d::prelude::{
+    ColliderConstructor, CollisionLayers, RigidBody, SpatialQuery, SpatialQueryFilter,
+};
 use bevy::{
     asset::AssetPath,
     ecs::{lifecycle::HookContext, world::DeferredWorld},
+    mesh::morph::MorphWeights,
     platform::collections::HashMap,
     prelude::*,
     scene::SceneInstanceReady,
@@ -11,10 +14,14 @@ use bevy::{
 use bevy_trenchbroom::prelude::*;
 
 use crate::{
-    gameplay::{ColliderHierarchyChildOf, PhysLayer, props::AnimationControls},
+    assets::GameAssets,
+    gameplay::{
+        ColliderHierarchyChildOf, PhysLayer, PlayerRoot, bark::Barks, props::AnimationControls,
+    },
     input::Use,
-    ratspinner::{RatCommand, RatHookTriggered, RatStart},
+    ratspinner::{RatCommand, RatDialogueState, RatHookTriggered, RatSpeakers, RatStart},
     ui::dialogue::UiDialogueState,
+    voice::VoicePhoneme,
 };
 
 #[point_class(base(Transform, Visibility, Target),  model({path: model}))]
@@ -27,12 +34,24 @@ pub(crate) struct Npc {
     pub(crate) idle_animation: Option<String>,
     /// Marks the NPC as one that must be eliminated or saved
     pub(crate) suspect: Option<SuspectType>,
+    /// speakers.ron id, supplies the script when `script_id` is unset
+    pub(crate) speaker: Option<String>,
     pub(crate) script_id: Option<String>,
     pub(crate) starting_walk_node: Option<String>,
     pub(crate) start_walk_on_spawn: bool,
     pub(crate) walk_loop: bool,
     pub(crate) no_collider: bool,
     pub(crate) speed: f32,
+    /// script of one-liners said when the player walks by
+    pub(crate) bark_script: Option<String>,
+    /// comma separated bark nodes, the bark script's entry when unset
+    pub(crate) bark_nodes: Option<String>,
+    pub(crate) bark_radius: f32,
+    pub(crate) bark_cooldown: f32,
+    /// the dialogue breaks off once the player is further away, 0 never
+    pub(crate) talk_range: f32,
+    /// the dialogue also breaks off when a wall comes between them
+    pub(crate) talk_line_of_sight: bool,
     #[class(ignore)]
     pub(crate) default_script_id: Option<String>,
 }
@@ -62,6 +81,7 @@ impl Default for Npc {
             model: Default::default(),
             idle_animation: None,
             suspect: None,
+            speaker: None,
             script_id: None,
             starting_walk_node: None,
             default_script_id: None,
@@ -69,6 +89,12 @@ impl Default for Npc {
             walk_loop: false,
             no_collider: false,
             speed: 1f32,
+            bark_script: None,
+            bark_nodes: None,
+            bark_radius: 4.0,
+            bark_cooldown: 20.0,
+            talk_range: 6.0,
+            talk_line_of_sight: true,
         }
     }
 }
@@ -80,10 +106,25 @@ impl Npc {
         if world.is_scene_world() {
             return;
         }
+        let speaker_script = world
+            .get::<Self>(hook.entity)
+            .and_then(|npc| npc.speaker.clone())
+            .and_then(|speaker| Self::speaker_script(&world, &speaker));
         let mut npc = world.get_mut::<Self>(hook.entity).unwrap();
         let npc_no_collider = npc.no_collider;
+        if npc.script_id.is_none() {
+            npc.script_id = speaker_script;
+        }
         npc.default_script_id = npc.script_id.clone();
         let npc = world.get::<Self>(hook.entity).unwrap();
+        let barks = npc.bark_script.as_ref().map(|script_id| {
+            Barks::new(
+                script_id.clone(),
+                npc.bark_nodes.as_deref().unwrap_or_default(),
+                npc.bark_radius,
+                npc.bark_cooldown,
+            )
+        });
         let asset_path = AssetPath::from(&npc.model);
         let assets = world.resource::<AssetServer>();
         let scene_asset_path = AssetPath::from(asset_path.to_string() + "#Scene0");
@@ -98,6 +139,9 @@ impl Npc {
             .commands()
             .entity(hook.entity)
             .insert(SceneRoot(scene_handle.clone()));
+        if let Some(barks) = barks {
+            world.commands().entity(hook.entity).insert(barks);
+        }
         if !npc_no_collider {
             world.commands().entity(hook.entity).with_children(|cmd| {
                 // Spawn the NPC collider in the center, since the npc models origins are at the
@@ -124,6 +168,16 @@ impl Npc {
             .observe(Self::on_arrived_destination);
     }
 
+    fn speaker_script(world: &World, speaker: &str) -> Option<String> {
+        let handle = &world.get_resource::<GameAssets>()?.rat_speakers;
+        let speakers = world.resource::<Assets<RatSpeakers>>().get(handle)?;
+        let Some(speaker_def) = speakers.get(speaker) else {
+            warn!("npc speaker '{speaker}' is not in speakers.ron");
+            return None;
+        };
+        speaker_def.script.clone()
+    }
+
     pub(crate) fn setup_animations(
         scene_ready: On<SceneInstanceReady>,
         mut cmd: Commands,
@@ -302,6 +356,109 @@ pub(crate) fn tick_walkback_timers(
     }
 }
 
+/// what `interrupt_distant_dialogue` keeps about the npc being talked to
+#[derive(Default)]
+pub(crate) struct TalkWatch {
+    target: Option<Entity>,
+    walking: bool,
+    /// the walk was under way when the talk started or one of its hooks sent
+    /// them off, it doesn't break the talk
+    own_walk: bool,
+    /// frames left in which a walk that starts counts as the dialogue's own
+    hooked_frames: u8,
+    out_of_reach_secs: f32,
+    interrupted: bool,
+}
+
+/// breaks the open dialogue off once, when its npc sets off on a walk of its
+/// own, or the player stays out of `talk_range` or out of sight for a moment
+pub(crate) fn interrupt_distant_dialogue(
+    time: Res<Time>,
+    dialogue: Res<RatDialogueState>,
+    npcs: Query<(&Npc, &Navigator, &GlobalTransform)>,
+    player: Query<(Entity, &GlobalTransform), With<PlayerRoot>>,
+    spatial: SpatialQuery,
+    mut hooks: MessageReader<RatHookTriggered>,
+    mut rat_commands: MessageWriter<RatCommand>,
+    mut watch: Local<TalkWatch>,
+) {
+    /// a door swinging by or a step back shouldn't end the talk
+    const GRACE_SECS: f32 = 0.5;
+    /// rays go to the middle of the npc, its origin is at the feet
+    const CHEST_HEIGHT: f32 = 1.0;
+    /// hook handlers run a frame or so after the hook goes out
+    const HOOK_FRAMES: u8 = 3;
+
+    let target = dialogue.target.filter(|_| dialogue.active);
+    let hooked = hooks
+        .read()
+        .filter(|hook| target.is_some() && hook.target == target)
+        .count()
+        > 0;
+    let npc = target.and_then(|target| npcs.get(target).ok());
+    let (Some((npc, navigator, npc_transform)), Ok((player, player_transform))) =
+        (npc, player.single())
+    else {
+        *watch = TalkWatch::default();
+        return;
+    };
+    let walking = !navigator.queue.is_empty();
+    if watch.target != target {
+        *watch = TalkWatch {
+            target,
+            walking,
+            own_walk: walking,
+            ..default()
+        };
+    }
+    if watch.interrupted {
+        return;
+    }
+    if hooked {
+        watch.hooked_frames = HOOK_FRAMES;
+    }
+    if walking && !watch.walking {
+        watch.own_walk = watch.hooked_frames > 0;
+    }
+    watch.walking = walking;
+    watch.hooked_frames = watch.hooked_frames.saturating_sub(1);
+    if walking {
+        watch.out_of_reach_secs = 0.0;
+        if !watch.own_walk {
+            watch.interrupted = true;
+            rat_commands.write(RatCommand::Interrupt);
+        }
+        return;
+    }
+
+    let from = player_transform.translation();
+    let to = npc_transform.translation() + Vec3::Y * CHEST_HEIGHT;
+    let distance = from.distance(to);
+    let too_far = npc.talk_range > 0.0 && distance > npc.talk_range;
+    let hidden = npc.talk_line_of_sight
+        && Dir3::new(to - from).is_ok_and(|direction| {
+            spatial
+                .cast_ray(
+                    from,
+                    direction,
+                    distance,
+                    true,
+                    &SpatialQueryFilter::from_mask(PhysLayer::Default)
+                        .with_excluded_entities([player]),
+                )
+                .is_some()
+        });
+    if !too_far && !hidden {
+        watch.out_of_reach_secs = 0.0;
+        return;
+    }
+    watch.out_of_reach_secs += time.delta_secs();
+    if watch.out_of_reach_secs >= GRACE_SECS {
+        watch.interrupted = true;
+        rat_commands.write(RatCommand::Interrupt);
+    }
+}
+
 // this is somewhat cursed
 pub(crate) fn npc_navigation(
     mut cmd: Commands,
@@ -343,13 +500,7 @@ pub(crate) fn npc_navigation(
                 <= CHECKPOINT_RADIUS.powi(2)
             {
                 if let Some(ref hook) = walk_target.hook {
-                    hooks.write(RatHookTriggered {
-                        hook: hook.clone(),
-                        script_id: Default::default(),
-                        node_id: Default::default(),
-                        option_id: None,
-                        target: None,
-                    });
+                    hooks.write(RatHookTriggered::new(hook));
                 }
                 nav.queue.pop();
                 if nav.queue.is_empty() {
@@ -400,55 +551,92 @@ pub(crate) fn build_nav_paths(
     }
 }
 
-pub(crate) fn handle_elimination_triggers(
-    mut hooks: MessageReader<RatHookTriggered>,
+pub(crate) fn lure_npc_on_hook(
+    In(event): In<RatHookTriggered>,
     mut cmd: Commands,
     mut npcs: Query<(&mut Npc, &mut Navigator), Without<DespawnTimer>>,
     mut lure_timers: Query<&mut WalkbackTimer>,
 ) {
-    for event in hooks.read() {
-        match event.hook.as_str() {
-            "game.lure" => {
-                // Immediately end other lure timers
-                // idk why this isnt working lulz
-                for mut timer in lure_timers.iter_mut() {
-                    timer.0.finish();
-                }
+    // Immediately end other lure timers
+    // idk why this isnt working lulz
+    for mut timer in lure_timers.iter_mut() {
+        timer.0.finish();
+    }
 
-                let npc_entity = event.target.unwrap();
-                if let Ok((_npc, mut navigator)) = npcs.get_mut(npc_entity) {
-                    // Begin walk animation
-                    navigator.queue = navigator.path.iter().rev().copied().collect::<Vec<_>>();
-                    cmd.entity(npc_entity).trigger(StartedWalk);
-                }
-            }
-            "game.kill" => {
-                // TODO: play animations.
-                let npc_entity = event.target.unwrap();
-                // We don't want already despawning NPCs
-                if let Ok((mut npc, _)) = npcs.get_mut(npc_entity) {
-                    npc.script_id = None;
-                    cmd.run_system_cached_with(
-                        Npc::transition_to_animation_one_shot,
-                        (npc_entity, "death", false),
-                    );
-                    cmd.entity(npc_entity).insert(DespawnTimer(Timer::new(
-                        Duration::from_secs_f32(1.0),
-                        TimerMode::Once,
-                    )));
-                }
-            }
-            "game.spare" => {
-                let npc_entity = event.target.unwrap();
-                if let Ok((_npc, mut navigator)) = npcs.get_mut(npc_entity) {
-                    // Begin walk animation
-                    navigator.queue = navigator.path.iter().copied().collect::<Vec<_>>();
-                    cmd.entity(npc_entity).trigger(StartedWalk);
-                }
-            }
-            _ => (),
+    let npc_entity = event.target.unwrap();
+    if let Ok((_npc, mut navigator)) = npcs.get_mut(npc_entity) {
+        // Begin walk animation
+        navigator.queue = navigator.path.iter().rev().copied().collect::<Vec<_>>();
+        cmd.entity(npc_entity).trigger(StartedWalk);
+    }
+}
+
+pub(crate) fn kill_npc_on_hook(
+    In(event): In<RatHookTriggered>,
+    mut cmd: Commands,
+    mut npcs: Query<&mut Npc, Without<DespawnTimer>>,
+) {
+    // TODO: play animations.
+    let npc_entity = event.target.unwrap();
+    // We don't want already despawning NPCs
+    if let Ok(mut npc) = npcs.get_mut(npc_entity) {
+        npc.script_id = None;
+        cmd.run_system_cached_with(
+            Npc::transition_to_animation_one_shot,
+            (npc_entity, "death", false),
+        );
+        cmd.entity(npc_entity).insert(DespawnTimer(Timer::new(
+            Duration::from_secs_f32(1.0),
+            TimerMode::Once,
+        )));
+    }
+}
+
+pub(crate) fn spare_npc_on_hook(
+    In(event): In<RatHookTriggered>,
+    mut cmd: Commands,
+    mut npcs: Query<&mut Navigator, (With<Npc>, Without<DespawnTimer>)>,
+) {
+    let npc_entity = event.target.unwrap();
+    if let Ok(mut navigator) = npcs.get_mut(npc_entity) {
+        // Begin walk animation
+        navigator.queue = navigator.path.iter().copied().collect::<Vec<_>>();
+        cmd.entity(npc_entity).trigger(StartedWalk);
+    }
+}
+
+/// drives a talking npc's first blend shape with the phonemes of their line.
+/// models without one just keep their mouth shut
+pub(crate) fn move_npc_mouths(
+    time: Res<Time>,
+    mut phonemes: MessageReader<VoicePhoneme>,
+    mut mouths: Local<HashMap<Entity, f32>>,
+    npcs: Query<(), With<Npc>>,
+    children: Query<&Children>,
+    mut weights: Query<&mut MorphWeights>,
+) {
+    for phoneme in phonemes.read() {
+        if let Some(npc) = phoneme.target.filter(|target| npcs.contains(*target)) {
+            mouths.insert(npc, phoneme.openness);
         }
     }
+    // closes by itself between phonemes and after a line is cut off
+    let keep = (-time.delta_secs() * 8.0).exp();
+    mouths.retain(|npc, openness| {
+        if !npcs.contains(*npc) {
+            return false;
+        }
+        *openness *= keep;
+        let open = *openness > 0.01;
+        for child in children.iter_descendants(*npc) {
+            if let Ok(mut weights) = weights.get_mut(child)
+                && let Some(jaw) = weights.weights_mut().first_mut()
+            {
+                *jaw = if open { *openness } else { 0.0 };
+            }
+        }
+        open
+    });
 }
 
 pub(crate) fn handle_despawn_timers(
@@ -1,12 +1,25 @@
-//! minimal yarn-like dialogue runtime with asset-based .rat/.ron loading
+//! minimal yarn-like dialogue runtime with asset-based .rat/.ron/.yarn loading
+//!
+//! a script is nodes with a line, options and `->` targets, which can jump to
+//! or call into other scripts. on top of that sit variables, guards and
+//! effects (`vars`), inline `{pause=..}`/`{glitch}` markup (`markup`),
+//! `{placeholders}` filled by resolvers, generated options
+//! (`add_rat_options`), hooks with arguments (`add_rat_hook`) and speakers
+//! from `ratspinner/speakers.ron`. `RatCommand` opens a dialogue with ui,
+//! barks a line or plays a script between npcs to overhear. read lines and
+//! picked options are kept in `RatSeen`, one history per install.
+//!
+//! `rat-lint`, `rat-fmt` and `rat-graph` check, format and draw scripts,
+//! `RatHarness` and `tests/rat/*.rat.test` play them headless in tests.
 //!
 //! quick start:
 //! `commands.write_message(ratspinner::RatCommand::Start(ratspinner::RatStart::new("npc.default").target(npc)));`
 //!
 //! code-first script with chainable hooks/options:
 //! ```no_run
-//! # use feverish::ratspinner::{RatScriptBuilder, RatNodeBuilder, RatOptionBuilder, RatCommand};
-//! # let mut commands = todo!();
+//! # use bevy::prelude::*;
+//! # use feverish::ratspinner::{RatCommand, RatNodeBuilder, RatOptionBuilder, RatScriptBuilder};
+//! # fn register(mut commands: Commands) {
 //! commands.write_message(RatCommand::Register(
 //!     RatScriptBuilder::new("demo")
 //!         .entry("start")
@@ -22,20 +35,46 @@
 //!         .node(RatNodeBuilder::new("right").speaker("mr. d.").text("right chosen."))
 //!         .build(),
 //! ));
+//! # }
+//! ```
+//!
 //! i should have used yarnspinner
 //! i probably have mental issues
-//! ```
 
+mod graph;
+mod harness;
+mod hooks;
+mod interpolate;
+mod lint;
+mod markup;
+mod printer;
+mod providers;
 mod runtime;
+mod seen;
+mod speakers;
 mod types;
+mod vars;
+mod yarn;
 
 use bevy::prelude::*;
-pub use runtime::RatDialogueState;
-#[allow(unused_imports)]
+use bevy_common_assets::ron::RonAssetPlugin;
+pub use graph::{RatGraphFormat, write_graph};
+pub use harness::{RatHarness, RatScriptTest};
+pub use hooks::hook_matches;
+pub(crate) use hooks::{RatHookAppExt, RatHookRegistry};
+pub(crate) use interpolate::{RatTextAppExt, RatTextResolvers};
+pub use lint::{lint_assets, lint_script, read_scripts, read_speakers};
+pub(crate) use markup::{RatGlyph, RatMarkup};
+pub use printer::{parse_script_file, write_rat, write_ron};
+pub(crate) use providers::{RatOptionProviders, RatOptionsAppExt};
+pub(crate) use runtime::RatDialogueState;
+pub(crate) use seen::RatSeenSavePlugin;
+pub use speakers::{RatSpeakers, SPEAKERS_PATH};
 pub use types::{
-    RatCommand, RatCommandsExt, RatDialoguePresentation, RatHookTriggered, RatNodeBuilder,
-    RatOptionBuilder, RatScript, RatScriptAsset, RatScriptBuilder, RatStart,
+    RatCommand, RatNodeBuilder, RatOptionBuilder, RatScript, RatScriptBuilder, RatStart,
 };
+pub(crate) use types::{RatCueTriggered, RatHookTriggered, RatNodeEntered, RatScriptAsset};
+pub(crate) use vars::RatVariables;
 
 use crate::AppState;
 
@@ -46,10 +85,21 @@ impl Plugin for RatSpinnerPlugin {
         app.init_resource::<runtime::RatLibrary>()
             .init_resource::<runtime::RatRuntime>()
             .init_resource::<runtime::RatDialogueState>()
+            .init_resource::<RatVariables>()
+            .init_resource::<RatHookRegistry>()
+            .init_resource::<RatOptionProviders>()
+            .add_rat_options("inventory", providers::inventory_options)
+            .add_rat_options("inventory.items", providers::inventory_item_options)
+            .init_resource::<RatTextResolvers>()
+            .add_rat_resolver("item", interpolate::item_text)
+            .add_rat_resolver("speaker", interpolate::speaker_text)
             .init_asset::<types::RatScriptAsset>()
             .init_asset_loader::<runtime::RatScriptAssetLoader>()
+            .add_plugins(RonAssetPlugin::<RatSpeakers>::new(&["speakers.ron"]))
             .add_message::<RatCommand>()
             .add_message::<RatHookTriggered>()
+            .add_message::<RatNodeEntered>()
+            .add_message::<RatCueTriggered>()
             .add_systems(
                 OnEnter(AppState::Main),
                 (
@@ -57,6 +107,16 @@ impl Plugin for RatSpinnerPlugin {
                     runtime::seed_builtin_script.after(runtime::load_scripts_from_assets),
                 ),
             )
-            .add_systems(Update, runtime::handle_rat_commands);
+            .add_systems(
+                Update,
+                (
+                    runtime::reload_modified_scripts,
+                    runtime::tick_dialogue_timeout,
+                    runtime::tick_conversation,
+                    runtime::handle_rat_commands,
+                    hooks::dispatch_rat_hooks,
+                )
+                    .chain(),
+            );
     }
 }
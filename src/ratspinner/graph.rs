@@ -0,0 +1,595 @@
+use std::collections::HashMap;
+
+use super::{
+    markup::RatMarkup,
+    types::{RatNode, RatNodeRef, RatScript, RatTarget, parse_hook_call},
+    vars::RatCondition,
+};
+
+/// longest node text / option text shown before it's cut off
+const LABEL_CHARS: usize = 40;
+
+const KILL_COLOR: &str = "#c0392b";
+const SPARE_COLOR: &str = "#27ae60";
+const HOOK_COLOR: &str = "#d35400";
+const MISSING_COLOR: &str = "#8e44ad";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RatGraphFormat {
+    /// graphviz, `dot -Tsvg`
+    #[default]
+    Dot,
+    /// renders inline in markdown on github/gitlab
+    Mermaid,
+}
+
+impl RatGraphFormat {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw {
+            "dot" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            _ => Err(format!(
+                "unknown graph format '{raw}', expected dot or mermaid"
+            )),
+        }
+    }
+}
+
+/// one graph for all of `scripts`, a box per script. nodes show speaker and
+/// the start of their text, edges the option text. anything firing
+/// `game.kill*` is red, `game.spare*` green, other hooks orange. `-> @other`
+/// targets outside `scripts` and targets that don't exist get their own node
+pub fn write_graph(scripts: &[RatScript], format: RatGraphFormat) -> String {
+    let graph = Graph::build(scripts);
+    match format {
+        RatGraphFormat::Dot => graph.dot(),
+        RatGraphFormat::Mermaid => graph.mermaid(),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tone {
+    Kill,
+    Spare,
+    Hook,
+}
+
+impl Tone {
+    fn of(hooks: &[String]) -> Option<Self> {
+        let names: Vec<String> = hooks
+            .iter()
+            .map(|hook| parse_hook_call(hook).map_or_else(|_| hook.clone(), |(name, _)| name))
+            .collect();
+        if names.iter().any(|name| name.starts_with("game.kill")) {
+            Some(Self::Kill)
+        } else if names.iter().any(|name| name.starts_with("game.spare")) {
+            Some(Self::Spare)
+        } else if names.is_empty() {
+            None
+        } else {
+            Some(Self::Hook)
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Self::Kill => KILL_COLOR,
+            Self::Spare => SPARE_COLOR,
+            Self::Hook => HOOK_COLOR,
+        }
+    }
+
+    fn class(self) -> &'static str {
+        match self {
+            Self::Kill => "kill",
+            Self::Spare => "spare",
+            Self::Hook => "hook",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VertexKind {
+    Node { entry: bool },
+    End,
+    Missing,
+}
+
+struct Vertex {
+    id: String,
+    label: Vec<String>,
+    kind: VertexKind,
+    tone: Option<Tone>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EdgeKind {
+    Next,
+    Else,
+    Call,
+}
+
+struct Edge {
+    from: String,
+    to: String,
+    label: Vec<String>,
+    kind: EdgeKind,
+    tone: Option<Tone>,
+}
+
+struct Cluster {
+    title: String,
+    vertices: Vec<Vertex>,
+}
+
+#[derive(Default)]
+struct Graph {
+    clusters: Vec<Cluster>,
+    /// targets that don't resolve to anything in the graph
+    missing: Vec<Vertex>,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    fn build(scripts: &[RatScript]) -> Self {
+        let mut ids = HashMap::new();
+        for (index, (script, node)) in scripts
+            .iter()
+            .flat_map(|script| script.ordered_nodes().map(move |node| (script, node)))
+            .enumerate()
+        {
+            ids.insert((script.id.clone(), node.id.clone()), format!("n{index}"));
+        }
+        let mut builder = GraphBuilder {
+            scripts,
+            ids,
+            missing: HashMap::new(),
+            end: String::new(),
+            graph: Graph::default(),
+        };
+        for (index, script) in scripts.iter().enumerate() {
+            builder.script(index, script);
+        }
+        builder.graph
+    }
+
+    fn dot(&self) -> String {
+        let mut out = vec![
+            "digraph ratspinner {".to_string(),
+            "    rankdir=LR;".to_string(),
+            "    node [shape=box, style=rounded, fontname=\"monospace\"];".to_string(),
+            "    edge [fontname=\"monospace\"];".to_string(),
+        ];
+        for (index, cluster) in self.clusters.iter().enumerate() {
+            out.push(String::new());
+            out.push(format!("    subgraph cluster_{index} {{"));
+            out.push(format!("        label=\"{}\";", dot_escape(&cluster.title)));
+            for vertex in &cluster.vertices {
+                out.push(format!("        {}", dot_vertex(vertex)));
+            }
+            out.push("    }".to_string());
+        }
+        if !self.missing.is_empty() {
+            out.push(String::new());
+            out.extend(
+                self.missing
+                    .iter()
+                    .map(|vertex| format!("    {}", dot_vertex(vertex))),
+            );
+        }
+        if !self.edges.is_empty() {
+            out.push(String::new());
+        }
+        for edge in &self.edges {
+            let mut attrs = Vec::new();
+            if !edge.label.is_empty() {
+                attrs.push(format!("label=\"{}\"", dot_label(&edge.label)));
+            }
+            match edge.kind {
+                EdgeKind::Next => {}
+                EdgeKind::Else => attrs.push("style=dashed".to_string()),
+                EdgeKind::Call => attrs.push("style=dotted".to_string()),
+            }
+            if let Some(tone) = edge.tone {
+                let color = tone.color();
+                attrs.push(format!(
+                    "color=\"{color}\", fontcolor=\"{color}\", penwidth=2"
+                ));
+            }
+            let attrs = if attrs.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", attrs.join(", "))
+            };
+            out.push(format!("    {} -> {}{attrs};", edge.from, edge.to));
+        }
+        out.push("}".to_string());
+        out.push(String::new());
+        out.join("\n")
+    }
+
+    fn mermaid(&self) -> String {
+        let mut out = vec!["flowchart LR".to_string()];
+        let mut assigned: Vec<(String, &str)> = Vec::new();
+        for (index, cluster) in self.clusters.iter().enumerate() {
+            out.push(format!(
+                "    subgraph s{index}[\"{}\"]",
+                mermaid_escape(&cluster.title)
+            ));
+            for vertex in &cluster.vertices {
+                out.push(format!("        {}", mermaid_vertex(vertex)));
+                if let Some(class) = mermaid_class(vertex) {
+                    assigned.push((vertex.id.clone(), class));
+                }
+            }
+            out.push("    end".to_string());
+        }
+        for vertex in &self.missing {
+            out.push(format!("    {}", mermaid_vertex(vertex)));
+            if let Some(class) = mermaid_class(vertex) {
+                assigned.push((vertex.id.clone(), class));
+            }
+        }
+
+        let mut link_styles = Vec::new();
+        for (index, edge) in self.edges.iter().enumerate() {
+            let arrow = match (edge.kind, edge.tone) {
+                (EdgeKind::Else | EdgeKind::Call, _) => "-.->",
+                (EdgeKind::Next, Some(_)) => "==>",
+                (EdgeKind::Next, None) => "-->",
+            };
+            let label = if edge.label.is_empty() {
+                String::new()
+            } else {
+                format!("|\"{}\"|", mermaid_label(&edge.label))
+            };
+            out.push(format!("    {} {arrow}{label} {}", edge.from, edge.to));
+            if let Some(tone) = edge.tone {
+                link_styles.push(format!(
+                    "    linkStyle {index} stroke:{},stroke-width:3px",
+                    tone.color()
+                ));
+            }
+        }
+        out.extend(link_styles);
+
+        if !assigned.is_empty() {
+            out.push("    classDef entry stroke-width:3px".to_string());
+            out.push(format!(
+                "    classDef kill stroke:{KILL_COLOR},stroke-width:3px"
+            ));
+            out.push(format!(
+                "    classDef spare stroke:{SPARE_COLOR},stroke-width:3px"
+            ));
+            out.push(format!(
+                "    classDef hook stroke:{HOOK_COLOR},stroke-width:2px"
+            ));
+            out.push(format!(
+                "    classDef missing stroke:{MISSING_COLOR},stroke-dasharray:4"
+            ));
+            for (id, class) in &assigned {
+                out.push(format!("    class {id} {class}"));
+            }
+        }
+        out.push(String::new());
+        out.join("\n")
+    }
+}
+
+struct GraphBuilder<'a> {
+    scripts: &'a [RatScript],
+    /// `(script_id, node_id)` -> vertex id
+    ids: HashMap<(String, String), String>,
+    /// raw target -> vertex id
+    missing: HashMap<String, String>,
+    /// end vertex of the script being walked
+    end: String,
+    graph: Graph,
+}
+
+impl GraphBuilder<'_> {
+    fn script(&mut self, index: usize, script: &RatScript) {
+        self.end = format!("done{index}");
+        let mut vertices: Vec<Vertex> = script
+            .ordered_nodes()
+            .map(|node| Vertex {
+                id: self.vertex_id(&script.id, &node.id).unwrap_or_default(),
+                label: node_label(node),
+                kind: VertexKind::Node {
+                    entry: node.id == script.entry,
+                },
+                tone: Tone::of(&node.hooks),
+            })
+            .collect();
+        let edges_before = self.graph.edges.len();
+        for node in script.ordered_nodes() {
+            self.node_edges(script, node);
+        }
+        let ends_here = self.graph.edges[edges_before..]
+            .iter()
+            .any(|edge| edge.to == self.end);
+        if ends_here {
+            vertices.push(Vertex {
+                id: self.end.clone(),
+                label: vec!["end".to_string()],
+                kind: VertexKind::End,
+                tone: None,
+            });
+        }
+        self.graph.clusters.push(Cluster {
+            title: script.id.clone(),
+            vertices,
+        });
+    }
+
+    fn node_edges(&mut self, script: &RatScript, node: &RatNode) {
+        let from = self.vertex_id(&script.id, &node.id).unwrap_or_default();
+
+        if !node.when.is_empty() && (node.else_next.is_some() || !node.options.is_empty()) {
+            // a failed guard skips the node, options included
+            let fallback = node.else_next.as_ref().or(node.next.as_ref());
+            let mut label = vec!["else".to_string()];
+            label.extend(conditions_label(&node.when));
+            self.edge(script, &from, fallback, label, EdgeKind::Else, None);
+        }
+
+        if let (Some(timeout), Some(target)) = (&node.timeout, node.timeout_target()) {
+            let label = vec![format!("timeout {}s", timeout.secs)];
+            self.edge(script, &from, Some(target), label, EdgeKind::Else, None);
+        }
+
+        if node.options.is_empty() {
+            // silent nodes and plain lines carry on to `->`
+            self.edge(
+                script,
+                &from,
+                node.next.as_ref(),
+                Vec::new(),
+                EdgeKind::Next,
+                None,
+            );
+            return;
+        }
+        for option in &node.options {
+            let mut label = vec![truncate(&option.text)];
+            label.extend(conditions_label(&option.conditions));
+            label.extend(option.hooks.iter().map(|hook| format!("hook: {hook}")));
+            if let Some(timeout) = &node.timeout
+                && option.id.as_ref() == Some(&timeout.next)
+            {
+                label.push(format!("on timeout {}s", timeout.secs));
+            }
+            let target = option.next.as_ref().or(node.next.as_ref());
+            let tone = Tone::of(&option.hooks);
+            self.edge(script, &from, target, label, EdgeKind::Next, tone);
+        }
+    }
+
+    fn edge(
+        &mut self,
+        script: &RatScript,
+        from: &str,
+        target: Option<&String>,
+        mut label: Vec<String>,
+        kind: EdgeKind,
+        tone: Option<Tone>,
+    ) {
+        let (to, kind) = match target {
+            None => (self.end.clone(), kind),
+            Some(raw) => match RatTarget::parse(raw) {
+                Ok(RatTarget::Return) => {
+                    label.insert(0, "return".to_string());
+                    (self.end.clone(), kind)
+                }
+                Ok(RatTarget::Goto(node_ref)) => (self.resolve(script, &node_ref, raw), kind),
+                Ok(RatTarget::Call(node_ref)) => {
+                    label.insert(0, "call".to_string());
+                    (self.resolve(script, &node_ref, raw), EdgeKind::Call)
+                }
+                Err(_) => (self.missing(raw), kind),
+            },
+        };
+        self.graph.edges.push(Edge {
+            from: from.to_string(),
+            to,
+            label,
+            kind,
+            tone,
+        });
+    }
+
+    fn resolve(&mut self, script: &RatScript, node_ref: &RatNodeRef, raw: &str) -> String {
+        let script_id = node_ref.script.as_deref().unwrap_or(&script.id);
+        let node_id = match &node_ref.node {
+            Some(node) => Some(node.as_str()),
+            None => self
+                .scripts
+                .iter()
+                .find(|script| script.id == script_id)
+                .map(|script| script.entry.as_str()),
+        };
+        match node_id.and_then(|node_id| self.vertex_id(script_id, node_id)) {
+            Some(id) => id,
+            None => self.missing(raw),
+        }
+    }
+
+    fn vertex_id(&self, script_id: &str, node_id: &str) -> Option<String> {
+        self.ids
+            .get(&(script_id.to_string(), node_id.to_string()))
+            .cloned()
+    }
+
+    fn missing(&mut self, raw: &str) -> String {
+        if let Some(id) = self.missing.get(raw) {
+            return id.clone();
+        }
+        let id = format!("missing{}", self.missing.len());
+        self.missing.insert(raw.to_string(), id.clone());
+        self.graph.missing.push(Vertex {
+            id: id.clone(),
+            label: vec![format!("? {raw}")],
+            kind: VertexKind::Missing,
+            tone: None,
+        });
+        id
+    }
+}
+
+/// `[id]`, `speaker: text..`, the node's hooks and option providers
+fn node_label(node: &RatNode) -> Vec<String> {
+    let mut label = vec![format!("[{}]", node.id)];
+    let mut text = truncate(&node.text);
+    if !node.variants.is_empty() {
+        text.push_str(&format!(" (+{})", node.variants.len()));
+    }
+    match (node.speaker.is_empty(), text.is_empty()) {
+        (false, false) => label.push(format!("{}: {text}", node.speaker)),
+        (true, false) => label.push(text),
+        (false, true) => label.push(format!("{}:", node.speaker)),
+        (true, true) => {}
+    }
+    label.extend(node.hooks.iter().map(|hook| format!("hook: {hook}")));
+    label.extend(
+        node.options_from
+            .iter()
+            .map(|provider| format!("options from: {provider}")),
+    );
+    label
+}
+
+fn conditions_label(conditions: &[RatCondition]) -> Option<String> {
+    if conditions.is_empty() {
+        return None;
+    }
+    let conditions: Vec<String> = conditions.iter().map(ToString::to_string).collect();
+    Some(format!("if: {}", conditions.join(" && ")))
+}
+
+/// plain text without markup tags, cut to `LABEL_CHARS`
+fn truncate(text: &str) -> String {
+    let plain = RatMarkup::lenient(text).plain();
+    let plain = plain.split_whitespace().collect::<Vec<_>>().join(" ");
+    if plain.chars().count() <= LABEL_CHARS {
+        return plain;
+    }
+    let cut: String = plain.chars().take(LABEL_CHARS - 3).collect();
+    format!("{}...", cut.trim_end())
+}
+
+fn dot_vertex(vertex: &Vertex) -> String {
+    let mut attrs = vec![format!("label=\"{}\"", dot_label(&vertex.label))];
+    match vertex.kind {
+        VertexKind::Node { entry: true } => attrs.push("style=\"rounded,bold\"".to_string()),
+        VertexKind::Node { entry: false } => {}
+        VertexKind::End => attrs.push("shape=doublecircle".to_string()),
+        VertexKind::Missing => attrs.push(format!(
+            "shape=octagon, color=\"{MISSING_COLOR}\", fontcolor=\"{MISSING_COLOR}\""
+        )),
+    }
+    if let Some(tone) = vertex.tone {
+        attrs.push(format!("color=\"{}\", penwidth=2", tone.color()));
+    }
+    format!("{} [{}];", vertex.id, attrs.join(", "))
+}
+
+fn dot_label(lines: &[String]) -> String {
+    let lines: Vec<String> = lines.iter().map(|line| dot_escape(line)).collect();
+    lines.join("\\n")
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_vertex(vertex: &Vertex) -> String {
+    let label = mermaid_label(&vertex.label);
+    match vertex.kind {
+        VertexKind::Node { .. } => format!("{}[\"{label}\"]", vertex.id),
+        VertexKind::End => format!("{}((\"{label}\"))", vertex.id),
+        VertexKind::Missing => format!("{}{{{{\"{label}\"}}}}", vertex.id),
+    }
+}
+
+fn mermaid_class(vertex: &Vertex) -> Option<&'static str> {
+    match (vertex.kind, vertex.tone) {
+        (VertexKind::Missing, _) => Some("missing"),
+        (_, Some(tone)) => Some(tone.class()),
+        (VertexKind::Node { entry: true }, None) => Some("entry"),
+        _ => None,
+    }
+}
+
+fn mermaid_label(lines: &[String]) -> String {
+    let lines: Vec<String> = lines.iter().map(|line| mermaid_escape(line)).collect();
+    lines.join("<br/>")
+}
+
+/// mermaid reads `"` and `<`/`>` inside labels, entity codes dodge that
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::ratspinner::{
+        lint::read_scripts,
+        types::{RatNodeBuilder, RatOptionBuilder, RatScriptBuilder},
+    };
+
+    #[test]
+    fn shipped_scripts_draw_without_missing_targets() {
+        let assets_root = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let scripts = read_scripts(&assets_root).expect("scripts should load");
+        let dot = write_graph(&scripts, RatGraphFormat::Dot);
+
+        assert!(dot.starts_with("digraph ratspinner {"));
+        assert!(!dot.contains("missing"), "{dot}");
+        for script in &scripts {
+            assert!(dot.contains(&format!("label=\"{}\";", script.id)));
+        }
+        let kill = dot
+            .lines()
+            .find(|line| line.contains("(Eliminate) Goodbye!"))
+            .expect("npc.dunce's kill option should be an edge");
+        assert!(kill.contains("hook: game.kill"), "{kill}");
+        assert!(kill.contains("color=\"#c0392b\""), "{kill}");
+    }
+
+    #[test]
+    fn mermaid_marks_hooks_and_dangling_targets() {
+        let script = RatScriptBuilder::new("demo")
+            .entry("start")
+            .node(
+                RatNodeBuilder::new("start")
+                    .speaker("mr. d.")
+                    .text("{glitch}choose{/glitch} \"carefully\".")
+                    .option(
+                        RatOptionBuilder::new("spare")
+                            .goto("spared")
+                            .hook("game.spare"),
+                    )
+                    .option(RatOptionBuilder::new("wander").goto("nowhere")),
+            )
+            .node(RatNodeBuilder::new("spared").text("thanks."))
+            .build();
+        let mermaid = write_graph(&[script], RatGraphFormat::Mermaid);
+
+        assert!(mermaid.starts_with("flowchart LR\n"), "{mermaid}");
+        assert!(
+            mermaid.contains("n0[\"[start]<br/>mr. d.: choose #quot;carefully#quot;.\"]"),
+            "{mermaid}"
+        );
+        assert!(mermaid.contains("n0 ==>|\"spare<br/>hook: game.spare\"| n1"));
+        assert!(mermaid.contains("linkStyle 0 stroke:#27ae60"));
+        assert!(mermaid.contains("n0 -->|\"wander\"| missing0"));
+        assert!(mermaid.contains("missing0{{\"? nowhere\"}}"));
+        assert!(mermaid.contains("n1 --> done0"));
+        assert!(mermaid.contains("class missing0 missing"));
+    }
+}
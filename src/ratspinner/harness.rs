@@ -0,0 +1,617 @@
+use std::{path::Path, time::Duration};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+
+use super::{
+    RatSpinnerPlugin,
+    interpolate::RatTextResolvers,
+    lint::{read_scripts, read_speakers},
+    providers::{RatOptionAction, RatOptionProviders},
+    runtime::{self, RatDialogueState, RatLibrary, RatRuntime, parse_script_bytes},
+    speakers::SPEAKERS_PATH,
+    types::{
+        RatCommand, RatCueTriggered, RatHookTriggered, RatNodeEntered, RatOption, RatScript,
+        RatStart, parse_hook_call,
+    },
+    vars::{RatCondition, RatEffect, RatVariables, parse_conditions},
+};
+use crate::{
+    ui::{
+        DiscoveryEntry, DiscoveryKind, UiBarkCommand, UiDialogueCommand, UiDiscoveryCommand,
+        UiDiscoveryDb,
+    },
+    voice::{PrewarmVoice, Speak, StopVoice},
+};
+
+/// everything a harness run entered and fired, in order
+#[derive(Resource, Debug, Clone, Default)]
+pub struct RatTranscript {
+    pub nodes: Vec<RatNodeEntered>,
+    pub hooks: Vec<RatHookTriggered>,
+    pub cues: Vec<RatCueTriggered>,
+}
+
+impl RatTranscript {
+    pub fn visited(&self, script_id: &str, node_id: &str) -> bool {
+        self.nodes
+            .iter()
+            .any(|node| node.script_id == script_id && node.node_id == node_id)
+    }
+
+    pub fn fired(&self, hook: &str) -> bool {
+        self.hooks.iter().any(|fired| fired.hook == hook)
+    }
+}
+
+/// a bare app with `RatSpinnerPlugin` and stand-ins for the ui and voice, for
+/// playing scripts headless in tests. every command runs one update
+pub struct RatHarness {
+    app: App,
+}
+
+impl Default for RatHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RatHarness {
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), RatSpinnerPlugin))
+            // the runtime still writes to the ui and voice, nobody reads it here
+            .init_resource::<UiDiscoveryDb>()
+            .add_message::<UiDialogueCommand>()
+            .add_message::<UiBarkCommand>()
+            .add_message::<UiDiscoveryCommand>()
+            .add_message::<Speak>()
+            .add_message::<PrewarmVoice>()
+            .add_message::<StopVoice>()
+            .init_resource::<RatTranscript>()
+            // the clock only moves in `wait`, `timeout:` nodes never run out by themselves
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO))
+            .add_systems(
+                Update,
+                record_transcript.after(runtime::handle_rat_commands),
+            );
+        app.finish();
+        app.cleanup();
+        Self { app }
+    }
+
+    /// registers every script from `<assets_root>/default.assets.ron` and the
+    /// speakers next to them, parsed the same lenient way the game loads them
+    pub fn from_assets(assets_root: &Path) -> Result<Self, String> {
+        let mut harness = Self::new();
+        harness.load_speakers(&assets_root.join(SPEAKERS_PATH))?;
+        for script in read_scripts(assets_root)? {
+            harness.register(script);
+        }
+        Ok(harness)
+    }
+
+    /// swaps in the speaker registry from a speakers.ron, for `load` and the
+    /// names and portraits the runtime shows
+    pub fn load_speakers(&mut self, path: &Path) -> Result<&mut Self, String> {
+        let speakers = read_speakers(path)?;
+        self.app
+            .world_mut()
+            .resource_mut::<RatLibrary>()
+            .set_speakers(speakers);
+        Ok(self)
+    }
+
+    /// registers the scripts in one .rat, .ron or .yarn file, against the
+    /// speakers from `load_speakers` (or `from_assets`)
+    pub fn load(&mut self, path: &Path) -> Result<&mut Self, String> {
+        let bytes = std::fs::read(path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        let speakers = self.app.world().resource::<RatLibrary>().speakers();
+        let scripts =
+            parse_script_bytes(&bytes, path, false, speakers).map_err(|error| error.to_string())?;
+        for script in scripts {
+            self.register(script);
+        }
+        Ok(self)
+    }
+
+    pub fn register(&mut self, script: RatScript) -> &mut Self {
+        self.send(RatCommand::Register(script))
+    }
+
+    /// starts headless whatever `start` asks for
+    pub fn start(&mut self, start: RatStart) -> &mut Self {
+        self.send(RatCommand::Start(start.headless()))
+    }
+
+    pub fn choose(&mut self, index: usize) -> &mut Self {
+        self.send(RatCommand::Choose(index))
+    }
+
+    pub fn advance(&mut self) -> &mut Self {
+        self.send(RatCommand::Advance)
+    }
+
+    pub fn close(&mut self) -> &mut Self {
+        self.send(RatCommand::Close)
+    }
+
+    pub fn interrupt(&mut self) -> &mut Self {
+        self.send(RatCommand::Interrupt)
+    }
+
+    pub fn bark(&mut self, start: RatStart) -> &mut Self {
+        self.send(RatCommand::Bark(start))
+    }
+
+    /// starts a conversation between npcs, `wait` lets its lines play
+    pub fn overhear(&mut self, start: RatStart) -> &mut Self {
+        self.send(RatCommand::Overhear(start))
+    }
+
+    /// picks a visible option by id, index or the start of its text
+    pub fn choose_option(&mut self, option: &str) -> Result<&mut Self, String> {
+        let options = self.options();
+        let index = options
+            .iter()
+            .position(|candidate| candidate.id.as_deref() == Some(option))
+            .or_else(|| {
+                option
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index < options.len())
+            })
+            .or_else(|| {
+                options
+                    .iter()
+                    .position(|candidate| candidate.text.starts_with(option))
+            });
+        let Some(index) = index else {
+            let known: Vec<String> = options
+                .iter()
+                .map(|candidate| match &candidate.id {
+                    Some(id) => format!("{id} ({})", candidate.text),
+                    None => candidate.text.clone(),
+                })
+                .collect();
+            return Err(format!(
+                "no option '{option}' at {}, visible: {}",
+                self.describe_current(),
+                known.join(", ")
+            ));
+        };
+        Ok(self.choose(index))
+    }
+
+    /// plays `commands` from `start` and returns everything recorded so far
+    pub fn play(
+        &mut self,
+        start: RatStart,
+        commands: impl IntoIterator<Item = RatCommand>,
+    ) -> &RatTranscript {
+        self.start(start);
+        for command in commands {
+            self.send(command);
+        }
+        self.transcript()
+    }
+
+    pub fn send(&mut self, command: RatCommand) -> &mut Self {
+        self.app.world_mut().write_message(command);
+        self.app.update();
+        self
+    }
+
+    /// lets `secs` pass in small steps, for `timeout:` nodes
+    pub fn wait(&mut self, secs: f32) -> &mut Self {
+        const STEP: f32 = 0.1;
+        let mut left = secs;
+        while left > 0.0 {
+            let step = left.min(STEP);
+            self.app
+                .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                    step,
+                )));
+            self.app.update();
+            left -= step;
+        }
+        self.app
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        self
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.app.world().resource::<RatDialogueState>().active
+    }
+
+    /// script of the conversation the npcs are having, if any
+    pub fn overhearing(&self) -> Option<String> {
+        self.app
+            .world()
+            .resource::<RatRuntime>()
+            .conversation()
+            .map(str::to_string)
+    }
+
+    /// (script_id, node_id) of the open dialogue
+    pub fn current(&self) -> Option<(String, String)> {
+        self.app
+            .world()
+            .resource::<RatRuntime>()
+            .current()
+            .map(|(script_id, node_id)| (script_id.to_string(), node_id.to_string()))
+    }
+
+    /// the open node's line as it's said, with `{var}` and `{item:..}` filled
+    pub fn line(&self) -> Option<String> {
+        let world = self.app.world();
+        world.resource::<RatRuntime>().line(
+            world.resource::<RatLibrary>(),
+            world.resource::<RatVariables>(),
+            world.resource::<UiDiscoveryDb>(),
+            world.resource::<RatTextResolvers>(),
+        )
+    }
+
+    /// options the player can pick right now, `[if: ...]` applied and
+    /// `options_from:` providers included. inside a provider's menu these are
+    /// its entries, `back` being one past the last
+    pub fn options(&self) -> Vec<RatOption> {
+        let world = self.app.world();
+        world
+            .resource::<RatRuntime>()
+            .options(
+                world.resource::<RatLibrary>(),
+                world.resource::<RatVariables>(),
+                world.resource::<UiDiscoveryDb>(),
+                world.resource::<RatOptionProviders>(),
+                world.resource::<RatTextResolvers>(),
+            )
+            .into_iter()
+            .map(|option| RatOption {
+                next: match option.action {
+                    RatOptionAction::Goto(next) => next,
+                    _ => None,
+                },
+                id: option.id,
+                text: option.text,
+                hooks: option.hooks,
+                conditions: Vec::new(),
+                effects: option.effects,
+            })
+            .collect()
+    }
+
+    /// puts an item in the inventory, for `options_from: inventory`
+    pub fn give_item(&mut self, id: &str, title: &str) -> &mut Self {
+        self.app
+            .world_mut()
+            .resource_mut::<UiDiscoveryDb>()
+            .upsert(DiscoveryKind::Item, DiscoveryEntry::new(id, title));
+        self
+    }
+
+    pub fn transcript(&self) -> &RatTranscript {
+        self.app.world().resource::<RatTranscript>()
+    }
+
+    pub fn variables(&self) -> &RatVariables {
+        self.app.world().resource::<RatVariables>()
+    }
+
+    pub fn variables_mut(&mut self) -> Mut<'_, RatVariables> {
+        self.app.world_mut().resource_mut::<RatVariables>()
+    }
+
+    /// for registering hook handlers with `add_rat_hook`
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    fn describe_current(&self) -> String {
+        match self.current() {
+            Some((script_id, node_id)) => format!("{script_id}:{node_id}"),
+            None => "closed dialogue".to_string(),
+        }
+    }
+}
+
+fn record_transcript(
+    mut nodes: MessageReader<RatNodeEntered>,
+    mut hooks: MessageReader<RatHookTriggered>,
+    mut cues: MessageReader<RatCueTriggered>,
+    mut transcript: ResMut<RatTranscript>,
+) {
+    transcript.nodes.extend(nodes.read().cloned());
+    transcript.hooks.extend(hooks.read().cloned());
+    transcript.cues.extend(cues.read().cloned());
+}
+
+/// a scripted playthrough from a `.rat.test` file, one step per line:
+///
+/// ```text
+/// // npc.lover can be spared at the window
+/// start: npc.lover.lured
+/// expect node: judgement
+/// choose: (Spare)
+/// expect hook: game.spare
+/// expect end
+/// ```
+///
+/// `start:` takes anything `RatStart::new` does, `choose:` an option id, index
+/// or text prefix, `wait:` lets seconds pass for `timeout:` nodes and
+/// `set:`/`inc:` change variables like in scripts. `expect
+/// node:` (`node` or `script:node`) and `expect hook:` (`name` or
+/// `name(args)`) must show up after whatever the previous expectation of the
+/// same kind matched. `expect line:` checks the text line of the last entered
+/// node, `expect when:` a condition and `expect end` that the dialogue closed
+#[derive(Debug, Clone)]
+pub struct RatScriptTest {
+    pub name: String,
+    steps: Vec<(usize, RatTestStep)>,
+}
+
+#[derive(Debug, Clone)]
+enum RatTestStep {
+    Start(String),
+    Choose(String),
+    Advance,
+    Close,
+    Wait(f32),
+    Effect(RatEffect),
+    ExpectNode(String),
+    ExpectHook(String, Vec<String>),
+    ExpectLine(usize),
+    ExpectWhen(Vec<RatCondition>),
+    ExpectEnd,
+}
+
+impl RatScriptTest {
+    pub fn parse(name: impl Into<String>, content: &str) -> Result<Self, String> {
+        let name = name.into();
+        let mut steps = Vec::new();
+        for (line_index, line) in content.lines().enumerate() {
+            let line_number = line_index + 1;
+            let raw = line.trim();
+            if raw.is_empty() || raw.starts_with("//") {
+                continue;
+            }
+            let (key, value) = match raw.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (raw, ""),
+            };
+            let step = match key {
+                "start" => Ok(RatTestStep::Start(value.to_string())),
+                "choose" => Ok(RatTestStep::Choose(value.to_string())),
+                "advance" => Ok(RatTestStep::Advance),
+                "close" => Ok(RatTestStep::Close),
+                "wait" => value
+                    .parse()
+                    .ok()
+                    .filter(|secs: &f32| secs.is_finite() && *secs >= 0.0)
+                    .map(RatTestStep::Wait)
+                    .ok_or_else(|| format!("'{value}' is not a number of seconds")),
+                "set" => RatEffect::parse_set(value).map(RatTestStep::Effect),
+                "inc" => RatEffect::parse_inc(value).map(RatTestStep::Effect),
+                "expect node" => Ok(RatTestStep::ExpectNode(value.to_string())),
+                "expect hook" => {
+                    parse_hook_call(value).map(|(hook, args)| RatTestStep::ExpectHook(hook, args))
+                }
+                "expect line" => value
+                    .parse()
+                    .map(RatTestStep::ExpectLine)
+                    .map_err(|_| format!("'{value}' is not a line index")),
+                "expect when" => parse_conditions(value).map(RatTestStep::ExpectWhen),
+                "expect end" => Ok(RatTestStep::ExpectEnd),
+                _ => Err(format!("unknown step '{key}'")),
+            };
+            match step {
+                Ok(step) if needs_value(&step) && value.is_empty() => {
+                    return Err(format!("{name}:{line_number}: '{key}' needs a value"));
+                }
+                Ok(step) => steps.push((line_number, step)),
+                Err(error) => return Err(format!("{name}:{line_number}: {error}")),
+            }
+        }
+        if steps.is_empty() {
+            return Err(format!("{name}: no steps"));
+        }
+        Ok(Self { name, steps })
+    }
+
+    /// plays every step, stopping at the first expectation that fails
+    pub fn run(&self, harness: &mut RatHarness) -> Result<(), String> {
+        let mut node_cursor = harness.transcript().nodes.len();
+        let mut hook_cursor = harness.transcript().hooks.len();
+        for (line_number, step) in &self.steps {
+            let fail = |error: String| format!("{}:{line_number}: {error}", self.name);
+            match step {
+                RatTestStep::Start(target) => {
+                    harness.start(RatStart::new(target.clone()));
+                    if !harness.is_open() {
+                        return Err(fail(format!("'{target}' did not start a dialogue")));
+                    }
+                }
+                RatTestStep::Choose(option) => {
+                    harness.choose_option(option).map_err(fail)?;
+                }
+                RatTestStep::Advance => {
+                    harness.advance();
+                }
+                RatTestStep::Close => {
+                    harness.close();
+                }
+                RatTestStep::Wait(secs) => {
+                    harness.wait(*secs);
+                }
+                RatTestStep::Effect(effect) => harness.variables_mut().apply(effect),
+                RatTestStep::ExpectNode(expected) => {
+                    let nodes = &harness.transcript().nodes;
+                    let found = nodes[node_cursor..].iter().position(|node| {
+                        match expected.split_once(':') {
+                            Some((script_id, node_id)) => {
+                                node.script_id == script_id && node.node_id == node_id
+                            }
+                            None => node.node_id == *expected,
+                        }
+                    });
+                    let Some(offset) = found else {
+                        let entered: Vec<String> = nodes[node_cursor..]
+                            .iter()
+                            .map(|node| format!("{}:{}", node.script_id, node.node_id))
+                            .collect();
+                        return Err(fail(format!(
+                            "node '{expected}' was not entered, got [{}]",
+                            entered.join(", ")
+                        )));
+                    };
+                    node_cursor += offset + 1;
+                }
+                RatTestStep::ExpectHook(hook, args) => {
+                    let hooks = &harness.transcript().hooks;
+                    let found = hooks[hook_cursor..].iter().position(|fired| {
+                        fired.hook == *hook && (args.is_empty() || fired.args == *args)
+                    });
+                    let Some(offset) = found else {
+                        let fired: Vec<String> = hooks[hook_cursor..]
+                            .iter()
+                            .map(|fired| fired.hook.clone())
+                            .collect();
+                        return Err(fail(format!(
+                            "hook '{hook}' did not fire, got [{}]",
+                            fired.join(", ")
+                        )));
+                    };
+                    hook_cursor += offset + 1;
+                }
+                RatTestStep::ExpectLine(expected) => {
+                    let line = harness.transcript().nodes.last().map(|node| node.line);
+                    if line != Some(*expected) {
+                        return Err(fail(format!("expected line {expected}, got {line:?}")));
+                    }
+                }
+                RatTestStep::ExpectWhen(conditions) => {
+                    if !harness.variables().check(conditions) {
+                        let failed: Vec<String> = conditions
+                            .iter()
+                            .filter(|condition| !condition.eval(harness.variables()))
+                            .map(ToString::to_string)
+                            .collect();
+                        return Err(fail(format!("does not hold: {}", failed.join(" && "))));
+                    }
+                }
+                RatTestStep::ExpectEnd => {
+                    if harness.is_open() {
+                        return Err(fail(format!(
+                            "dialogue is still open at {}",
+                            harness.describe_current()
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn needs_value(step: &RatTestStep) -> bool {
+    matches!(
+        step,
+        RatTestStep::Start(_)
+            | RatTestStep::Choose(_)
+            | RatTestStep::ExpectNode(_)
+            | RatTestStep::ExpectHook(..)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ratspinner::types::{RatNodeBuilder, RatOptionBuilder, RatScriptBuilder};
+
+    #[test]
+    fn records_nodes_and_hooks_in_order() {
+        let mut harness = RatHarness::new();
+        harness.register(
+            RatScriptBuilder::new("demo")
+                .entry("start")
+                .node(
+                    RatNodeBuilder::new("start")
+                        .text("choose.")
+                        .hook("dialog.start")
+                        .option(
+                            RatOptionBuilder::new("left")
+                                .goto("left")
+                                .hook("dialog.left"),
+                        )
+                        .option(RatOptionBuilder::new("right").goto("right")),
+                )
+                .node(
+                    RatNodeBuilder::new("left")
+                        .text("left chosen.")
+                        .hook("door.open(front_door)"),
+                )
+                .node(RatNodeBuilder::new("right").text("right chosen."))
+                .build(),
+        );
+
+        let transcript = harness.play(
+            RatStart::new("demo"),
+            [RatCommand::Choose(0), RatCommand::Advance],
+        );
+
+        let nodes: Vec<&str> = transcript
+            .nodes
+            .iter()
+            .map(|node| node.node_id.as_str())
+            .collect();
+        assert_eq!(nodes, ["start", "left"]);
+        let hooks: Vec<&str> = transcript
+            .hooks
+            .iter()
+            .map(|hook| hook.hook.as_str())
+            .collect();
+        assert_eq!(hooks, ["dialog.start", "dialog.left", "door.open"]);
+        assert_eq!(transcript.hooks[2].arg(0), Some("front_door"));
+        assert!(!transcript.visited("demo", "right"));
+        assert!(!harness.is_open());
+    }
+
+    #[test]
+    fn rat_tests_report_the_failing_line() {
+        let test = RatScriptTest::parse(
+            "inline.rat.test",
+            "start: npc.default\nchoose: leave\nexpect hook: game.kill\n",
+        )
+        .expect("test should parse");
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let mut harness = RatHarness::from_assets(&assets).expect("assets should load");
+        let error = test.run(&mut harness).expect_err("game.kill never fires");
+        assert!(error.starts_with("inline.rat.test:3:"), "{error}");
+
+        let error = RatScriptTest::parse(
+            "typo.rat.test",
+            "start: npc.default\nexpect nod: greeting\n",
+        )
+        .expect_err("`expect nod` is not a step");
+        assert!(error.starts_with("typo.rat.test:2:"), "{error}");
+    }
+
+    #[test]
+    fn loaded_scripts_see_the_loaded_speakers() {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let ron = assets.join("ratspinner/npc.default.ron");
+        let mut harness = RatHarness::new();
+        let error = harness
+            .load(&ron)
+            .err()
+            .expect("npc.default.ron leaves its speaker to speakers.ron");
+        assert!(error.contains("unknown speaker 'mr.d'"), "{error}");
+
+        harness
+            .load_speakers(&assets.join(SPEAKERS_PATH))
+            .expect("speakers should load")
+            .load(&ron)
+            .expect("the speaker fills in the rest");
+    }
+}
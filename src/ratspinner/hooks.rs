@@ -0,0 +1,165 @@
+use bevy::{ecs::system::SystemId, prelude::*};
+
+use super::types::{RatHookTriggered, RatScript, parse_hook_call};
+
+/// hook handlers keyed by name or glob (`door.*`, `game.*_ending`). every
+/// matching handler runs, in registration order
+#[derive(Resource, Default)]
+pub struct RatHookRegistry {
+    handlers: Vec<(String, SystemId<In<RatHookTriggered>>)>,
+}
+
+impl RatHookRegistry {
+    pub fn handles(&self, hook: &str) -> bool {
+        self.handlers
+            .iter()
+            .any(|(pattern, _)| hook_matches(pattern, hook))
+    }
+
+    /// logs every hook used in `scripts` that nothing is registered for
+    pub(super) fn warn_unhandled<'a>(&self, scripts: impl IntoIterator<Item = &'a RatScript>) {
+        let mut unhandled: Vec<String> = scripts
+            .into_iter()
+            .flat_map(|script| script.nodes.values())
+            .flat_map(|node| {
+                node.hooks
+                    .iter()
+                    .chain(node.options.iter().flat_map(|option| &option.hooks))
+            })
+            .filter_map(|raw| parse_hook_call(raw).ok().map(|(name, _)| name))
+            .filter(|name| !self.handles(name))
+            .collect();
+        unhandled.sort_unstable();
+        unhandled.dedup();
+        if !unhandled.is_empty() {
+            warn!("ratspinner hooks with no handler: {}", unhandled.join(", "));
+        }
+    }
+}
+
+pub trait RatHookAppExt {
+    /// runs `handler` for every triggered hook whose name matches `pattern`
+    fn add_rat_hook<M>(
+        &mut self,
+        pattern: impl Into<String>,
+        handler: impl IntoSystem<In<RatHookTriggered>, (), M> + 'static,
+    ) -> &mut Self;
+}
+
+impl RatHookAppExt for App {
+    fn add_rat_hook<M>(
+        &mut self,
+        pattern: impl Into<String>,
+        handler: impl IntoSystem<In<RatHookTriggered>, (), M> + 'static,
+    ) -> &mut Self {
+        let world = self.world_mut();
+        let system = world.register_system(handler);
+        // plugins may register before RatSpinnerPlugin is built
+        world
+            .get_resource_or_init::<RatHookRegistry>()
+            .handlers
+            .push((pattern.into(), system));
+        self
+    }
+}
+
+/// `*` matches any run of characters, dots included
+pub fn hook_matches(pattern: &str, hook: &str) -> bool {
+    let Some((head, rest)) = pattern.split_once('*') else {
+        return pattern == hook;
+    };
+    let Some(mut remaining) = hook.strip_prefix(head) else {
+        return false;
+    };
+    let mut parts = rest.split('*').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            return remaining.ends_with(part);
+        }
+        let Some(index) = remaining.find(part) else {
+            return false;
+        };
+        remaining = &remaining[index + part.len()..];
+    }
+    true
+}
+
+pub(super) fn dispatch_rat_hooks(
+    mut hooks: MessageReader<RatHookTriggered>,
+    registry: Res<RatHookRegistry>,
+    mut commands: Commands,
+) {
+    for hook in hooks.read() {
+        for (pattern, system) in &registry.handlers {
+            if hook_matches(pattern, &hook.hook) {
+                commands.run_system_with(*system, hook.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_whole_names() {
+        assert!(hook_matches("door.open", "door.open"));
+        assert!(!hook_matches("door.open", "door.opened"));
+        assert!(hook_matches("door.*", "door.open"));
+        assert!(!hook_matches("door.*", "door"));
+        assert!(hook_matches("*.open", "front.door.open"));
+        assert!(hook_matches("game.*_ending", "game.spare_all_ending"));
+        assert!(!hook_matches("game.*_ending", "game.spare_ending.late"));
+        assert!(hook_matches("*", ""));
+        // the head and the tail can't share a character
+        assert!(!hook_matches("a*a", "a"));
+        assert!(hook_matches("a*a", "aa"));
+        assert!(hook_matches("a*b*c", "abc"));
+        assert!(!hook_matches("a*bc*c", "abc"));
+    }
+
+    #[derive(Resource, Default)]
+    struct OpenedDoors(Vec<String>);
+
+    fn open_door(In(hook): In<RatHookTriggered>, mut doors: ResMut<OpenedDoors>) {
+        doors.0.extend(hook.args);
+    }
+
+    #[test]
+    fn handlers_get_their_arguments() {
+        let mut app = App::new();
+        app.add_message::<RatHookTriggered>()
+            .init_resource::<OpenedDoors>()
+            .add_rat_hook("door.*", open_door)
+            .add_rat_hook("door.open", open_door)
+            .add_systems(Update, dispatch_rat_hooks);
+
+        for raw in ["door.open(front_door, quietly)", "window.open(back_window)"] {
+            let (hook, args) = parse_hook_call(raw).expect("hook should parse");
+            app.world_mut().write_message(RatHookTriggered {
+                hook,
+                args,
+                script_id: "hall".into(),
+                node_id: "start".into(),
+                option_id: None,
+                target: None,
+            });
+        }
+        app.update();
+
+        // both patterns match, each handler runs once
+        let doors = &app.world().resource::<OpenedDoors>().0;
+        assert_eq!(doors, &["front_door", "quietly", "front_door", "quietly"]);
+        assert!(
+            app.world()
+                .resource::<RatHookRegistry>()
+                .handles("door.close")
+        );
+        assert!(
+            !app.world()
+                .resource::<RatHookRegistry>()
+                .handles("window.open")
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use std::{collections::HashMap, iter};
+
+use bevy::prelude::*;
+
+use super::{
+    markup::is_markup_tag,
+    providers::{RatOptionContext, SHOWN_ITEM_VAR},
+    types::RatScript,
+    vars::RatValue,
+};
+use crate::ui::DiscoveryKind;
+
+type RatTextResolver = Box<dyn Fn(&str, &RatOptionContext) -> Option<String> + Send + Sync>;
+
+/// resolvers keyed by the namespace placeholders use, `{item:office_key.name}`
+/// asks `item` for `office_key.name`
+#[derive(Resource, Default)]
+pub(crate) struct RatTextResolvers {
+    resolvers: HashMap<String, RatTextResolver>,
+}
+
+impl RatTextResolvers {
+    pub fn resolves(&self, namespace: &str) -> bool {
+        self.resolvers.contains_key(namespace)
+    }
+
+    /// a node or reply line with its placeholders filled. the result is still
+    /// markup, braces coming out of a value are escaped
+    pub(super) fn fill_line(&self, raw: &str, ctx: &RatOptionContext) -> String {
+        fill(raw, true, |placeholder| self.resolve(placeholder, ctx))
+    }
+
+    /// option text with its placeholders filled, `{{` turns into a brace
+    pub(super) fn fill_plain(&self, raw: &str, ctx: &RatOptionContext) -> String {
+        fill(raw, false, |placeholder| self.resolve(placeholder, ctx))
+    }
+
+    /// missing values come out empty, a line shouldn't show its braces
+    fn resolve(&self, placeholder: Placeholder, ctx: &RatOptionContext) -> String {
+        let value = match placeholder.namespace {
+            None => ctx.variables.get(placeholder.arg).map(ToString::to_string),
+            Some(namespace) => self
+                .resolvers
+                .get(namespace)
+                .and_then(|resolver| resolver(placeholder.arg, ctx)),
+        };
+        value.unwrap_or_else(|| {
+            warn!(
+                "ratspinner {}:{} has nothing for '{{{}}}'",
+                ctx.script.id, ctx.node.id, placeholder.raw
+            );
+            String::new()
+        })
+    }
+
+    /// logs every placeholder namespace in `scripts` that nothing resolves
+    pub(super) fn warn_unknown<'a>(&self, scripts: impl IntoIterator<Item = &'a RatScript>) {
+        let mut unknown: Vec<String> = scripts
+            .into_iter()
+            .flat_map(script_namespaces)
+            .filter(|namespace| !self.resolves(namespace))
+            .collect();
+        unknown.sort_unstable();
+        unknown.dedup();
+        if !unknown.is_empty() {
+            warn!(
+                "ratspinner text resolvers not registered: {}",
+                unknown.join(", ")
+            );
+        }
+    }
+}
+
+pub(crate) trait RatTextAppExt {
+    /// lets text say `{<namespace>:<arg>}`, filled with what `resolver`
+    /// returns for `arg`
+    fn add_rat_resolver(
+        &mut self,
+        namespace: impl Into<String>,
+        resolver: impl Fn(&str, &RatOptionContext) -> Option<String> + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl RatTextAppExt for App {
+    fn add_rat_resolver(
+        &mut self,
+        namespace: impl Into<String>,
+        resolver: impl Fn(&str, &RatOptionContext) -> Option<String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<RatTextResolvers>()
+            .resolvers
+            .insert(namespace.into(), Box::new(resolver));
+        self
+    }
+}
+
+/// `{name}` reads a variable, `{namespace:arg}` asks a resolver
+#[derive(Debug, Clone, Copy)]
+struct Placeholder<'a> {
+    raw: &'a str,
+    namespace: Option<&'a str>,
+    arg: &'a str,
+}
+
+impl<'a> Placeholder<'a> {
+    /// `None` for markup tags and anything else that isn't a placeholder
+    fn parse(tag: &'a str) -> Option<Self> {
+        let raw = tag.trim();
+        if is_markup_tag(raw) {
+            return None;
+        }
+        let (namespace, arg) = match raw.split_once(':') {
+            Some((namespace, arg)) => (Some(namespace.trim()), arg.trim()),
+            None => (None, raw),
+        };
+        let valid = namespace.is_none_or(is_name) && is_name(arg);
+        valid.then_some(Self {
+            raw,
+            namespace,
+            arg,
+        })
+    }
+}
+
+fn is_name(raw: &str) -> bool {
+    raw.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_')
+        && raw
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '.'))
+}
+
+/// `raw` with `value` in place of every placeholder. `markup` keeps `{{` and
+/// escapes braces in values, otherwise `{{` becomes a plain brace
+fn fill(raw: &str, markup: bool, mut value: impl FnMut(Placeholder) -> String) -> String {
+    let mut filled = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix('{') {
+            filled.push_str(if markup { "{{" } else { "{" });
+            rest = escaped;
+            continue;
+        }
+        let Some(end) = after.find('}') else {
+            // left for the markup parser to complain about
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &after[..end];
+        match Placeholder::parse(tag) {
+            Some(placeholder) if markup => filled.push_str(&value(placeholder).replace('{', "{{")),
+            Some(placeholder) => filled.push_str(&value(placeholder)),
+            None => filled.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// `raw` with its placeholders dropped, for checking the markup around them
+pub(super) fn without_placeholders(raw: &str) -> String {
+    fill(raw, true, |_| String::new())
+}
+
+/// resolver namespaces a line or option text uses
+fn namespaces(raw: &str) -> Vec<String> {
+    let mut namespaces = Vec::new();
+    fill(raw, true, |placeholder| {
+        namespaces.extend(placeholder.namespace.map(str::to_string));
+        String::new()
+    });
+    namespaces
+}
+
+/// resolver namespaces used anywhere in `script`, once per use
+pub(super) fn script_namespaces(script: &RatScript) -> Vec<String> {
+    script
+        .nodes
+        .values()
+        .flat_map(|node| {
+            iter::once(&node.text)
+                .chain(&node.variants)
+                .chain(node.options.iter().map(|option| &option.text))
+        })
+        .flat_map(|text| namespaces(text))
+        .collect()
+}
+
+/// `{item:office_key.name}`, also `.subtitle` and `.description`. the id
+/// `shown` is the last item picked from the inventory
+pub(super) fn item_text(arg: &str, ctx: &RatOptionContext) -> Option<String> {
+    let (id, field) = match arg.rsplit_once('.') {
+        Some((id, field @ ("name" | "subtitle" | "description"))) => (id, field),
+        _ => (arg, "name"),
+    };
+    let id = match (id, ctx.variables.get(SHOWN_ITEM_VAR)) {
+        ("shown", Some(RatValue::Str(shown))) => shown.as_str(),
+        _ => id,
+    };
+    if let Some(meta) = ctx.items.get(id) {
+        return Some(match field {
+            "subtitle" => meta.subtitle.clone(),
+            "description" => meta.description.clone(),
+            _ => meta.name.clone(),
+        });
+    }
+    // items handed out without a .item.meta only live in the inventory
+    let entry = ctx
+        .discovery_db
+        .entries(DiscoveryKind::Item)
+        .iter()
+        .find(|entry| entry.id == id)?;
+    Some(match field {
+        "subtitle" => entry.subtitle.clone(),
+        "description" => entry.description.clone(),
+        _ => entry.title.clone(),
+    })
+}
+
+/// `{speaker:marcus.leaning}`, the name `speakers.ron` gives them
+pub(super) fn speaker_text(arg: &str, ctx: &RatOptionContext) -> Option<String> {
+    ctx.speakers.get(arg).map(|speaker| speaker.name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ratspinner::{
+        harness::RatHarness,
+        types::{RatNodeBuilder, RatOptionBuilder, RatScriptBuilder, RatStart},
+    };
+
+    /// `fill` with every placeholder said as `<arg>`
+    fn angled(raw: &str, markup: bool) -> String {
+        fill(raw, markup, |placeholder| format!("<{}>", placeholder.arg))
+    }
+
+    #[test]
+    fn placeholders_parse_names_and_skip_markup() {
+        let plain = Placeholder::parse(" lies_told ").expect("a variable");
+        assert_eq!((plain.namespace, plain.arg), (None, "lies_told"));
+        let item = Placeholder::parse("item: fork.name").expect("a resolver");
+        assert_eq!((item.namespace, item.arg), (Some("item"), "fork.name"));
+        for tag in [
+            "pause=0.5",
+            "/glitch",
+            "speed=2",
+            "whisper",
+            "2cool",
+            "a b",
+            "",
+        ] {
+            assert!(Placeholder::parse(tag).is_none(), "{tag}");
+        }
+    }
+
+    #[test]
+    fn double_braces_stay_in_markup_and_turn_plain_in_options() {
+        assert_eq!(angled("{{not}} {x}", true), "{{not}} <x>");
+        assert_eq!(angled("{{not}} {x}", false), "{not}} <x>");
+    }
+
+    #[test]
+    fn braces_in_values_are_escaped_for_markup_only() {
+        let braced = |raw: &str, markup| fill(raw, markup, |_| "{glitch}".into());
+        assert_eq!(braced("say {x}", true), "say {{glitch}");
+        assert_eq!(braced("say {x}", false), "say {glitch}");
+    }
+
+    #[test]
+    fn markup_tags_and_unclosed_braces_pass_through() {
+        assert_eq!(
+            angled("{glitch}{x}{/glitch}{pause=0.5}", true),
+            "{glitch}<x>{/glitch}{pause=0.5}"
+        );
+        assert_eq!(angled("{x} and {y", true), "<x> and {y");
+        assert_eq!(angled("{not a name}", true), "{not a name}");
+        assert_eq!(
+            without_placeholders("{x}{whisper}hi{/whisper}"),
+            "{whisper}hi{/whisper}"
+        );
+    }
+
+    #[test]
+    fn lines_escape_values_and_drop_missing_ones() {
+        let mut harness = RatHarness::new();
+        harness.register(
+            RatScriptBuilder::new("notes")
+                .entry("start")
+                .node(
+                    RatNodeBuilder::new("start")
+                        .text("{note}|{missing}|{whisper}{note}{/whisper}")
+                        .option(RatOptionBuilder::new("{note}{missing}").goto("start")),
+                )
+                .build(),
+        );
+        harness
+            .variables_mut()
+            .set("note", RatValue::Str("{pause=1}".into()));
+        harness.start(RatStart::new("notes"));
+        assert_eq!(harness.line().as_deref(), Some("{pause=1}||{pause=1}"));
+        assert_eq!(harness.options()[0].text, "{pause=1}");
+    }
+
+    #[test]
+    fn placeholders_are_filled_before_the_line_is_said() {
+        let mut harness = RatHarness::new();
+        harness.register(
+            RatScriptBuilder::new("counting")
+                .entry("start")
+                .node(
+                    RatNodeBuilder::new("start")
+                        .text("{lies_told} lies, {{not} counting the {item:fork.name}. {nobody}")
+                        .option(RatOptionBuilder::new("{lies_told}? fine.").goto("start")),
+                )
+                .build(),
+        );
+        harness.variables_mut().set("lies_told", RatValue::Int(2));
+        harness.give_item("fork", "Fork");
+        harness.start(RatStart::new("counting"));
+        assert_eq!(
+            harness.line().as_deref(),
+            Some("2 lies, {not} counting the Fork. ")
+        );
+        assert_eq!(harness.options()[0].text, "2? fine.");
+    }
+}
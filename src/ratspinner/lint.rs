@@ -0,0 +1,674 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, iter,
+    path::Path,
+};
+
+use bevy_asset_loader::prelude::{StandardDynamicAsset, StandardDynamicAssetCollection};
+
+use super::{
+    hooks::hook_matches,
+    interpolate::script_namespaces,
+    runtime::parse_script_bytes,
+    speakers::{RatSpeakers, SPEAKERS_PATH},
+    types::{RatScript, RatTarget, parse_hook_call},
+};
+
+/// dynamic asset key the game loads scripts from
+const SCRIPTS_KEY: &str = "ratspinner.scripts";
+
+/// hook patterns with a registered handler. the linter runs without an app,
+/// so keep in sync with the `add_rat_hook` calls in `gameplay`
+pub const HANDLED_HOOKS: &[&str] = &[
+    "game.start",
+    "game.lure",
+    "game.kill",
+    "game.spare",
+    "door.open",
+];
+
+/// `options_from:` names with a registered provider, same deal as
+/// `HANDLED_HOOKS` but for the `add_rat_options` calls
+pub const OPTION_PROVIDERS: &[&str] = &["inventory", "inventory.items"];
+
+/// `{namespace:..}` placeholders with a resolver, for the `add_rat_resolver`
+/// calls
+pub const TEXT_RESOLVERS: &[&str] = &["item", "speaker"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RatLintSeverity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct RatLintIssue {
+    pub severity: RatLintSeverity,
+    pub file: String,
+    pub script_id: Option<String>,
+    pub node_id: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for RatLintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            RatLintSeverity::Warning => "warning",
+            RatLintSeverity::Error => "error",
+        };
+        write!(f, "{severity}: {}", self.file)?;
+        match (&self.script_id, &self.node_id) {
+            (Some(script), Some(node)) => write!(f, " [{script}/{node}]")?,
+            (Some(script), None) => write!(f, " [{script}]")?,
+            _ => {}
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RatLintReport {
+    pub issues: Vec<RatLintIssue>,
+}
+
+impl RatLintReport {
+    pub fn errors(&self) -> usize {
+        self.count(RatLintSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.count(RatLintSeverity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors() > 0
+    }
+
+    fn count(&self, severity: RatLintSeverity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    }
+
+    fn push(
+        &mut self,
+        severity: RatLintSeverity,
+        file: &str,
+        script: Option<&RatScript>,
+        node_id: Option<&str>,
+        message: String,
+    ) {
+        self.issues.push(RatLintIssue {
+            severity,
+            file: file.to_string(),
+            script_id: script.map(|script| script.id.clone()),
+            node_id: node_id.map(str::to_string),
+            message,
+        });
+    }
+}
+
+/// lints every script listed under `ratspinner.scripts` in
+/// `<assets_root>/default.assets.ron`
+pub fn lint_assets(assets_root: &Path) -> RatLintReport {
+    let mut report = RatLintReport::default();
+    let manifest = "default.assets.ron";
+
+    let paths = match read_script_paths(&assets_root.join(manifest)) {
+        Ok(paths) => paths,
+        Err(error) => {
+            report.push(RatLintSeverity::Error, manifest, None, None, error);
+            return report;
+        }
+    };
+    let speakers = match read_speakers(&assets_root.join(SPEAKERS_PATH)) {
+        Ok(speakers) => speakers,
+        Err(error) => {
+            report.push(RatLintSeverity::Error, SPEAKERS_PATH, None, None, error);
+            return report;
+        }
+    };
+
+    // parse everything first so `@script:node` targets can be resolved
+    let mut parsed: Vec<(&String, RatScript)> = Vec::new();
+    let mut seen_ids: HashMap<String, String> = HashMap::new();
+    for path in &paths {
+        let bytes = match std::fs::read(assets_root.join(path)) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                report.push(
+                    RatLintSeverity::Error,
+                    path,
+                    None,
+                    None,
+                    format!("failed to read script: {error}"),
+                );
+                continue;
+            }
+        };
+        let scripts = match parse_script_bytes(&bytes, Path::new(path), true, &speakers) {
+            Ok(scripts) => scripts,
+            Err(error) => {
+                report.push(RatLintSeverity::Error, path, None, None, error.to_string());
+                continue;
+            }
+        };
+
+        for script in scripts {
+            if let Some(other) = seen_ids.insert(script.id.clone(), path.clone()) {
+                report.push(
+                    RatLintSeverity::Error,
+                    path,
+                    Some(&script),
+                    None,
+                    format!("script id is also defined in {other}"),
+                );
+            }
+            parsed.push((path, script));
+        }
+    }
+
+    let scripts: HashMap<&str, &RatScript> = parsed
+        .iter()
+        .map(|(_, script)| (script.id.as_str(), script))
+        .collect();
+    for (path, script) in &parsed {
+        lint_script_with(script, path, Some(assets_root), Some(&scripts), &mut report);
+    }
+    lint_speakers(&speakers, assets_root, &scripts, &mut report);
+
+    report
+}
+
+/// portraits in the speaker set exist and default scripts are real
+fn lint_speakers(
+    speakers: &RatSpeakers,
+    assets_root: &Path,
+    scripts: &HashMap<&str, &RatScript>,
+    report: &mut RatLintReport,
+) {
+    for id in speakers.ids() {
+        let speaker = &speakers.0[id];
+        let mut push = |message| {
+            report.push(
+                RatLintSeverity::Error,
+                SPEAKERS_PATH,
+                None,
+                None,
+                format!("speaker '{id}' {message}"),
+            )
+        };
+        for portrait in iter::once(&speaker.portrait).chain(speaker.portraits.values()) {
+            if !assets_root.join(portrait).is_file() {
+                push(format!("has missing portrait '{portrait}'"));
+            }
+        }
+        if let Some(script) = &speaker.script
+            && !scripts.contains_key(script.as_str())
+        {
+            push(format!("points at missing script '{script}'"));
+        }
+    }
+}
+
+/// runs every per-script check. portraits and `sfx:` sounds are only checked
+/// when `assets_root` is given, `@script:node` targets are skipped since other
+/// scripts are unknown
+pub fn lint_script(
+    script: &RatScript,
+    file: &str,
+    assets_root: Option<&Path>,
+    report: &mut RatLintReport,
+) {
+    lint_script_with(script, file, assets_root, None, report);
+}
+
+fn lint_script_with(
+    script: &RatScript,
+    file: &str,
+    assets_root: Option<&Path>,
+    scripts: Option<&HashMap<&str, &RatScript>>,
+    report: &mut RatLintReport,
+) {
+    let mut node_ids: Vec<&String> = script.nodes.keys().collect();
+    node_ids.sort();
+
+    for node_id in &node_ids {
+        let node = &script.nodes[*node_id];
+        let mut push =
+            |severity, message| report.push(severity, file, Some(script), Some(node_id), message);
+
+        if let Some(next) = &node.next
+            && let Some(problem) = target_problem(next, script, scripts)
+        {
+            push(RatLintSeverity::Error, format!("`-> {next}` {problem}"));
+        }
+        if let Some(else_next) = &node.else_next
+            && let Some(problem) = target_problem(else_next, script, scripts)
+        {
+            push(
+                RatLintSeverity::Error,
+                format!("`else: {else_next}` {problem}"),
+            );
+        }
+        if let Some(timeout) = node.timeout_target()
+            && let Some(problem) = target_problem(timeout, script, scripts)
+        {
+            push(
+                RatLintSeverity::Error,
+                format!("`timeout: -> {timeout}` is no option id and {problem}"),
+            );
+        }
+        if let Some(timeout) = &node.timeout
+            && node.options.iter().any(|option| {
+                option.id.as_ref() == Some(&timeout.next) && !option.conditions.is_empty()
+            })
+        {
+            push(
+                RatLintSeverity::Error,
+                format!(
+                    "`timeout: -> {}` picks an option `[if: ...]` can hide, the countdown \
+                     would have nothing to pick",
+                    timeout.next
+                ),
+            );
+        }
+
+        let mut option_ids = HashSet::new();
+        for option in &node.options {
+            if let Some(next) = &option.next
+                && let Some(problem) = target_problem(next, script, scripts)
+            {
+                push(
+                    RatLintSeverity::Error,
+                    format!("option '{}' {problem}", option.text),
+                );
+            }
+            if let Some(id) = &option.id
+                && !option_ids.insert(id)
+            {
+                push(
+                    RatLintSeverity::Error,
+                    format!("duplicate option id '{id}'"),
+                );
+            }
+        }
+
+        if let Some(root) = assets_root
+            && !node.portrait_path.is_empty()
+            && !root.join(&node.portrait_path).is_file()
+        {
+            push(
+                RatLintSeverity::Error,
+                format!("portrait '{}' does not exist", node.portrait_path),
+            );
+        }
+        if let Some(root) = assets_root
+            && let Some(sfx) = &node.cues.sfx
+            && !root.join(format!("audio/{sfx}.ogg")).is_file()
+        {
+            push(
+                RatLintSeverity::Error,
+                format!("sfx 'audio/{sfx}.ogg' does not exist"),
+            );
+        }
+    }
+
+    let reachable = reachable_nodes(script);
+    let exits = nodes_with_exit(script);
+    for node_id in &node_ids {
+        if !reachable.contains(node_id.as_str()) {
+            report.push(
+                RatLintSeverity::Warning,
+                file,
+                Some(script),
+                Some(node_id),
+                "node is unreachable".to_string(),
+            );
+        }
+        if !exits.contains(node_id.as_str()) {
+            report.push(
+                RatLintSeverity::Error,
+                file,
+                Some(script),
+                Some(node_id),
+                "node has no exit, every path loops back forever".to_string(),
+            );
+        }
+    }
+    for cycle in silent_cycles(script) {
+        report.push(
+            RatLintSeverity::Error,
+            file,
+            Some(script),
+            Some(cycle[0]),
+            format!(
+                "silent nodes loop without showing a line: {} -> {}",
+                cycle.join(" -> "),
+                cycle[0]
+            ),
+        );
+    }
+
+    // one warning per hook, not per use
+    let mut hooks: Vec<String> = script
+        .nodes
+        .values()
+        .flat_map(|node| {
+            node.hooks
+                .iter()
+                .chain(node.options.iter().flat_map(|option| &option.hooks))
+        })
+        .filter_map(|raw| parse_hook_call(raw).ok().map(|(name, _)| name))
+        .filter(|hook| {
+            !HANDLED_HOOKS
+                .iter()
+                .any(|pattern| hook_matches(pattern, hook))
+        })
+        .collect();
+    hooks.sort_unstable();
+    hooks.dedup();
+    for hook in hooks {
+        report.push(
+            RatLintSeverity::Warning,
+            file,
+            Some(script),
+            None,
+            format!("hook '{hook}' is not handled by any system"),
+        );
+    }
+
+    let mut providers: Vec<&String> = script
+        .nodes
+        .values()
+        .flat_map(|node| &node.options_from)
+        .filter(|name| !OPTION_PROVIDERS.contains(&name.as_str()))
+        .collect();
+    providers.sort_unstable();
+    providers.dedup();
+    for provider in providers {
+        report.push(
+            RatLintSeverity::Error,
+            file,
+            Some(script),
+            None,
+            format!("option provider '{provider}' is not registered"),
+        );
+    }
+
+    let mut resolvers: Vec<String> = script_namespaces(script)
+        .into_iter()
+        .filter(|namespace| !TEXT_RESOLVERS.contains(&namespace.as_str()))
+        .collect();
+    resolvers.sort_unstable();
+    resolvers.dedup();
+    for resolver in resolvers {
+        report.push(
+            RatLintSeverity::Error,
+            file,
+            Some(script),
+            None,
+            format!("text resolver '{resolver}' is not registered"),
+        );
+    }
+}
+
+/// `speakers.ron` straight from disk, for tools running without the asset server
+pub fn read_speakers(path: &Path) -> Result<RatSpeakers, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|error| format!("failed to read speakers: {error}"))?;
+    ron::de::from_str(&content).map_err(|error| format!("failed to parse speakers: {error}"))
+}
+
+/// every script in `<assets_root>/default.assets.ron`, parsed leniently like
+/// the game does
+pub fn read_scripts(assets_root: &Path) -> Result<Vec<RatScript>, String> {
+    let speakers = read_speakers(&assets_root.join(SPEAKERS_PATH))?;
+    let mut scripts = Vec::new();
+    for path in read_script_paths(&assets_root.join("default.assets.ron"))? {
+        let bytes = std::fs::read(assets_root.join(&path))
+            .map_err(|error| format!("failed to read {path}: {error}"))?;
+        let parsed = parse_script_bytes(&bytes, Path::new(&path), false, &speakers)
+            .map_err(|error| error.to_string())?;
+        scripts.extend(parsed);
+    }
+    Ok(scripts)
+}
+
+fn read_script_paths(manifest: &Path) -> Result<Vec<String>, String> {
+    let content = std::fs::read_to_string(manifest)
+        .map_err(|error| format!("failed to read asset manifest: {error}"))?;
+    let collection = ron::de::from_str::<StandardDynamicAssetCollection>(&content)
+        .map_err(|error| format!("failed to parse asset manifest: {error}"))?;
+    match collection.0.get(SCRIPTS_KEY) {
+        Some(StandardDynamicAsset::Files { paths }) => Ok(paths.clone()),
+        Some(_) => Err(format!("'{SCRIPTS_KEY}' is not a `Files` entry")),
+        None => Err(format!("'{SCRIPTS_KEY}' is missing")),
+    }
+}
+
+/// what is wrong with a `next`/`->` target, if anything. `scripts` resolves
+/// `@script:node` refs, without it those are assumed fine
+fn target_problem(
+    target: &str,
+    script: &RatScript,
+    scripts: Option<&HashMap<&str, &RatScript>>,
+) -> Option<String> {
+    let node_ref = match RatTarget::parse(target) {
+        Ok(RatTarget::Goto(node_ref) | RatTarget::Call(node_ref)) => node_ref,
+        Ok(RatTarget::Return) => return None,
+        Err(error) => return Some(format!("is invalid: {error}")),
+    };
+    let target_script = match node_ref.script.as_deref() {
+        None => script,
+        Some(id) if id == script.id => script,
+        Some(id) => match scripts?.get(id) {
+            Some(other) => *other,
+            None => return Some(format!("points at missing script '{id}'")),
+        },
+    };
+    let node_id = node_ref.node.as_ref().unwrap_or(&target_script.entry);
+    (!target_script.nodes.contains_key(node_id))
+        .then(|| format!("points at missing node '{node_id}'"))
+}
+
+fn local_target(target: Option<&String>) -> Option<String> {
+    let target = RatTarget::parse(target?).ok()?;
+    target.local_node().map(str::to_string)
+}
+
+/// entry plus the `response_*` nodes the `inventory` provider can jump to.
+/// jumps into this script from elsewhere are not followed
+fn reachable_nodes(script: &RatScript) -> HashSet<&str> {
+    let mut queue: VecDeque<String> = VecDeque::from([script.entry.clone()]);
+    let shows_items = script
+        .nodes
+        .values()
+        .any(|node| node.options_from.iter().any(|name| name == "inventory"));
+    if shows_items {
+        queue.extend(
+            script
+                .nodes
+                .keys()
+                .filter(|id| id.starts_with("response_"))
+                .cloned(),
+        );
+    }
+
+    let mut reachable = HashSet::new();
+    while let Some(node_id) = queue.pop_front() {
+        let Some(node) = script.nodes.get(&node_id) else {
+            continue;
+        };
+        if !reachable.insert(node.id.as_str()) {
+            continue;
+        }
+        queue.extend(local_target(node.next.as_ref()));
+        queue.extend(local_target(node.else_next.as_ref()));
+        queue.extend(local_target(node.timeout_target()));
+        queue.extend(
+            node.options
+                .iter()
+                .filter_map(|option| local_target(option.next.as_ref())),
+        );
+    }
+    reachable
+}
+
+/// `next` chains of silent nodes that come back around, which the runtime
+/// would hop through until it gives up. each loop comes once, starting at its
+/// smallest node id
+fn silent_cycles(script: &RatScript) -> Vec<Vec<&str>> {
+    let mut ids: Vec<&str> = script.nodes.keys().map(String::as_str).collect();
+    ids.sort_unstable();
+    let mut cycles = Vec::new();
+    for start in ids {
+        let mut path = vec![start];
+        let mut current = &script.nodes[start];
+        while current.is_silent() {
+            let Some(next) = local_target(current.next.as_ref())
+                .and_then(|next| script.nodes.get_key_value(&next))
+            else {
+                break;
+            };
+            match path.iter().position(|id| *id == next.0) {
+                // loops that don't come back to `start` are found from their own nodes
+                Some(0) if path.iter().all(|id| *id >= start) => {
+                    cycles.push(path);
+                    break;
+                }
+                Some(_) => break,
+                None => {
+                    path.push(next.0);
+                    current = next.1;
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// nodes that can eventually close the dialogue. guards are ignored, so this
+/// is optimistic
+fn nodes_with_exit(script: &RatScript) -> HashSet<&str> {
+    let mut exits: HashSet<&str> = HashSet::new();
+    loop {
+        let before = exits.len();
+        for node in script.nodes.values() {
+            if exits.contains(node.id.as_str()) {
+                continue;
+            }
+            let leads_out = |next: Option<&String>| {
+                let Some(next) = next else {
+                    return true;
+                };
+                match RatTarget::parse(next) {
+                    // dangling targets close the dialogue at runtime, reported above
+                    Ok(RatTarget::Goto(node_ref)) if node_ref.script.is_none() => node_ref
+                        .node
+                        .as_deref()
+                        .is_none_or(|id| exits.contains(id) || !script.nodes.contains_key(id)),
+                    // other scripts, calls and returns are assumed to come back out
+                    _ => true,
+                }
+            };
+            let picks_exit = if node.options.is_empty() {
+                leads_out(node.next.as_ref())
+                    || (node.else_next.is_some() && leads_out(node.else_next.as_ref()))
+            } else {
+                node.options
+                    .iter()
+                    .any(|option| leads_out(option.next.as_ref().or(node.next.as_ref())))
+            };
+            let has_exit = picks_exit
+                || node
+                    .timeout_target()
+                    .is_some_and(|timeout| leads_out(Some(timeout)));
+            if has_exit {
+                exits.insert(node.id.as_str());
+            }
+        }
+        if exits.len() == before {
+            return exits;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeouts_on_gated_options_are_linted() {
+        let source = "\
+// script: interrogation
+// entry: alibi
+
+[alibi]
+text: where were you at nine?
+timeout: 6 -> silence
+> at home. [id: home]
+> ... [id: silence] [if: !lawyer_present]
+";
+        let scripts = parse_script_bytes(
+            source.as_bytes(),
+            Path::new("interrogation.rat"),
+            true,
+            &RatSpeakers::default(),
+        )
+        .expect("script should parse");
+        let mut report = RatLintReport::default();
+        lint_script(&scripts[0], "interrogation.rat", None, &mut report);
+        assert!(
+            report
+                .issues
+                .iter()
+                .any(|issue| issue.message.contains("`[if: ...]` can hide")),
+            "{:?}",
+            report.issues
+        );
+    }
+
+    #[test]
+    fn silent_loops_are_linted() {
+        let source = "\
+// script: stairwell
+// entry: landing
+
+[landing]
+text: the stairs go on.
+> down. -> down
+
+[down]
+inc: floors
+-> turn
+
+[turn]
+set: dizzy = true
+-> down
+";
+        let scripts = parse_script_bytes(
+            source.as_bytes(),
+            Path::new("stairwell.rat"),
+            true,
+            &RatSpeakers::default(),
+        )
+        .expect("script should parse");
+        let mut report = RatLintReport::default();
+        lint_script(&scripts[0], "stairwell.rat", None, &mut report);
+        let silent: Vec<String> = report
+            .issues
+            .iter()
+            .filter(|issue| issue.message.starts_with("silent nodes loop"))
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            silent,
+            [
+                "error: stairwell.rat [stairwell/down]: silent nodes loop without showing a line: \
+                 down -> turn -> down"
+            ]
+        );
+    }
+}
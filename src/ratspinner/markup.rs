@@ -0,0 +1,274 @@
+use crate::voice::SpeakSegment;
+
+/// span tags, closed with `{/tag}` or running to the end of the line
+const SPAN_TAGS: [&str; 4] = ["speed", "glitch", "shake", "whisper"];
+
+/// style of one revealed character
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatTextStyle {
+    /// reveal and speech rate, `{speed=0.5}` is half as fast
+    pub speed: f32,
+    pub glitch: bool,
+    pub shake: bool,
+    pub whisper: bool,
+}
+
+impl Default for RatTextStyle {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            glitch: false,
+            shake: false,
+            whisper: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatGlyph {
+    pub ch: char,
+    pub style: RatTextStyle,
+    /// seconds to hold before this character, from `{pause=..}`
+    pub pause: f32,
+}
+
+/// node text with `{pause=0.5}`, `{speed=0.5}..{/speed}`, `{glitch}..{/glitch}`,
+/// `{shake}..{/shake}` and `{whisper}..{/whisper}` resolved per character.
+/// `{{` is a literal brace
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RatMarkup {
+    pub glyphs: Vec<RatGlyph>,
+    /// pause after the last character
+    pub trailing_pause: f32,
+}
+
+impl RatMarkup {
+    /// fails on the first unknown or malformed tag
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let (markup, errors) = parse_markup(raw);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(markup),
+        }
+    }
+
+    /// bad tags are kept as plain text, for display
+    pub fn lenient(raw: &str) -> Self {
+        parse_markup(raw).0
+    }
+
+    pub fn plain(&self) -> String {
+        self.glyphs.iter().map(|glyph| glyph.ch).collect()
+    }
+
+    pub fn total_pause(&self) -> f32 {
+        self.glyphs.iter().map(|glyph| glyph.pause).sum::<f32>() + self.trailing_pause
+    }
+
+    /// runs of equally styled text for the voice, split at pauses
+    pub fn speak_segments(&self) -> Vec<SpeakSegment> {
+        let mut segments: Vec<SpeakSegment> = Vec::new();
+        for glyph in &self.glyphs {
+            let style = glyph.style;
+            match segments.last_mut() {
+                Some(last)
+                    if glyph.pause <= 0.0
+                        && last.speed == style.speed
+                        && last.whisper == style.whisper
+                        && last.glitch == style.glitch =>
+                {
+                    last.text.push(glyph.ch);
+                }
+                _ => segments.push(SpeakSegment {
+                    text: glyph.ch.to_string(),
+                    pause_before: glyph.pause,
+                    speed: style.speed,
+                    whisper: style.whisper,
+                    glitch: style.glitch,
+                }),
+            }
+        }
+        if self.trailing_pause > 0.0 {
+            segments.push(SpeakSegment::new("").pause_before(self.trailing_pause));
+        }
+        segments
+    }
+}
+
+/// `{pause=..}`, `{/speed}`, `{glitch}` and so on, anything else in braces is
+/// a placeholder filled before the markup is read
+pub(super) fn is_markup_tag(tag: &str) -> bool {
+    let name = tag.split_once('=').map_or(tag, |(name, _)| name).trim();
+    let name = name.strip_prefix('/').unwrap_or(name);
+    name == "pause" || name == "speed" || SPAN_TAGS.contains(&name)
+}
+
+fn parse_markup(raw: &str) -> (RatMarkup, Vec<String>) {
+    let mut markup = RatMarkup::default();
+    let mut errors = Vec::new();
+    // enclosing speeds, so `{/speed}` restores the outer one
+    let mut speeds: Vec<f32> = Vec::new();
+    let mut style = RatTextStyle::default();
+    let mut pause = 0.0;
+    let mut rest = raw;
+
+    while let Some(start) = rest.find('{') {
+        push_text(&mut markup, &rest[..start], style, &mut pause);
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix('{') {
+            push_text(&mut markup, "{", style, &mut pause);
+            rest = escaped;
+            continue;
+        }
+        let Some(end) = after.find('}') else {
+            errors.push(format!("unclosed tag '{}'", &rest[start..]));
+            push_text(&mut markup, &rest[start..], style, &mut pause);
+            rest = "";
+            break;
+        };
+        let literal = &rest[start..start + end + 2];
+        let tag = after[..end].trim();
+        rest = &after[end + 1..];
+
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (tag, None),
+        };
+        let result = match (name, value) {
+            ("pause", Some(value)) => parse_seconds(value).map(|secs| pause += secs),
+            ("speed", Some(value)) => match parse_seconds(value) {
+                Ok(speed) if speed > 0.0 => {
+                    speeds.push(style.speed);
+                    style.speed = speed;
+                    Ok(())
+                }
+                Ok(_) => Err("speed must be above 0".to_string()),
+                Err(error) => Err(error),
+            },
+            ("/speed", None) => match speeds.pop() {
+                Some(outer) => {
+                    style.speed = outer;
+                    Ok(())
+                }
+                None => Err("'{/speed}' without an open '{speed=..}'".to_string()),
+            },
+            (tag, None) if SPAN_TAGS.contains(&tag.trim_start_matches('/')) => {
+                let open = !tag.starts_with('/');
+                match tag.trim_start_matches('/') {
+                    "glitch" => style.glitch = open,
+                    "shake" => style.shake = open,
+                    "whisper" => style.whisper = open,
+                    _ => {}
+                }
+                Ok(())
+            }
+            ("pause" | "speed", None) => Err(format!("'{literal}' needs a value")),
+            _ => Err(format!(
+                "unknown tag '{literal}', expected pause, {}",
+                SPAN_TAGS.join(", ")
+            )),
+        };
+        if let Err(error) = result {
+            errors.push(error);
+            push_text(&mut markup, literal, style, &mut pause);
+        }
+    }
+    push_text(&mut markup, rest, style, &mut pause);
+    markup.trailing_pause = pause;
+    (markup, errors)
+}
+
+fn push_text(markup: &mut RatMarkup, text: &str, style: RatTextStyle, pause: &mut f32) {
+    for ch in text.chars() {
+        markup.glyphs.push(RatGlyph {
+            ch,
+            style,
+            pause: std::mem::take(pause),
+        });
+    }
+}
+
+fn parse_seconds(raw: &str) -> Result<f32, String> {
+    raw.parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite() && *value >= 0.0)
+        .ok_or_else(|| format!("'{raw}' is not a valid number"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles_cover_each_character() {
+        let markup = RatMarkup::parse("a{glitch}b{/glitch}{pause=0.5}c{whisper}de{pause=0.25}")
+            .expect("markup should parse");
+        assert_eq!(markup.plain(), "abcde");
+        let glitched: Vec<bool> = markup
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.style.glitch)
+            .collect();
+        assert_eq!(glitched, [false, true, false, false, false]);
+        let whispered: Vec<bool> = markup
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.style.whisper)
+            .collect();
+        assert_eq!(
+            whispered,
+            [false, false, false, true, true],
+            "spans run to the end"
+        );
+        assert_eq!(markup.glyphs[2].pause, 0.5);
+        assert_eq!(markup.trailing_pause, 0.25);
+        assert_eq!(markup.total_pause(), 0.75);
+
+        // `{/speed}` goes back to the enclosing speed
+        let speeds: Vec<f32> = RatMarkup::parse("{speed=0.5}a{speed=2}b{/speed}c{/speed}d")
+            .expect("markup should parse")
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.style.speed)
+            .collect();
+        assert_eq!(speeds, [0.5, 2.0, 0.5, 1.0]);
+
+        // the voice gets a segment per style run, split at pauses too
+        let segments: Vec<(String, bool, bool)> = markup
+            .speak_segments()
+            .into_iter()
+            .map(|segment| (segment.text, segment.glitch, segment.whisper))
+            .collect();
+        assert_eq!(
+            segments,
+            [
+                ("a".to_string(), false, false),
+                ("b".to_string(), true, false),
+                ("c".to_string(), false, false),
+                ("de".to_string(), false, true),
+                (String::new(), false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_and_bad_tags() {
+        let markup = RatMarkup::parse("{{glitch} stays {{ plain }").expect("escapes should parse");
+        assert_eq!(markup.plain(), "{glitch} stays { plain }");
+        assert!(markup.glyphs.iter().all(|glyph| !glyph.style.glitch));
+
+        for (raw, error) in [
+            ("oops {pause=1", "unclosed tag '{pause=1'"),
+            ("{bold}hi", "unknown tag '{bold}'"),
+            ("{pause}hi", "'{pause}' needs a value"),
+            ("{speed=0}hi", "speed must be above 0"),
+            ("{pause=soon}hi", "'soon' is not a valid number"),
+            ("hi{/speed}", "'{/speed}' without an open '{speed=..}'"),
+        ] {
+            let parsed = RatMarkup::parse(raw).expect_err(raw);
+            assert!(parsed.starts_with(error), "{raw}: {parsed}");
+            // shown as written when it can't be read
+            assert_eq!(RatMarkup::lenient(raw).plain(), raw);
+        }
+    }
+}
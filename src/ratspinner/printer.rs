@@ -0,0 +1,347 @@
+use std::path::Path;
+
+use ron::ser::PrettyConfig;
+
+use super::{
+    runtime::parse_script_bytes,
+    speakers::RatSpeakers,
+    types::{RatNode, RatOption, RatScript, RatScriptRon, RatVariantMode},
+    vars::RatCondition,
+};
+use crate::voice::VoicePreset;
+
+/// parses a .rat, .ron or .yarn file in strict mode, so formatting it never
+/// drops a line the parser only warned about
+pub fn parse_script_file(
+    bytes: &[u8],
+    path: &Path,
+    speakers: &RatSpeakers,
+) -> Result<Vec<RatScript>, String> {
+    parse_script_bytes(bytes, path, true, speakers).map_err(|error| error.to_string())
+}
+
+/// canonical .rat text, one `// script:` section per script with nodes in
+/// the order they were written. speaker ids are kept, portraits and voices
+/// only show up where they differ from the speaker's
+pub fn write_rat(scripts: &[RatScript], speakers: &RatSpeakers) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for script in scripts {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(format!("// script: {}", script.id));
+        lines.push(format!("// entry: {}", script.entry));
+        for node in script.ordered_nodes() {
+            lines.push(String::new());
+            write_node(&mut lines, node, speakers);
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// pretty RON for one script, the same shape `npc.default.ron` uses. like
+/// `write_rat`, speaker ids stand in for the name, portrait and voice they
+/// bring along
+pub fn write_ron(script: &RatScript, speakers: &RatSpeakers) -> Result<String, String> {
+    let mut raw = RatScriptRon::from(script);
+    for node in &mut raw.nodes {
+        // what reading the node back fills in when a field is left out
+        let def = node.speaker_id.as_deref().and_then(|id| speakers.get(id));
+        let name = def.map_or("", |def| &def.name);
+        let portrait = def.map_or("", |def| def.default_portrait(node.cues.emote.as_deref()));
+        let voice = def.map_or(VoicePreset::NeutralNpc, |def| def.voice.into());
+        let tuning = def.map(|def| def.tuning).unwrap_or_default();
+        node.speaker.take_if(|speaker| *speaker == name);
+        node.portrait_path.take_if(|path| *path == portrait);
+        node.voice.take_if(|ron| VoicePreset::from(*ron) == voice);
+        node.voice_tuning.take_if(|ron| *ron == tuning);
+    }
+    ron::ser::to_string_pretty(&raw, PrettyConfig::default())
+        .map(|text| text + "\n")
+        .map_err(|error| format!("failed to write RON: {error}"))
+}
+
+fn write_node(lines: &mut Vec<String>, node: &RatNode, speakers: &RatSpeakers) {
+    if !node.comments.is_empty() {
+        lines.extend(node.comments.iter().map(|comment| key_line("//", comment)));
+        lines.push(String::new());
+    }
+    lines.push(format!("[{}]", node.id));
+
+    let def = node.speaker_id.as_deref().and_then(|id| speakers.get(id));
+    match &node.speaker_id {
+        Some(speaker_id) => lines.push(key_line("speaker:", speaker_id)),
+        None if !node.speaker.is_empty() => lines.push(key_line("speaker:", &node.speaker)),
+        None => {}
+    }
+    // `emote:` brings its own portrait, which needs no `portrait:` line
+    let default_portrait = def.map_or("", |def| def.default_portrait(node.cues.emote.as_deref()));
+    if node.portrait_path != default_portrait {
+        // a key from the speaker's portrait set reads better than the path
+        let key = def.and_then(|def| {
+            def.portraits
+                .iter()
+                .filter(|(_, path)| **path == node.portrait_path)
+                .map(|(key, _)| key.as_str())
+                .min()
+        });
+        lines.push(key_line("portrait:", key.unwrap_or(&node.portrait_path)));
+    }
+    if node.voice != def.map_or(VoicePreset::NeutralNpc, |def| def.voice.into()) {
+        lines.push(key_line("voice:", node.voice.name()));
+    }
+    if let Some(emote) = &node.cues.emote {
+        lines.push(key_line("emote:", emote));
+    }
+
+    if !node.text.is_empty() || !node.variants.is_empty() {
+        for text in std::iter::once(&node.text).chain(&node.variants) {
+            lines.push(key_line("text:", text));
+        }
+    }
+    if !node.variants.is_empty() || node.variant_mode != RatVariantMode::default() {
+        lines.push(key_line("variants:", node.variant_mode.as_str()));
+    }
+    if !node.when.is_empty() {
+        lines.push(key_line("when:", &join_conditions(&node.when)));
+    }
+    if let Some(else_next) = &node.else_next {
+        lines.push(key_line("else:", else_next));
+    }
+    lines.extend(node.effects.iter().map(ToString::to_string));
+    lines.extend(node.hooks.iter().map(|hook| key_line("hook:", hook)));
+    for (key, cue) in [
+        ("anim:", &node.cues.anim),
+        ("look:", &node.cues.look),
+        ("sfx:", &node.cues.sfx),
+    ] {
+        if let Some(cue) = cue {
+            lines.push(key_line(key, cue));
+        }
+    }
+    lines.extend(node.options.iter().map(option_line));
+    if !node.options_from.is_empty() {
+        lines.push(key_line("options_from:", &node.options_from.join(", ")));
+    }
+    if let Some(timeout) = &node.timeout {
+        lines.push(key_line("timeout:", &timeout.to_string()));
+    }
+    if let Some(next) = &node.next {
+        lines.push(key_line("->", next));
+    }
+}
+
+/// `> text -> target [id: ..] [if: ..] [set: ..] [hook: ..]`
+fn option_line(option: &RatOption) -> String {
+    let mut line = key_line(">", &option.text);
+    if let Some(next) = &option.next {
+        line.push_str(&format!(" -> {next}"));
+    }
+    if let Some(id) = &option.id {
+        line.push_str(&format!(" [id: {id}]"));
+    }
+    if !option.conditions.is_empty() {
+        line.push_str(&format!(" [if: {}]", join_conditions(&option.conditions)));
+    }
+    for effect in &option.effects {
+        line.push_str(&format!(" [{effect}]"));
+    }
+    for hook in &option.hooks {
+        line.push_str(&format!(" [hook: {hook}]"));
+    }
+    line
+}
+
+fn join_conditions(conditions: &[RatCondition]) -> String {
+    let conditions: Vec<String> = conditions.iter().map(ToString::to_string).collect();
+    conditions.join(" && ")
+}
+
+/// `key value`, without a trailing space for empty values
+fn key_line(key: &str, value: &str) -> String {
+    if value.is_empty() {
+        key.to_string()
+    } else {
+        format!("{key} {value}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::ratspinner::{lint::read_speakers, speakers::SPEAKERS_PATH};
+
+    fn assets_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")
+    }
+
+    fn speakers() -> RatSpeakers {
+        read_speakers(&assets_dir().join(SPEAKERS_PATH)).expect("speakers.ron should load")
+    }
+
+    /// scripts with `extension` in `assets/ratspinner`, speakers.ron left out
+    fn script_paths(extension: &str) -> Vec<PathBuf> {
+        let dir = assets_dir().join("ratspinner");
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .unwrap_or_else(|error| panic!("failed to read {}: {error}", dir.display()))
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension().is_some_and(|ext| ext == extension)
+                    && !path.ends_with(SPEAKERS_PATH)
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    fn parse(path: &Path, bytes: &[u8], speakers: &RatSpeakers) -> Vec<RatScript> {
+        parse_script_file(bytes, path, speakers).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    #[test]
+    fn rat_files_round_trip() {
+        let speakers = speakers();
+        let paths = script_paths("rat");
+        assert!(!paths.is_empty(), "no .rat files in assets/ratspinner");
+
+        for path in &paths {
+            let bytes = std::fs::read(path).expect("failed to read script");
+            let scripts = parse(path, &bytes, &speakers);
+            let text = write_rat(&scripts, &speakers);
+            let reparsed = parse(path, text.as_bytes(), &speakers);
+            assert_eq!(
+                reparsed,
+                scripts,
+                "{} changed on the way through:\n{text}",
+                path.display()
+            );
+            assert_eq!(
+                write_rat(&reparsed, &speakers),
+                text,
+                "{} formats differently the second time",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn ron_files_round_trip() {
+        let speakers = speakers();
+        let paths = script_paths("ron");
+        assert!(!paths.is_empty(), "no .ron scripts in assets/ratspinner");
+
+        for path in &paths {
+            let bytes = std::fs::read(path).expect("failed to read script");
+            let scripts = parse(path, &bytes, &speakers);
+
+            let ron = write_ron(&scripts[0], &speakers).expect("script should serialize");
+            assert_eq!(parse(path, ron.as_bytes(), &speakers), scripts);
+
+            // plain speaker names survive the trip through .rat too
+            let text = write_rat(&scripts, &speakers);
+            let rat_path = path.with_extension("rat");
+            assert_eq!(
+                parse(&rat_path, text.as_bytes(), &speakers),
+                scripts,
+                "{} changed as .rat:\n{text}",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn rat_and_ron_copies_of_npc_default_match() {
+        let speakers = speakers();
+        let dir = assets_dir().join("ratspinner");
+        let read = |name: &str| {
+            let path = dir.join(name);
+            let bytes = std::fs::read(&path).expect("failed to read script");
+            parse(&path, &bytes, &speakers)
+        };
+
+        let from_rat = read("npc.default.rat");
+        let from_ron = read("npc.default.ron");
+        // speaker ids included, both copies load into the same script
+        assert_eq!(from_rat, from_ron);
+    }
+
+    #[test]
+    fn speakers_and_tuning_survive_the_trip_to_ron() {
+        let mut speakers = speakers();
+        let leaning = speakers
+            .0
+            .get_mut("marcus.leaning")
+            .expect("marcus.leaning should be a speaker");
+        leaning.tuning.pitch_hz = Some(96.0);
+        let leaning = leaning.clone();
+
+        let source = "\
+// script: wall
+// entry: lean
+
+[lean]
+speaker: marcus.leaning
+text: just me here.
+-> shout
+
+[shout]
+speaker: marcus.leaning
+voice: hostile_entity
+text: GET OUT.
+";
+        let path = Path::new("wall.rat");
+        let scripts = parse(path, source.as_bytes(), &speakers);
+        let node = &scripts[0].nodes["lean"];
+        assert_eq!(node.speaker_id.as_deref(), Some("marcus.leaning"));
+        assert_eq!(node.speaker, leaning.name);
+        assert_eq!(node.portrait_path, leaning.portrait);
+        assert_eq!(node.voice_tuning, leaning.tuning);
+
+        let ron = write_ron(&scripts[0], &speakers).expect("script should serialize");
+        assert!(
+            ron.contains("speaker_id: Some(\"marcus.leaning\")"),
+            "{ron}"
+        );
+        // the rest comes back from speakers.ron, only the override is written
+        for resolved in ["speaker:", "portrait_path:", "pitch_hz:"] {
+            assert!(!ron.contains(resolved), "{ron}");
+        }
+        assert_eq!(
+            ron.matches("voice: Some(hostile_entity)").count(),
+            1,
+            "{ron}"
+        );
+        let from_ron = parse(&path.with_extension("ron"), ron.as_bytes(), &speakers);
+        assert_eq!(from_ron, scripts);
+        // and back to .rat as the speaker id, not the name it resolved to
+        assert!(write_rat(&from_ron, &speakers).contains("speaker: marcus.leaning\n"));
+    }
+
+    #[test]
+    fn unknown_speaker_ids_are_rejected() {
+        let error = parse_script_file(
+            b"[start]\nspeaker: marcus.leening\ntext: hi.\n",
+            Path::new("typo.rat"),
+            &speakers(),
+        )
+        .expect_err("marcus.leening is not in speakers.ron");
+        assert!(
+            error.starts_with("unknown speaker 'marcus.leening' at typo.rat:2:10"),
+            "{error}"
+        );
+        assert!(error.contains("did you mean 'marcus.leaning'?"), "{error}");
+
+        // names with spaces are plain names, nothing to look up
+        let scripts = parse(
+            Path::new("plain.rat"),
+            b"[start]\nspeaker: the night clerk\ntext: hi.\n",
+            &speakers(),
+        );
+        let node = &scripts[0].nodes["start"];
+        assert_eq!(node.speaker, "the night clerk");
+        assert_eq!(node.speaker_id, None);
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use super::{
+    interpolate::RatTextResolvers,
+    runtime::visible_options,
+    speakers::RatSpeakers,
+    types::{RatNode, RatOption, RatScript},
+    vars::{RatEffect, RatValue, RatVariables},
+};
+use crate::{
+    assets::ItemMeta,
+    ui::{
+        DiscoveryEntry, DiscoveryInteraction, DiscoveryInteractionAction,
+        DiscoveryInteractionActor, DiscoveryKind, UiDialogueOption, UiDialoguePreview,
+        UiDiscoveryCommand, UiDiscoveryDb,
+    },
+};
+
+/// the item last picked from `options_from: inventory`
+pub const SHOWN_ITEM_VAR: &str = "shown_item";
+/// whether that item was shown to the speaker before
+pub const SHOWN_ITEM_SHARED_VAR: &str = "shown_item_shared";
+/// answers every item without a `response_<item id>` node of its own
+const ITEM_RESPONSE_NODE: &str = "response_item";
+
+/// what a provider gets to look at when the node is drawn or picked from,
+/// text resolvers get the same
+pub(crate) struct RatOptionContext<'a> {
+    pub script: &'a RatScript,
+    pub node: &'a RatNode,
+    pub variables: &'a RatVariables,
+    pub discovery_db: &'a UiDiscoveryDb,
+    pub speakers: &'a RatSpeakers,
+    /// `.item.meta` assets by item id
+    pub items: &'a HashMap<String, ItemMeta>,
+    pub resolvers: &'a RatTextResolvers,
+}
+
+/// what picking an option does
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RatOptionAction {
+    /// a `next`/`->` target, `None` ends the dialogue
+    Goto(Option<String>),
+    /// the speaker answers with this line, the next pick goes back to the node
+    Reply(String),
+    /// lists `provider`'s options in the picker, `back` returns to the node
+    Menu {
+        provider: String,
+        title: String,
+        prompt: String,
+    },
+}
+
+/// one option on screen. written options are turned into these too, so the
+/// list the ui shows and the one `RatCommand::Choose` indexes are the same
+#[derive(Debug, Clone)]
+pub(crate) struct RatProvidedOption {
+    pub text: String,
+    pub id: Option<String>,
+    pub action: RatOptionAction,
+    pub hooks: Vec<String>,
+    pub effects: Vec<RatEffect>,
+    /// written when the option is picked
+    pub discovery: Vec<UiDiscoveryCommand>,
+    pub preview: Option<UiDialoguePreview>,
+    pub item_id: Option<String>,
+    /// picked before, or for items already shown to this speaker
+    pub seen: bool,
+    /// disabled options are shown but picking them does nothing
+    pub enabled: bool,
+}
+
+impl RatProvidedOption {
+    pub fn new(text: impl Into<String>, action: RatOptionAction) -> Self {
+        Self {
+            text: text.into(),
+            id: None,
+            action,
+            hooks: Vec::new(),
+            effects: Vec::new(),
+            discovery: Vec::new(),
+            preview: None,
+            item_id: None,
+            seen: false,
+            enabled: true,
+        }
+    }
+
+    /// a `>` line from the script, falling back to the node's `->`
+    pub(super) fn written(option: &RatOption, node: &RatNode) -> Self {
+        Self {
+            id: option.id.clone(),
+            hooks: option.hooks.clone(),
+            effects: option.effects.clone(),
+            ..Self::new(
+                option.text.clone(),
+                RatOptionAction::Goto(option.next.clone().or_else(|| node.next.clone())),
+            )
+        }
+    }
+
+    pub(super) fn ui_option(&self) -> UiDialogueOption {
+        UiDialogueOption {
+            text: self.text.clone(),
+            preview: self.preview.clone(),
+            item_id: self.item_id.clone(),
+            seen: self.seen,
+            enabled: self.enabled,
+        }
+    }
+}
+
+type RatOptionProvider = Box<dyn Fn(&RatOptionContext) -> Vec<RatProvidedOption> + Send + Sync>;
+
+/// option providers keyed by the name nodes use in `options_from:`
+#[derive(Resource, Default)]
+pub(crate) struct RatOptionProviders {
+    providers: HashMap<String, RatOptionProvider>,
+}
+
+impl RatOptionProviders {
+    pub fn provides(&self, name: &str) -> bool {
+        self.providers.contains_key(name)
+    }
+
+    /// what `name` offers right now, nothing if it isn't registered
+    pub(super) fn options(&self, name: &str, ctx: &RatOptionContext) -> Vec<RatProvidedOption> {
+        self.providers
+            .get(name)
+            .map(|provider| provider(ctx))
+            .unwrap_or_default()
+    }
+
+    /// the node's visible `>` options, then whatever its providers add
+    pub(super) fn node_options(&self, ctx: &RatOptionContext) -> Vec<RatProvidedOption> {
+        let mut options: Vec<RatProvidedOption> = visible_options(ctx.node, ctx.variables)
+            .into_iter()
+            .map(|option| {
+                let mut written = RatProvidedOption::written(option, ctx.node);
+                written.seen = ctx.variables.seen().chose(
+                    &ctx.script.id,
+                    &ctx.node.id,
+                    option.id.as_deref().unwrap_or(&option.text),
+                );
+                written
+            })
+            .collect();
+        for name in &ctx.node.options_from {
+            options.extend(self.options(name, ctx));
+        }
+        options
+    }
+
+    /// logs every `options_from:` name in `scripts` that nothing provides
+    pub(super) fn warn_unknown<'a>(&self, scripts: impl IntoIterator<Item = &'a RatScript>) {
+        let mut unknown: Vec<&str> = scripts
+            .into_iter()
+            .flat_map(|script| script.nodes.values())
+            .flat_map(|node| &node.options_from)
+            .map(String::as_str)
+            .filter(|name| !self.provides(name))
+            .collect();
+        unknown.sort_unstable();
+        unknown.dedup();
+        if !unknown.is_empty() {
+            warn!(
+                "ratspinner option providers not registered: {}",
+                unknown.join(", ")
+            );
+        }
+    }
+}
+
+pub(crate) trait RatOptionsAppExt {
+    /// lets nodes with `options_from: <name>` show the options `provider` returns
+    fn add_rat_options(
+        &mut self,
+        name: impl Into<String>,
+        provider: impl Fn(&RatOptionContext) -> Vec<RatProvidedOption> + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl RatOptionsAppExt for App {
+    fn add_rat_options(
+        &mut self,
+        name: impl Into<String>,
+        provider: impl Fn(&RatOptionContext) -> Vec<RatProvidedOption> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<RatOptionProviders>()
+            .providers
+            .insert(name.into(), Box::new(provider));
+        self
+    }
+}
+
+/// `options_from: inventory`, a "Show item..." entry opening the item picker
+pub(super) fn inventory_options(ctx: &RatOptionContext) -> Vec<RatProvidedOption> {
+    let picker = RatOptionAction::Menu {
+        provider: "inventory.items".to_string(),
+        title: "inventory".to_string(),
+        prompt: "pick an item to show".to_string(),
+    };
+    vec![RatProvidedOption {
+        enabled: !ctx.discovery_db.entries(DiscoveryKind::Item).is_empty(),
+        ..RatProvidedOption::new("Show item...", picker)
+    }]
+}
+
+/// the item picker. items jump to the script's `response_<item id>` node if
+/// it has one, then to its `response_item` node, otherwise the speaker shrugs
+/// it off. picking one sets `shown_item` and `shown_item_shared` for the line
+/// that answers it
+pub(super) fn inventory_item_options(ctx: &RatOptionContext) -> Vec<RatProvidedOption> {
+    let speaker = &ctx.node.speaker;
+    ctx.discovery_db
+        .entries(DiscoveryKind::Item)
+        .iter()
+        .map(|item| {
+            let shared =
+                ctx.discovery_db
+                    .was_item_shared_with_speaker(&item.id, &ctx.script.id, speaker);
+            let response = format!("response_{}", item.id);
+            let (action, discovery) = if ctx.script.nodes.contains_key(&response) {
+                (RatOptionAction::Goto(Some(response)), Vec::new())
+            } else if ctx.script.nodes.contains_key(ITEM_RESPONSE_NODE) {
+                (
+                    RatOptionAction::Goto(Some(ITEM_RESPONSE_NODE.to_string())),
+                    item_shared(ctx, item),
+                )
+            } else {
+                let line = if shared {
+                    "hmm... {item:shown.name}. i remember this"
+                } else {
+                    "hmm... {item:shown.name}. what is this?"
+                };
+                (
+                    RatOptionAction::Reply(line.to_string()),
+                    item_shared(ctx, item),
+                )
+            };
+            RatProvidedOption {
+                id: Some(item.id.clone()),
+                hooks: vec!["dialogue.show_item".to_string()],
+                effects: vec![
+                    RatEffect::set(SHOWN_ITEM_VAR, RatValue::Str(item.id.clone())),
+                    RatEffect::set(SHOWN_ITEM_SHARED_VAR, RatValue::Bool(shared)),
+                ],
+                discovery,
+                preview: Some(item_preview(item)),
+                item_id: Some(item.id.clone()),
+                seen: shared,
+                ..RatProvidedOption::new(item.title.clone(), action)
+            }
+        })
+        .collect()
+}
+
+/// marks the item seen and remembers who it was shown to
+fn item_shared(ctx: &RatOptionContext, item: &DiscoveryEntry) -> Vec<UiDiscoveryCommand> {
+    vec![
+        UiDiscoveryCommand::SetSeen {
+            kind: DiscoveryKind::Item,
+            id: item.id.clone(),
+            seen: true,
+        },
+        UiDiscoveryCommand::RecordInteraction {
+            interaction: DiscoveryInteraction::new(
+                DiscoveryKind::Item,
+                item.id.clone(),
+                DiscoveryInteractionAction::Shared,
+                DiscoveryInteractionActor::Speaker(ctx.node.speaker.clone()),
+            )
+            .script(ctx.script.id.clone())
+            .node(ctx.node.id.clone())
+            .option(item.id.clone())
+            .note("dialogue.show_item"),
+        },
+    ]
+}
+
+fn item_preview(item: &DiscoveryEntry) -> UiDialoguePreview {
+    UiDialoguePreview {
+        title: item.title.clone(),
+        subtitle: item.subtitle.clone(),
+        description: item.description.clone(),
+        image_path: item.image_path.clone(),
+        model_path: item.model_path.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::ratspinner::{harness::RatHarness, types::RatStart, vars::RatValue};
+
+    fn texts(harness: &RatHarness) -> Vec<String> {
+        harness
+            .options()
+            .into_iter()
+            .map(|option| option.text)
+            .collect()
+    }
+
+    fn shipped() -> RatHarness {
+        let assets_root = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        RatHarness::from_assets(&assets_root).expect("assets should load")
+    }
+
+    #[test]
+    fn inventory_provider_shows_items() {
+        let mut harness = shipped();
+        harness.start(RatStart::new("npc.dunce"));
+        assert_eq!(
+            texts(&harness).last().map(String::as_str),
+            Some("Show item...")
+        );
+
+        // nothing to show yet, picking it does nothing
+        harness
+            .choose_option("Show item...")
+            .expect("entry is listed");
+        assert_eq!(
+            harness.current(),
+            Some(("npc.dunce".into(), "greeting".into()))
+        );
+        assert!(texts(&harness).contains(&"Show item...".to_string()));
+
+        harness.give_item("cd", "CD").give_item("fork", "Fork");
+        harness
+            .choose_option("Show item...")
+            .expect("entry is listed");
+        assert_eq!(texts(&harness), ["Fork", "CD"], "newest item first");
+        harness.choose_option("cd").expect("cd is in the picker");
+        assert_eq!(
+            harness.current(),
+            Some(("npc.dunce".into(), "response_cd".into()))
+        );
+        assert!(harness.transcript().fired("dialogue.show_item"));
+
+        // no `response_fork`, the speaker answers in place and `back` returns
+        harness
+            .choose_option("Show item...")
+            .expect("entry is listed");
+        harness
+            .choose_option("fork")
+            .expect("fork is in the picker");
+        assert_eq!(
+            harness.current(),
+            Some(("npc.dunce".into(), "response_cd".into()))
+        );
+        assert!(harness.options().is_empty());
+        harness.choose(0);
+        assert_eq!(texts(&harness), ["Show item..."]);
+    }
+
+    #[test]
+    fn shown_items_without_a_response_use_the_script_fallback() {
+        let mut harness = shipped();
+        harness.give_item("certification", "Certification");
+        harness.start(RatStart::new("npc.human"));
+        harness
+            .choose_option("Show item...")
+            .expect("entry is listed");
+        harness
+            .choose_option("certification")
+            .expect("certification is in the picker");
+        assert_eq!(
+            harness.current(),
+            Some(("npc.human".into(), "response_item_new".into()))
+        );
+        assert_eq!(
+            harness.variables().get("shown_item"),
+            Some(&RatValue::Str("certification".into()))
+        );
+        assert_eq!(
+            harness.line().as_deref(),
+            Some("The Certification? Never seen it. Put it back where you found it.")
+        );
+    }
+}
@@ -1,23 +1,39 @@
-use std::{collections::HashMap, fmt, str::FromStr};
+use std::{collections::HashMap, fmt, path::Path, str::FromStr};
 
 use bevy::{
     asset::{AssetLoader, LoadContext, io::Reader},
     prelude::*,
     reflect::TypePath,
 };
-
-use super::types::{
-    RatCommand, RatDialoguePresentation, RatHookTriggered, RatNode, RatNodeBuilder, RatOption,
-    RatOptionBuilder, RatScript, RatScriptAsset, RatScriptBuilder, RatScriptRon, RatStart,
+use rand::RngExt;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    hooks::RatHookRegistry,
+    interpolate::{self, RatTextResolvers},
+    markup::RatMarkup,
+    providers::{RatOptionAction, RatOptionContext, RatOptionProviders, RatProvidedOption},
+    speakers::{RatSpeakers, SPEAKERS_PATH},
+    types::{
+        RatCommand, RatCueTriggered, RatCues, RatDialoguePresentation, RatHookTriggered, RatNode,
+        RatNodeBuilder, RatNodeEntered, RatNodeRef, RatOption, RatOptionBuilder, RatScript,
+        RatScriptAsset, RatScriptBuilder, RatScriptRon, RatStart, RatTarget, RatTimeout,
+        RatVariantMode, parse_hook_call, split_hook_list,
+    },
+    vars::{RatCondition, RatEffect, RatVariables, RatVisit, parse_conditions},
+    yarn::parse_yarn_script,
 };
 use crate::{
-    assets::GameAssets,
+    assets::{GameAssets, ItemMeta},
     ui::{
         DiscoveryInteraction, DiscoveryInteractionAction, DiscoveryInteractionActor, DiscoveryKind,
-        UiDialogueCommand, UiDialogueMode, UiDialogueOption, UiDialoguePreview, UiDialogueRequest,
-        UiDiscoveryCommand, UiDiscoveryDb,
+        UiBarkCommand, UiBarkRequest, UiDialogueCommand, UiDialogueMode, UiDialogueOption,
+        UiDialoguePreview, UiDialogueRequest, UiDialogueTimeout, UiDiscoveryCommand, UiDiscoveryDb,
+    },
+    voice::{
+        PrewarmVoice, Speak, SpeakId, StopVoice, VoicePreset, estimate_segments_duration_secs,
+        estimate_speech_duration_secs,
     },
-    voice::{Speak, StopVoice, VoicePreset, estimate_speech_duration_secs},
 };
 
 #[derive(Default, TypePath)]
@@ -29,6 +45,8 @@ pub enum RatScriptAssetLoaderError {
     Utf8(std::str::Utf8Error),
     Ron(ron::error::SpannedError),
     Invalid(String),
+    Parse(RatParseError),
+    Speakers(String),
     UnsupportedExtension(String),
 }
 
@@ -39,6 +57,8 @@ impl fmt::Display for RatScriptAssetLoaderError {
             Self::Utf8(error) => write!(f, "script asset is not valid utf-8: {error}"),
             Self::Ron(error) => write!(f, "failed to parse script RON: {error}"),
             Self::Invalid(error) => write!(f, "invalid script asset: {error}"),
+            Self::Parse(error) => write!(f, "{error}"),
+            Self::Speakers(error) => write!(f, "failed to load {SPEAKERS_PATH}: {error}"),
             Self::UnsupportedExtension(ext) => {
                 write!(f, "unsupported script extension '{ext}'")
             }
@@ -66,131 +86,595 @@ impl From<ron::error::SpannedError> for RatScriptAssetLoaderError {
     }
 }
 
+impl From<RatParseError> for RatScriptAssetLoaderError {
+    fn from(value: RatParseError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+/// located .rat error, e.g. `unknown key 'speakr' at ratspinner/npc.lover.rat:14:1`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RatParseError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl RatParseError {
+    pub(super) fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for RatParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}:{}:{}",
+            self.message, self.file, self.line, self.column
+        )?;
+        if let Some(hint) = &self.hint {
+            write!(f, " ({hint})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RatParseError {}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RatScriptLoaderSettings {
+    /// reject unknown keys, annotations and stray lines instead of warning
+    pub strict: bool,
+}
+
+/// where the parser is reading from, how picky it is and who can talk
+pub(super) struct RatParseContext<'a> {
+    file: &'a str,
+    strict: bool,
+    pub(super) speakers: &'a RatSpeakers,
+}
+
+impl RatParseContext<'_> {
+    pub(super) fn error(
+        &self,
+        line: usize,
+        column: usize,
+        message: impl Into<String>,
+    ) -> RatParseError {
+        RatParseError {
+            file: self.file.to_string(),
+            line,
+            column,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    /// unknown directives fail in strict mode and are skipped with a warning
+    /// otherwise
+    pub(super) fn reject(&self, error: RatParseError) -> Result<(), RatParseError> {
+        if self.strict {
+            return Err(error);
+        }
+        warn!("ratspinner ignored {error}");
+        Ok(())
+    }
+}
+
 impl AssetLoader for RatScriptAssetLoader {
     type Asset = RatScriptAsset;
     type Error = RatScriptAssetLoaderError;
-    type Settings = ();
+    type Settings = RatScriptLoaderSettings;
 
     async fn load(
         &self,
         reader: &mut dyn Reader,
-        _settings: &Self::Settings,
+        settings: &Self::Settings,
         load_context: &mut LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
 
-        let path = load_context.path().path();
-        let extension = path
-            .extension()
-            .and_then(|value| value.to_str())
-            .unwrap_or_default()
-            .to_ascii_lowercase();
-        let fallback_script_id = path
-            .file_stem()
-            .and_then(|value| value.to_str())
-            .unwrap_or("script");
-
-        let scripts = match extension.as_str() {
-            "rat" => {
-                let content = std::str::from_utf8(&bytes)?;
-                parse_rat_scripts(content, fallback_script_id)?
-            }
-            "ron" => {
-                let raw = ron::de::from_bytes::<RatScriptRon>(&bytes)?;
-                vec![RatScript::try_from(raw).map_err(RatScriptAssetLoaderError::Invalid)?]
-            }
-            _ => return Err(RatScriptAssetLoaderError::UnsupportedExtension(extension)),
-        };
-
+        // loaded as a dependency so editing speakers.ron reloads every script
+        let speakers = load_context
+            .loader()
+            .immediate()
+            .load::<RatSpeakers>(SPEAKERS_PATH)
+            .await
+            .map_err(|error| RatScriptAssetLoaderError::Speakers(error.to_string()))?;
+
+        let scripts = parse_script_bytes(
+            &bytes,
+            load_context.path().path(),
+            settings.strict,
+            speakers.get(),
+        )?;
         Ok(RatScriptAsset::new(scripts))
     }
 
     fn extensions(&self) -> &[&str] {
-        &["rat", "ron"]
+        &["rat", "ron", "yarn"]
+    }
+}
+
+/// parses a .rat, .ron or .yarn script file, shared by the asset loader and the linter
+pub(super) fn parse_script_bytes(
+    bytes: &[u8],
+    path: &Path,
+    strict: bool,
+    speakers: &RatSpeakers,
+) -> Result<Vec<RatScript>, RatScriptAssetLoaderError> {
+    let extension = path
+        .extension()
+        .and_then(|value| value.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let fallback_script_id = path
+        .file_stem()
+        .and_then(|value| value.to_str())
+        .unwrap_or("script");
+
+    match extension.as_str() {
+        "rat" | "yarn" => {
+            let content = std::str::from_utf8(bytes)?;
+            let file = path.to_string_lossy();
+            let ctx = RatParseContext {
+                file: &file,
+                strict,
+                speakers,
+            };
+            if extension == "yarn" {
+                return Ok(vec![parse_yarn_script(content, fallback_script_id, &ctx)?]);
+            }
+            Ok(parse_rat_scripts(content, fallback_script_id, &ctx)?)
+        }
+        "ron" => {
+            let raw = ron::de::from_bytes::<RatScriptRon>(bytes)?;
+            Ok(vec![
+                raw.into_script(speakers)
+                    .map_err(RatScriptAssetLoaderError::Invalid)?,
+            ])
+        }
+        _ => Err(RatScriptAssetLoaderError::UnsupportedExtension(extension)),
     }
 }
 
 #[derive(Resource, Default)]
 pub(super) struct RatLibrary {
     scripts: HashMap<String, RatScript>,
+    speakers: RatSpeakers,
+    /// `.item.meta` assets by item id, for `{item:..}`
+    items: HashMap<String, ItemMeta>,
+    /// script ids each asset file provided, so a reload can drop stale ones
+    sources: HashMap<AssetId<RatScriptAsset>, Vec<String>>,
+}
+
+impl RatLibrary {
+    pub(super) fn speakers(&self) -> &RatSpeakers {
+        &self.speakers
+    }
+
+    pub(super) fn set_speakers(&mut self, speakers: RatSpeakers) {
+        self.speakers = speakers;
+    }
+
+    /// swaps in the scripts of one asset file, returning every script id that
+    /// was removed or (re)added
+    fn replace_source(
+        &mut self,
+        id: AssetId<RatScriptAsset>,
+        asset: &RatScriptAsset,
+    ) -> Vec<String> {
+        let mut touched = self.sources.remove(&id).unwrap_or_default();
+        for script_id in &touched {
+            self.scripts.remove(script_id);
+        }
+
+        let ids: Vec<String> = asset
+            .scripts
+            .iter()
+            .map(|script| script.id.clone())
+            .collect();
+        for script in &asset.scripts {
+            self.scripts.insert(script.id.clone(), script.clone());
+        }
+        touched.extend(ids.iter().cloned());
+        self.sources.insert(id, ids);
+        touched
+    }
+
+    /// what providers and text resolvers see of `node`
+    fn context<'a>(
+        &'a self,
+        script: &'a RatScript,
+        node: &'a RatNode,
+        variables: &'a RatVariables,
+        discovery_db: &'a UiDiscoveryDb,
+        resolvers: &'a RatTextResolvers,
+    ) -> RatOptionContext<'a> {
+        RatOptionContext {
+            script,
+            node,
+            variables,
+            discovery_db,
+            speakers: &self.speakers,
+            items: &self.items,
+            resolvers,
+        }
+    }
 }
 
+/// pause between two lines of an overheard conversation
+const CONVERSATION_GAP_SECS: f32 = 0.6;
+/// fired at the target when `RatCommand::Interrupt` breaks a dialogue off
+pub const INTERRUPTED_HOOK: &str = "dialogue.interrupted";
+/// said before an interrupted dialogue picks up where it left off
+const RESUME_LINE: &str = "As I was saying...";
+/// silent nodes in a row before the dialogue is taken for a loop and closed
+const MAX_SILENT_HOPS: usize = 64;
+
 #[derive(Resource, Default)]
 pub(super) struct RatRuntime {
     active: Option<ActiveDialogue>,
+    /// npcs talking among themselves, next to whatever the player is in
+    conversation: Option<Conversation>,
+    /// dialogues broken off by `RatCommand::Interrupt`, per target, picked
+    /// up again by the next start on the same target
+    interrupted: HashMap<Entity, ActiveDialogue>,
+    /// the last `SpeakId` a dialogue line went out with
+    last_speak_id: u64,
+}
+
+impl RatRuntime {
+    /// ties a line's `Speak` to its typewriter
+    fn next_speak_id(&mut self) -> SpeakId {
+        self.last_speak_id += 1;
+        SpeakId(self.last_speak_id)
+    }
+
+    /// (script_id, node_id) of the open dialogue
+    pub(super) fn current(&self) -> Option<(&str, &str)> {
+        self.active
+            .as_ref()
+            .map(|active| (active.script_id.as_str(), active.node_id.as_str()))
+    }
+
+    /// script the npcs are overheard talking in, if any
+    pub(super) fn conversation(&self) -> Option<&str> {
+        self.conversation
+            .as_ref()
+            .map(|conversation| conversation.script_id.as_str())
+    }
+
+    /// the open node's line as it's said, placeholders filled
+    pub(super) fn line(
+        &self,
+        library: &RatLibrary,
+        variables: &RatVariables,
+        discovery_db: &UiDiscoveryDb,
+        resolvers: &RatTextResolvers,
+    ) -> Option<String> {
+        let active = self.active.as_ref()?;
+        let script = library.scripts.get(&active.script_id)?;
+        let node = script.nodes.get(&active.node_id)?;
+        let ctx = library.context(script, node, variables, discovery_db, resolvers);
+        let line = resolvers.fill_line(node.line(active.line), &ctx);
+        Some(RatMarkup::lenient(&line).plain())
+    }
+
+    /// what `RatCommand::Choose` picks from right now: the node's options, a
+    /// provider menu's (`back` is one past the end) or nothing under a reply.
+    /// node options come with their placeholders filled
+    pub(super) fn options(
+        &self,
+        library: &RatLibrary,
+        variables: &RatVariables,
+        discovery_db: &UiDiscoveryDb,
+        providers: &RatOptionProviders,
+        resolvers: &RatTextResolvers,
+    ) -> Vec<RatProvidedOption> {
+        let Some(active) = &self.active else {
+            return Vec::new();
+        };
+        let Some(script) = library.scripts.get(&active.script_id) else {
+            return Vec::new();
+        };
+        let Some(node) = script.nodes.get(&active.node_id) else {
+            return Vec::new();
+        };
+        let ctx = library.context(script, node, variables, discovery_db, resolvers);
+        match &active.overlay {
+            DialogueOverlay::None => providers
+                .node_options(&ctx)
+                .into_iter()
+                .map(|option| RatProvidedOption {
+                    text: resolvers.fill_plain(&option.text, &ctx),
+                    ..option
+                })
+                .collect(),
+            DialogueOverlay::Menu(provider) => providers.options(provider, &ctx),
+            DialogueOverlay::Reply => Vec::new(),
+        }
+    }
 }
 
 #[derive(Resource, Default)]
 pub struct RatDialogueState {
     pub active: bool,
+    /// world entity the open dialogue is with
+    pub target: Option<Entity>,
 }
 
 #[derive(Debug, Clone)]
 struct ActiveDialogue {
+    /// `RatStart::script_id` it began with, a start with the same one resumes
+    /// it after an interruption
+    start_id: String,
     script_id: String,
     node_id: String,
     target: Option<Entity>,
     presentation: RatDialoguePresentation,
     overlay: DialogueOverlay,
+    /// set once the current node's effects were applied, so overlays don't
+    /// re-run them
+    entered: bool,
+    /// text line picked when the node was entered, redraws reuse it
+    line: usize,
+    /// the line was read before this visit, on this run or an earlier one
+    read: bool,
+    /// seconds until the node's `timeout:` picks for the player, at or below
+    /// zero once `RatCommand::TimeOut` went out
+    time_left: Option<f32>,
+    /// (script_id, node_id) to go back to on `-> return`, pushed by `call`
+    return_stack: Vec<(String, String)>,
+    /// silent nodes passed through since the last one that showed something
+    silent_hops: usize,
+}
+
+impl ActiveDialogue {
+    fn goto(&mut self, node_id: String) {
+        self.node_id = node_id;
+        self.overlay = DialogueOverlay::None;
+        self.entered = false;
+        self.time_left = None;
+    }
+
+    /// what's left of `node`'s countdown, for the ui's bar
+    fn ui_timeout(&self, node: &RatNode) -> Option<UiDialogueTimeout> {
+        let timeout = node.timeout.as_ref()?;
+        Some(UiDialogueTimeout {
+            secs_left: self.time_left?.max(0.0),
+            secs_total: timeout.secs,
+        })
+    }
+
+    fn jump(&mut self, script_id: String, node_id: String) {
+        self.script_id = script_id;
+        self.goto(node_id);
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// a script npcs play out to each other, see `RatCommand::Overhear`
+#[derive(Debug, Clone)]
+struct Conversation {
+    script_id: String,
+    /// node said next, `None` once the last line is out
+    node_id: Option<String>,
+    target: Option<Entity>,
+    cast: HashMap<String, Entity>,
+    /// seconds until the next line, the previous one is still being said
+    wait_left: f32,
+}
+
+impl Conversation {
+    fn involves(&self, entity: Entity) -> bool {
+        self.target == Some(entity) || self.cast.values().any(|cast| *cast == entity)
+    }
+
+    /// the entity saying `node`'s line
+    fn speaker(&self, node: &RatNode) -> Option<Entity> {
+        node.speaker_id
+            .as_ref()
+            .and_then(|speaker_id| self.cast.get(speaker_id))
+            .copied()
+            .or(self.target)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 enum DialogueOverlay {
     #[default]
     None,
-    InventoryPicker,
-    ItemResponse,
+    /// picking from a provider's menu, named here
+    Menu(String),
+    /// a provided option's reply is on screen
+    Reply,
 }
 
 pub(super) fn load_scripts_from_assets(
     mut library: ResMut<RatLibrary>,
     game_assets: Res<GameAssets>,
     script_assets: Res<Assets<RatScriptAsset>>,
+    speaker_assets: Res<Assets<RatSpeakers>>,
+    item_assets: Res<Assets<ItemMeta>>,
+    hook_registry: Res<RatHookRegistry>,
+    providers: Res<RatOptionProviders>,
+    resolvers: Res<RatTextResolvers>,
 ) {
     library.scripts.clear();
+    library.sources.clear();
+    library.speakers = speaker_assets
+        .get(&game_assets.rat_speakers)
+        .cloned()
+        .unwrap_or_default();
+    library.items = game_assets
+        .items
+        .iter()
+        .filter_map(|handle| item_assets.get(handle))
+        .map(|item| (item.id.clone(), item.clone()))
+        .collect();
 
     for handle in &game_assets.rat_scripts {
         if let Some(asset) = script_assets.get(handle) {
-            for script in &asset.scripts {
-                library.scripts.insert(script.id.clone(), script.clone());
-            }
+            library.replace_source(handle.id(), asset);
         } else {
             warn!("ratspinner script handle not ready: {:?}", handle);
         }
     }
+    hook_registry.warn_unhandled(library.scripts.values());
+    providers.warn_unknown(library.scripts.values());
+    resolvers.warn_unknown(library.scripts.values());
+}
+
+/// picks up .rat edits while playing (needs `bevy/file_watcher`, on in
+/// dev_native). the open dialogue stays on its node, or restarts at the entry
+/// if that node is gone
+pub(super) fn reload_modified_scripts(
+    mut commands: Commands,
+    mut asset_events: MessageReader<AssetEvent<RatScriptAsset>>,
+    mut speaker_events: MessageReader<AssetEvent<RatSpeakers>>,
+    mut hooks: MessageWriter<RatHookTriggered>,
+    mut ui_commands: MessageWriter<UiDialogueCommand>,
+    mut discovery_commands: MessageWriter<UiDiscoveryCommand>,
+    script_assets: Res<Assets<RatScriptAsset>>,
+    speaker_assets: Res<Assets<RatSpeakers>>,
+    mut library: ResMut<RatLibrary>,
+    mut runtime: ResMut<RatRuntime>,
+    mut state: ResMut<RatDialogueState>,
+    mut variables: ResMut<RatVariables>,
+    discovery_db: Res<UiDiscoveryDb>,
+    providers: Res<RatOptionProviders>,
+    resolvers: Res<RatTextResolvers>,
+    hook_registry: Res<RatHookRegistry>,
+) {
+    // scripts depend on speakers.ron and reload on their own, this only keeps
+    // the contact info current
+    for event in speaker_events.read() {
+        if let AssetEvent::Modified { id } = event
+            && let Some(speakers) = speaker_assets.get(*id)
+        {
+            library.speakers = speakers.clone();
+        }
+    }
+
+    let mut reloaded = Vec::new();
+    for event in asset_events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        // only files the library was built from, not stray loads
+        if !library.sources.contains_key(id) {
+            continue;
+        }
+        let Some(asset) = script_assets.get(*id) else {
+            continue;
+        };
+        let touched = library.replace_source(*id, asset);
+        info!("ratspinner reloaded scripts: {}", touched.join(", "));
+        hook_registry.warn_unhandled(&asset.scripts);
+        providers.warn_unknown(&asset.scripts);
+        resolvers.warn_unknown(&asset.scripts);
+        reloaded.extend(touched);
+    }
+
+    let Some(active) = runtime.active.clone() else {
+        return;
+    };
+    if !reloaded.contains(&active.script_id) {
+        return;
+    }
+
+    let Some(script) = library.scripts.get(&active.script_id) else {
+        warn!(
+            "ratspinner script '{}' vanished on reload, closing dialogue",
+            active.script_id
+        );
+        close_dialogue(&mut commands, &mut runtime, &mut state, &mut ui_commands);
+        return;
+    };
+
+    if let Some(node) = script.nodes.get(&active.node_id) {
+        // same node: redraw only, its hooks and effects already ran
+        if let Some(active_mut) = runtime.active.as_mut() {
+            active_mut.overlay = DialogueOverlay::None;
+        }
+        if !is_headless(Some(&active)) {
+            let ctx = library.context(script, node, &variables, &discovery_db, &resolvers);
+            ui_commands.write(UiDialogueCommand::Start(node_dialogue_request(
+                &ctx,
+                &providers,
+                active.line,
+                false,
+                active.ui_timeout(node),
+                0.0,
+                None,
+            )));
+        }
+        return;
+    }
+
+    let entry = script.entry.clone();
+    warn!(
+        "ratspinner node '{}' is gone from '{}', restarting at '{}'",
+        active.node_id, active.script_id, entry
+    );
+    if let Some(active_mut) = runtime.active.as_mut() {
+        active_mut.goto(entry);
+    }
+    show_current_node(
+        &mut commands,
+        &mut hooks,
+        &mut ui_commands,
+        &mut discovery_commands,
+        &library,
+        &mut runtime,
+        &mut state,
+        &mut variables,
+        &discovery_db,
+        &providers,
+        &resolvers,
+        true,
+    );
 }
 
 fn parse_rat_scripts(
     content: &str,
     fallback_script_id: &str,
-) -> Result<Vec<RatScript>, RatScriptAssetLoaderError> {
-    let mut sections = Vec::new();
-    let mut current = String::new();
+    ctx: &RatParseContext,
+) -> Result<Vec<RatScript>, RatParseError> {
+    // each section remembers its first line so errors point into the whole file
+    let mut sections: Vec<(usize, String)> = Vec::new();
+    let mut current = (1, String::new());
 
-    for line in content.lines() {
+    for (line_index, line) in content.lines().enumerate() {
         let trimmed = line.trim();
-        if trimmed.starts_with("// script:") && !current.trim().is_empty() {
-            sections.push(current);
-            current = String::new();
+        if trimmed.starts_with("// script:") && !current.1.trim().is_empty() {
+            sections.push(std::mem::replace(
+                &mut current,
+                (line_index + 1, String::new()),
+            ));
         }
-        current.push_str(line);
-        current.push('\n');
+        current.1.push_str(line);
+        current.1.push('\n');
     }
 
-    if !current.trim().is_empty() {
+    if !current.1.trim().is_empty() {
         sections.push(current);
     }
 
     if sections.is_empty() {
-        return Err(RatScriptAssetLoaderError::Invalid(
-            "script does not define any content".to_string(),
-        ));
+        return Err(ctx.error(1, 1, "script does not define any content"));
     }
 
     sections
         .into_iter()
-        .map(|section| parse_rat_script(&section, fallback_script_id))
+        .map(|(first_line, section)| {
+            parse_rat_script(&section, first_line, fallback_script_id, ctx)
+        })
         .collect()
 }
 
@@ -223,7 +707,8 @@ pub(super) fn seed_builtin_script(mut library: ResMut<RatLibrary>) {
                     RatOptionBuilder::new("i should go.")
                         .id("leave")
                         .hook("npc.default.option.leave"),
-                ),
+                )
+                .options_from("inventory"),
         )
         .node(
             RatNodeBuilder::new("identity")
@@ -244,89 +729,182 @@ pub(super) fn seed_builtin_script(mut library: ResMut<RatLibrary>) {
     library.scripts.insert(script.id.clone(), script);
 }
 
+/// speaker, portrait and voice stay unset until `build` so a `speaker:` line
+/// fills in whatever the node didn't set, in any order
 #[derive(Debug)]
 struct RatNodeDraft {
     id: String,
-    speaker: String,
-    text: String,
-    portrait_path: String,
-    voice: VoicePreset,
+    line: usize,
+    speaker: Option<String>,
+    texts: Vec<String>,
+    /// where the second `text:` is, for the missing `variants:` error
+    variant_line: Option<usize>,
+    variant_mode: Option<RatVariantMode>,
+    portrait: Option<String>,
+    voice: Option<VoicePreset>,
     next: Option<String>,
+    else_next: Option<String>,
     hooks: Vec<String>,
+    when: Vec<RatCondition>,
+    effects: Vec<RatEffect>,
     options: Vec<RatOption>,
+    options_from: Vec<String>,
+    timeout: Option<RatTimeout>,
+    cues: RatCues,
+    comments: Vec<String>,
 }
 
 impl RatNodeDraft {
-    fn new(id: String) -> Self {
+    fn new(id: String, line: usize) -> Self {
         Self {
             id,
-            speaker: "unknown".to_string(),
-            text: String::new(),
-            portrait_path: "models/npc_a/npc_a.png".to_string(),
-            voice: VoicePreset::NeutralNpc,
+            line,
+            speaker: None,
+            texts: Vec::new(),
+            variant_line: None,
+            variant_mode: None,
+            portrait: None,
+            voice: None,
             next: None,
+            else_next: None,
             hooks: Vec::new(),
+            when: Vec::new(),
+            effects: Vec::new(),
             options: Vec::new(),
+            options_from: Vec::new(),
+            timeout: None,
+            cues: RatCues::default(),
+            comments: Vec::new(),
         }
     }
 
-    fn build(self) -> RatNode {
+    fn build(self, speakers: &RatSpeakers) -> RatNode {
+        // a registered id pulls in the speaker, anything else is a plain name
+        let def = self.speaker.as_deref().and_then(|id| speakers.get(id));
+        let mut node = RatNodeBuilder::new(self.id);
+        if let Some(speaker) = self.speaker {
+            node = match def {
+                Some(def) => node.speaker_from(speaker, def),
+                None => node.speaker(speaker),
+            };
+        }
+        if let Some(portrait) = self.portrait {
+            node = node.portrait(match def {
+                Some(def) => def.portrait(&portrait),
+                None => portrait,
+            });
+        } else if let Some(def) = def {
+            // `emote: angry` wears the speaker's `angry` portrait
+            node = node.portrait(def.default_portrait(self.cues.emote.as_deref()));
+        }
+        if let Some(voice) = self.voice {
+            node = node.voice(voice);
+        }
+        let mut texts = self.texts.into_iter();
         RatNode {
-            id: self.id,
-            speaker: self.speaker,
-            text: self.text,
-            portrait_path: self.portrait_path,
-            voice: self.voice,
+            text: texts.next().unwrap_or_default(),
+            variants: texts.collect(),
+            variant_mode: self.variant_mode.unwrap_or_default(),
             next: self.next,
+            else_next: self.else_next,
             hooks: self.hooks,
+            when: self.when,
+            effects: self.effects,
             options: self.options,
+            options_from: self.options_from,
+            timeout: self.timeout,
+            cues: self.cues,
+            comments: self.comments,
+            ..node.build()
         }
     }
 }
 
+const NODE_KEYS: [&str; 16] = [
+    "speaker",
+    "text",
+    "variants",
+    "portrait",
+    "voice",
+    "hook",
+    "when",
+    "else",
+    "set",
+    "inc",
+    "options_from",
+    "timeout",
+    "anim",
+    "look",
+    "sfx",
+    "emote",
+];
+const OPTION_ANNOTATIONS: [&str; 5] = ["hook", "id", "if", "set", "inc"];
+const VOICE_NAMES: [&str; 4] = [
+    "hostile_entity",
+    "lost_child",
+    "corrupted_transmission",
+    "neutral_npc",
+];
+
 fn parse_rat_script(
     content: &str,
+    first_line: usize,
     fallback_script_id: &str,
-) -> Result<RatScript, RatScriptAssetLoaderError> {
+    ctx: &RatParseContext,
+) -> Result<RatScript, RatParseError> {
     let mut script_id = fallback_script_id.to_string();
     let mut entry = "start".to_string();
+    let mut entry_line = None;
     let mut nodes: HashMap<String, RatNode> = HashMap::new();
-    let mut first_node_id: Option<String> = None;
+    let mut order: Vec<String> = Vec::new();
     let mut current: Option<RatNodeDraft> = None;
+    // plain `//` lines go to the node they sit in front of, or the open one
+    let mut comments: Vec<String> = Vec::new();
 
     for (line_index, line) in content.lines().enumerate() {
+        let line_number = first_line + line_index;
         let raw = line.trim();
         if raw.is_empty() {
             continue;
         }
+        let error_at =
+            |part: &str, message: String| ctx.error(line_number, column_in(line, part), message);
 
         if let Some(meta) = raw.strip_prefix("//") {
-            parse_metadata_comment(meta.trim(), &mut script_id, &mut entry);
+            let meta = meta.trim();
+            if meta.starts_with("entry:") {
+                entry_line = Some((line_number, column_in(line, meta)));
+            }
+            if !parse_metadata_comment(meta, &mut script_id, &mut entry) {
+                comments.push(meta.to_string());
+            }
             continue;
         }
 
         if raw.starts_with('[') && raw.ends_with(']') {
-            flush_current_node(&mut current, &mut nodes)?;
+            flush_current_node(&mut current, &mut nodes, ctx)?;
             let node_id = raw[1..raw.len() - 1].trim();
             if node_id.is_empty() {
-                return Err(RatScriptAssetLoaderError::Invalid(format!(
-                    "line {} has an empty node id",
-                    line_index + 1
-                )));
-            }
-            if first_node_id.is_none() {
-                first_node_id = Some(node_id.to_string());
+                return Err(error_at(raw, "empty node id".to_string()));
             }
-            current = Some(RatNodeDraft::new(node_id.to_string()));
+            order.push(node_id.to_string());
+            let mut draft = RatNodeDraft::new(node_id.to_string(), line_number);
+            draft.comments = std::mem::take(&mut comments);
+            current = Some(draft);
             continue;
         }
 
         let Some(node) = current.as_mut() else {
+            ctx.reject(
+                error_at(raw, "content outside of a node".to_string())
+                    .hint("start a node with `[node_id]` first"),
+            )?;
             continue;
         };
+        node.comments.append(&mut comments);
 
         if let Some(option_raw) = raw.strip_prefix('>') {
-            let option = parse_option_line(option_raw.trim(), line_index + 1)?;
+            let option = parse_option_line(option_raw.trim(), line, line_number, ctx)?;
             node.options.push(option);
             continue;
         }
@@ -334,43 +912,123 @@ fn parse_rat_script(
         if let Some(next_raw) = raw.strip_prefix("->") {
             let next = next_raw.trim();
             if !next.is_empty() {
+                RatTarget::parse(next).map_err(|error| error_at(next, error))?;
                 node.next = Some(next.to_string());
             }
             continue;
         }
 
-        if let Some((key, value)) = raw.split_once(':') {
-            let key = key.trim();
-            let value = value.trim();
-            match key {
-                "speaker" => node.speaker = value.to_string(),
-                "text" => node.text = value.to_string(),
-                "portrait" => node.portrait_path = value.to_string(),
-                "voice" => {
-                    node.voice = VoicePreset::from_str(value).unwrap_or(VoicePreset::NeutralNpc);
+        let Some((key, value)) = raw.split_once(':') else {
+            ctx.reject(
+                error_at(raw, "unrecognized line".to_string())
+                    .hint("expected `key: value`, `> option` or `-> next`"),
+            )?;
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim();
+        let invalid = |error: String| error_at(value, error);
+        match key {
+            "speaker" => {
+                // dotted words are meant as speakers.ron ids, names have spaces
+                if looks_like_speaker_id(value) && ctx.speakers.get(value).is_none() {
+                    ctx.reject(
+                        invalid(format!("unknown speaker '{value}'"))
+                            .hint(suggestion(value, &ctx.speakers.ids())),
+                    )?;
+                }
+                node.speaker = Some(value.to_string());
+            }
+            "text" => {
+                if let Err(error) = RatMarkup::parse(&interpolate::without_placeholders(value)) {
+                    ctx.reject(invalid(error).hint(
+                        "tags are {pause=0.5}, {speed=0.5}, {glitch}, {shake} and {whisper}, \
+                         {var} and {item:id.name} are filled in, write {{ for a literal brace",
+                    ))?;
+                }
+                if node.texts.len() == 1 {
+                    node.variant_line = Some(line_number);
+                }
+                node.texts.push(value.to_string());
+            }
+            "variants" => match RatVariantMode::parse(value) {
+                Ok(mode) => node.variant_mode = Some(mode),
+                Err(error) => {
+                    ctx.reject(invalid(error).hint(suggestion(value, &RatVariantMode::NAMES)))?
+                }
+            },
+            "portrait" => node.portrait = Some(value.to_string()),
+            "voice" => match VoicePreset::from_str(value) {
+                Ok(voice) => node.voice = Some(voice),
+                Err(()) => ctx.reject(
+                    invalid(format!("unknown voice '{value}'"))
+                        .hint(suggestion(value, &VOICE_NAMES)),
+                )?,
+            },
+            "hook" => extend_hooks(value, &mut node.hooks, line, line_number, ctx)?,
+            "when" => node.when.extend(parse_conditions(value).map_err(invalid)?),
+            "else" => {
+                RatTarget::parse(value).map_err(invalid)?;
+                node.else_next = Some(value.to_string());
+            }
+            "set" => node
+                .effects
+                .push(RatEffect::parse_set(value).map_err(invalid)?),
+            "inc" => node
+                .effects
+                .push(RatEffect::parse_inc(value).map_err(invalid)?),
+            "options_from" => {
+                for provider in value.split(',').map(str::trim) {
+                    if provider.is_empty() || provider.contains(char::is_whitespace) {
+                        return Err(invalid(format!("bad option provider '{provider}'"))
+                            .hint("name registered providers, `options_from: inventory`"));
+                    }
+                    node.options_from.push(provider.to_string());
+                }
+            }
+            "timeout" => {
+                node.timeout = Some(
+                    RatTimeout::parse(value)
+                        .map_err(|error| invalid(error).hint("`timeout: 6 -> silence`"))?,
+                );
+            }
+            "anim" | "look" | "sfx" | "emote" => {
+                if value.is_empty() || value.contains(char::is_whitespace) {
+                    return Err(invalid(format!("bad {key} '{value}'"))
+                        .hint("one word, `anim: wave`, `look: player`, `sfx: door_locked`"));
                 }
-                "hook" => extend_hooks(value, &mut node.hooks),
-                _ => {}
+                let cue = match key {
+                    "anim" => &mut node.cues.anim,
+                    "look" => &mut node.cues.look,
+                    "sfx" => &mut node.cues.sfx,
+                    _ => &mut node.cues.emote,
+                };
+                *cue = Some(value.to_string());
             }
+            _ => ctx.reject(
+                error_at(key, format!("unknown key '{key}'")).hint(suggestion(key, &NODE_KEYS)),
+            )?,
         }
     }
 
-    flush_current_node(&mut current, &mut nodes)?;
+    if let Some(node) = current.as_mut() {
+        node.comments.append(&mut comments);
+    }
+    flush_current_node(&mut current, &mut nodes, ctx)?;
 
     if nodes.is_empty() {
-        return Err(RatScriptAssetLoaderError::Invalid(
-            "script does not define any nodes".to_string(),
-        ));
+        return Err(ctx.error(first_line, 1, "script does not define any nodes"));
     }
 
     if !nodes.contains_key(&entry) {
-        if let Some(first) = first_node_id {
-            entry = first;
-        } else {
-            return Err(RatScriptAssetLoaderError::Invalid(format!(
-                "entry node '{}' was not found",
-                entry
-            )));
+        if let Some((line, column)) = entry_line {
+            ctx.reject(
+                ctx.error(line, column, format!("entry node '{entry}' was not found"))
+                    .hint("falling back to the first node"),
+            )?;
+        }
+        if let Some(first) = order.first() {
+            entry = first.clone();
         }
     }
 
@@ -378,30 +1036,33 @@ fn parse_rat_script(
         id: script_id,
         entry,
         nodes,
+        order,
     })
 }
 
-fn parse_metadata_comment(raw: &str, script_id: &mut String, entry: &mut String) {
+/// false for plain comments
+fn parse_metadata_comment(raw: &str, script_id: &mut String, entry: &mut String) -> bool {
     let Some((key, value)) = raw.split_once(':') else {
-        return;
+        return false;
     };
     let key = key.trim();
     let value = value.trim();
-    if value.is_empty() {
-        return;
-    }
 
     match key {
+        "script" | "entry" if value.is_empty() => {}
         "script" => *script_id = value.to_string(),
         "entry" => *entry = value.to_string(),
-        _ => {}
+        _ => return false,
     }
+    true
 }
 
 fn parse_option_line(
     raw: &str,
+    line: &str,
     line_number: usize,
-) -> Result<RatOption, RatScriptAssetLoaderError> {
+    ctx: &RatParseContext,
+) -> Result<RatOption, RatParseError> {
     let (text_part, metadata, inline_annotations) = if let Some((text, rest)) = raw.split_once("->")
     {
         (text.trim(), Some(rest.trim()), "")
@@ -411,10 +1072,7 @@ fn parse_option_line(
     };
 
     if text_part.is_empty() {
-        return Err(RatScriptAssetLoaderError::Invalid(format!(
-            "line {} has an empty option text",
-            line_number
-        )));
+        return Err(ctx.error(line_number, column_in(line, raw), "empty option text"));
     }
 
     let mut option = RatOption {
@@ -422,16 +1080,20 @@ fn parse_option_line(
         text: text_part.to_string(),
         next: None,
         hooks: Vec::new(),
+        conditions: Vec::new(),
+        effects: Vec::new(),
     };
 
     if let Some(meta) = metadata {
         let (target, annotations) = split_target_and_annotations(meta);
         if !target.is_empty() {
+            RatTarget::parse(target)
+                .map_err(|error| ctx.error(line_number, column_in(line, target), error))?;
             option.next = Some(target.to_string());
         }
-        parse_option_annotations(annotations, &mut option);
+        parse_option_annotations(annotations, &mut option, line, line_number, ctx)?;
     }
-    parse_option_annotations(inline_annotations, &mut option);
+    parse_option_annotations(inline_annotations, &mut option, line, line_number, ctx)?;
 
     Ok(option)
 }
@@ -444,63 +1106,164 @@ fn split_target_and_annotations(raw: &str) -> (&str, &str) {
     }
 }
 
-fn parse_option_annotations(raw: &str, option: &mut RatOption) {
+fn parse_option_annotations(
+    raw: &str,
+    option: &mut RatOption,
+    line: &str,
+    line_number: usize,
+    ctx: &RatParseContext,
+) -> Result<(), RatParseError> {
+    let error_at =
+        |part: &str, message: String| ctx.error(line_number, column_in(line, part), message);
     let mut remaining = raw.trim();
-    while let Some(start) = remaining.find('[') {
+    while !remaining.is_empty() {
+        let Some(start) = remaining.find('[') else {
+            return ctx.reject(error_at(
+                remaining,
+                format!("unexpected '{remaining}' after annotations"),
+            ));
+        };
+        let stray = remaining[..start].trim();
+        if !stray.is_empty() {
+            ctx.reject(error_at(
+                stray,
+                format!("unexpected '{stray}' between annotations"),
+            ))?;
+        }
         let Some(end_rel) = remaining[start + 1..].find(']') else {
-            break;
+            return ctx.reject(
+                error_at(&remaining[start..], "unclosed annotation".to_string())
+                    .hint("annotations look like `[hook: game.kill]`"),
+            );
         };
         let end = start + 1 + end_rel;
         let annotation = remaining[start + 1..end].trim();
-        if let Some(value) = annotation.strip_prefix("hook:") {
-            extend_hooks(value, &mut option.hooks);
-        } else if let Some(value) = annotation.strip_prefix("id:") {
-            let value = value.trim();
-            if !value.is_empty() {
-                option.id = Some(value.to_string());
+        let (key, value) = annotation.split_once(':').unwrap_or((annotation, ""));
+        let key = key.trim();
+        let invalid = |error: String| error_at(value.trim(), error);
+        match key {
+            "hook" => extend_hooks(value, &mut option.hooks, line, line_number, ctx)?,
+            "id" => {
+                let value = value.trim();
+                if !value.is_empty() {
+                    option.id = Some(value.to_string());
+                }
             }
+            "if" => option
+                .conditions
+                .extend(parse_conditions(value).map_err(invalid)?),
+            "set" => option
+                .effects
+                .push(RatEffect::parse_set(value).map_err(invalid)?),
+            "inc" => option
+                .effects
+                .push(RatEffect::parse_inc(value).map_err(invalid)?),
+            _ => ctx.reject(
+                error_at(annotation, format!("unknown annotation '{key}'"))
+                    .hint(suggestion(key, &OPTION_ANNOTATIONS)),
+            )?,
         }
         remaining = remaining[end + 1..].trim();
     }
+    Ok(())
 }
 
-fn extend_hooks(raw: &str, hooks: &mut Vec<String>) {
-    for hook in raw.split(',') {
-        let hook = hook.trim();
-        if !hook.is_empty() {
-            hooks.push(hook.to_string());
-        }
+fn extend_hooks(
+    raw: &str,
+    hooks: &mut Vec<String>,
+    line: &str,
+    line_number: usize,
+    ctx: &RatParseContext,
+) -> Result<(), RatParseError> {
+    for hook in split_hook_list(raw) {
+        parse_hook_call(hook)
+            .map_err(|error| ctx.error(line_number, column_in(line, hook), error))?;
+        hooks.push(hook.to_string());
     }
+    Ok(())
 }
 
 fn flush_current_node(
     current: &mut Option<RatNodeDraft>,
     nodes: &mut HashMap<String, RatNode>,
-) -> Result<(), RatScriptAssetLoaderError> {
+    ctx: &RatParseContext,
+) -> Result<(), RatParseError> {
     let Some(node) = current.take() else {
         return Ok(());
     };
     if nodes.contains_key(&node.id) {
-        return Err(RatScriptAssetLoaderError::Invalid(format!(
-            "duplicate node id '{}'",
-            node.id
-        )));
+        return Err(ctx.error(node.line, 1, format!("duplicate node id '{}'", node.id)));
+    }
+    if let Some(line) = node.variant_line
+        && node.variant_mode.is_none()
+    {
+        // used to be last-one-wins, now every line is kept as a sequence
+        ctx.reject(
+            ctx.error(line, 1, format!("several `text:` lines in '{}'", node.id))
+                .hint("add `variants: sequence`, `cycle`, `random` or `once`"),
+        )?;
     }
-    let built = node.build();
+    let built = node.build(ctx.speakers);
     nodes.insert(built.id.clone(), built);
     Ok(())
 }
 
+fn looks_like_speaker_id(value: &str) -> bool {
+    value.contains('.') && !value.contains(char::is_whitespace)
+}
+
+/// 1-based char column of `part`, which must be a slice of `line`
+pub(super) fn column_in(line: &str, part: &str) -> usize {
+    let offset = (part.as_ptr() as usize)
+        .checked_sub(line.as_ptr() as usize)
+        .filter(|offset| *offset <= line.len())
+        .unwrap_or(0);
+    line[..offset].chars().count() + 1
+}
+
+/// closest known word for typo hints, or the full list when nothing is close
+pub(super) fn suggestion(word: &str, known: &[&str]) -> String {
+    let closest = known
+        .iter()
+        .map(|candidate| (edit_distance(word, candidate), candidate))
+        .min_by_key(|(distance, _)| *distance);
+    match closest {
+        Some((distance, candidate)) if distance <= 2 => format!("did you mean '{candidate}'?"),
+        _ => format!("expected one of: {}", known.join(", ")),
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
 pub(super) fn handle_rat_commands(
     mut commands: Commands,
     mut messages: MessageReader<RatCommand>,
     mut hooks: MessageWriter<RatHookTriggered>,
     mut ui_commands: MessageWriter<UiDialogueCommand>,
     mut discovery_commands: MessageWriter<UiDiscoveryCommand>,
+    mut bark_commands: MessageWriter<UiBarkCommand>,
     mut runtime: ResMut<RatRuntime>,
     mut state: ResMut<RatDialogueState>,
     mut library: ResMut<RatLibrary>,
+    mut variables: ResMut<RatVariables>,
     discovery_db: Res<UiDiscoveryDb>,
+    providers: Res<RatOptionProviders>,
+    resolvers: Res<RatTextResolvers>,
 ) {
     for msg in messages.read() {
         match msg {
@@ -508,6 +1271,18 @@ pub(super) fn handle_rat_commands(
                 library.scripts.insert(script.id.clone(), script.clone());
             }
             RatCommand::Start(start) => {
+                if start.presentation == RatDialoguePresentation::Ui {
+                    bark_commands.write(UiBarkCommand::Clear);
+                    // talking to one of them breaks the conversation up
+                    if let Some(target) = start.target
+                        && runtime
+                            .conversation
+                            .as_ref()
+                            .is_some_and(|conversation| conversation.involves(target))
+                    {
+                        runtime.conversation = None;
+                    }
+                }
                 start_dialogue(
                     &mut commands,
                     &mut hooks,
@@ -516,7 +1291,10 @@ pub(super) fn handle_rat_commands(
                     &library,
                     &mut runtime,
                     &mut state,
+                    &mut variables,
                     &discovery_db,
+                    &providers,
+                    &resolvers,
                     start.clone(),
                 );
             }
@@ -529,7 +1307,10 @@ pub(super) fn handle_rat_commands(
                     &library,
                     &mut runtime,
                     &mut state,
+                    &mut variables,
                     &discovery_db,
+                    &providers,
+                    &resolvers,
                 );
             }
             RatCommand::Choose(index) => {
@@ -541,23 +1322,92 @@ pub(super) fn handle_rat_commands(
                     &library,
                     &mut runtime,
                     &mut state,
+                    &mut variables,
                     &discovery_db,
+                    &providers,
+                    &resolvers,
                     *index,
                 );
             }
+            RatCommand::TimeOut => {
+                time_out(
+                    &mut commands,
+                    &mut hooks,
+                    &mut ui_commands,
+                    &mut discovery_commands,
+                    &library,
+                    &mut runtime,
+                    &mut state,
+                    &mut variables,
+                    &discovery_db,
+                    &providers,
+                    &resolvers,
+                );
+            }
             RatCommand::Close => {
                 let headless = is_headless(runtime.active.as_ref());
                 runtime.active = None;
                 state.active = false;
+                state.target = None;
                 if !headless {
                     ui_commands.write(UiDialogueCommand::Close);
                 }
                 commands.write_message(StopVoice);
             }
+            RatCommand::Interrupt => {
+                interrupt_dialogue(
+                    &mut commands,
+                    &mut hooks,
+                    &mut ui_commands,
+                    &mut runtime,
+                    &mut state,
+                );
+            }
+            RatCommand::Bark(start) => {
+                bark(
+                    &mut commands,
+                    &mut hooks,
+                    &mut bark_commands,
+                    &library,
+                    &runtime,
+                    &mut variables,
+                    &discovery_db,
+                    &resolvers,
+                    start,
+                );
+            }
+            RatCommand::Overhear(start) => {
+                overhear(&library, &mut runtime, start);
+            }
         }
     }
 }
 
+/// runs down the node's `timeout:` while no menu or reply sits over it
+pub(super) fn tick_dialogue_timeout(
+    time: Res<Time>,
+    mut runtime: ResMut<RatRuntime>,
+    mut rat_commands: MessageWriter<RatCommand>,
+) {
+    let Some(active) = runtime.active.as_mut() else {
+        return;
+    };
+    if active.overlay != DialogueOverlay::None {
+        return;
+    }
+    let Some(left) = active.time_left.as_mut() else {
+        return;
+    };
+    if *left <= 0.0 {
+        return;
+    }
+
+    *left -= time.delta_secs();
+    if *left <= 0.0 {
+        rat_commands.write(RatCommand::TimeOut);
+    }
+}
+
 fn start_dialogue(
     commands: &mut Commands,
     hooks: &mut MessageWriter<RatHookTriggered>,
@@ -566,7 +1416,10 @@ fn start_dialogue(
     library: &RatLibrary,
     runtime: &mut RatRuntime,
     state: &mut RatDialogueState,
+    variables: &mut RatVariables,
     discovery_db: &UiDiscoveryDb,
+    providers: &RatOptionProviders,
+    resolvers: &RatTextResolvers,
     start: RatStart,
 ) {
     let Some((script_id, entry_node)) = resolve_start_target(library, &start) else {
@@ -577,12 +1430,35 @@ fn start_dialogue(
         return;
     };
 
+    // any start on the target uses up what was left of an interrupted one
+    let interrupted = start
+        .target
+        .and_then(|target| runtime.interrupted.remove(&target));
+    if let Some(interrupted) = interrupted
+        && interrupted.start_id == start.script_id
+        && interrupted.presentation == start.presentation
+        && library
+            .scripts
+            .get(&interrupted.script_id)
+            .is_some_and(|script| script.nodes.contains_key(&interrupted.node_id))
+    {
+        resume_dialogue(commands, library, runtime, state, ui_commands, interrupted);
+        return;
+    }
+
     runtime.active = Some(ActiveDialogue {
+        start_id: start.script_id.clone(),
         script_id,
         node_id: entry_node.clone(),
         target: start.target,
         presentation: start.presentation,
         overlay: DialogueOverlay::None,
+        entered: false,
+        line: 0,
+        read: false,
+        time_left: None,
+        return_stack: Vec::new(),
+        silent_hops: 0,
     });
     discovery_commands.write(UiDiscoveryCommand::RecordInteraction {
         interaction: DiscoveryInteraction::new(
@@ -596,6 +1472,7 @@ fn start_dialogue(
         .note("dialogue.start"),
     });
     state.active = true;
+    state.target = start.target;
 
     show_current_node(
         commands,
@@ -605,20 +1482,316 @@ fn start_dialogue(
         library,
         runtime,
         state,
+        variables,
         discovery_db,
+        providers,
+        resolvers,
         true,
     );
 }
 
-fn resolve_start_target(library: &RatLibrary, start: &RatStart) -> Option<(String, String)> {
-    if let Some(script) = library.scripts.get(&start.script_id) {
-        let entry = start.entry.clone().unwrap_or_else(|| script.entry.clone());
-        if !script.nodes.contains_key(&entry) {
-            warn!(
-                "ratspinner entry '{}' not found in script '{}'",
-                entry, script.id
-            );
-            return None;
+/// reopens an interrupted dialogue on its node behind an "as I was saying"
+/// reply. the node was entered already, going back to it only redraws it
+fn resume_dialogue(
+    commands: &mut Commands,
+    library: &RatLibrary,
+    runtime: &mut RatRuntime,
+    state: &mut RatDialogueState,
+    ui_commands: &mut MessageWriter<UiDialogueCommand>,
+    mut active: ActiveDialogue,
+) {
+    let Some(node) = library
+        .scripts
+        .get(&active.script_id)
+        .and_then(|script| script.nodes.get(&active.node_id))
+    else {
+        return;
+    };
+
+    active.overlay = DialogueOverlay::Reply;
+    // the clock starts over, walking off shouldn't have used it up
+    active.time_left = node.timeout.as_ref().map(|timeout| timeout.secs);
+    let headless = is_headless(Some(&active));
+    let target = active.target;
+    runtime.active = Some(active);
+    state.active = true;
+    state.target = target;
+    if !headless {
+        let voice = runtime.next_speak_id();
+        show_reply(ui_commands, node, RESUME_LINE, None, "go on", voice);
+        commands.write_message(StopVoice);
+        let mut speak_msg = Speak::new(RESUME_LINE)
+            .id(voice)
+            .params(node.voice_params());
+        if let Some(target) = target {
+            speak_msg = speak_msg.target(target);
+        }
+        commands.write_message(speak_msg);
+    }
+}
+
+/// the player or the target walked off: the dialogue closes, fires
+/// `dialogue.interrupted` at the target and is kept for resuming
+fn interrupt_dialogue(
+    commands: &mut Commands,
+    hooks: &mut MessageWriter<RatHookTriggered>,
+    ui_commands: &mut MessageWriter<UiDialogueCommand>,
+    runtime: &mut RatRuntime,
+    state: &mut RatDialogueState,
+) {
+    let Some(mut active) = runtime.active.clone() else {
+        return;
+    };
+    hooks.write(
+        RatHookTriggered::new(INTERRUPTED_HOOK)
+            .script(active.script_id.clone())
+            .node(active.node_id.clone())
+            .target(active.target),
+    );
+    if let Some(target) = active.target {
+        // a menu or reply over the node isn't worth coming back to
+        active.overlay = DialogueOverlay::None;
+        runtime.interrupted.insert(target, active);
+    }
+    close_dialogue(commands, runtime, state, ui_commands);
+}
+
+/// one line of `start`'s node said over its target, nothing opens. guards,
+/// effects, variants and hooks work like in a dialogue, options and `->` are
+/// left alone
+fn bark(
+    commands: &mut Commands,
+    hooks: &mut MessageWriter<RatHookTriggered>,
+    bark_commands: &mut MessageWriter<UiBarkCommand>,
+    library: &RatLibrary,
+    runtime: &RatRuntime,
+    variables: &mut RatVariables,
+    discovery_db: &UiDiscoveryDb,
+    resolvers: &RatTextResolvers,
+    start: &RatStart,
+) {
+    // only headless dialogues leave the voice free, overheard npcs keep it
+    let dialogue_open = runtime.active.is_some() && !is_headless(runtime.active.as_ref());
+    if dialogue_open || runtime.conversation.is_some() {
+        return;
+    }
+    let Some((script_id, node_id)) = resolve_start_target(library, start) else {
+        return;
+    };
+    let Some(script) = library.scripts.get(&script_id) else {
+        return;
+    };
+    let Some(node) = resolve_guarded_node(script, &node_id, variables)
+        .and_then(|node_id| script.nodes.get(&node_id))
+    else {
+        return;
+    };
+    say_line(
+        commands,
+        hooks,
+        bark_commands,
+        library,
+        script,
+        node,
+        variables,
+        discovery_db,
+        resolvers,
+        start.target,
+    );
+}
+
+/// queues `start`'s script as a conversation, its first line goes out on the
+/// next tick. one at a time, anything asked for while one runs is dropped
+fn overhear(library: &RatLibrary, runtime: &mut RatRuntime, start: &RatStart) {
+    if runtime.conversation.is_some() {
+        return;
+    }
+    let Some((script_id, node_id)) = resolve_start_target(library, start) else {
+        return;
+    };
+    runtime.conversation = Some(Conversation {
+        script_id,
+        node_id: Some(node_id),
+        target: start.target,
+        cast: start.cast.clone(),
+        wait_left: 0.0,
+    });
+}
+
+/// says the conversation's lines one after another, each once the last one
+/// is done, following `->` or else the first visible option. waits while a
+/// dialogue with ui is open
+pub(super) fn tick_conversation(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut hooks: MessageWriter<RatHookTriggered>,
+    mut bark_commands: MessageWriter<UiBarkCommand>,
+    mut runtime: ResMut<RatRuntime>,
+    library: Res<RatLibrary>,
+    mut variables: ResMut<RatVariables>,
+    discovery_db: Res<UiDiscoveryDb>,
+    resolvers: Res<RatTextResolvers>,
+) {
+    if runtime.active.is_some() && !is_headless(runtime.active.as_ref()) {
+        return;
+    }
+    let Some(mut conversation) = runtime.conversation.take() else {
+        return;
+    };
+    conversation.wait_left -= time.delta_secs();
+    if conversation.wait_left > 0.0 {
+        runtime.conversation = Some(conversation);
+        return;
+    }
+    let Some(node_id) = conversation.node_id.take() else {
+        return;
+    };
+    let Some(script) = library.scripts.get(&conversation.script_id) else {
+        return;
+    };
+    let Some(node) = resolve_guarded_node(script, &node_id, &variables)
+        .and_then(|node_id| script.nodes.get(&node_id))
+    else {
+        return;
+    };
+
+    let speaker = conversation.speaker(node);
+    let said_secs = say_line(
+        &mut commands,
+        &mut hooks,
+        &mut bark_commands,
+        &library,
+        script,
+        node,
+        &mut variables,
+        &discovery_db,
+        &resolvers,
+        speaker,
+    );
+    conversation.wait_left = said_secs.map_or(0.0, |secs| secs + CONVERSATION_GAP_SECS);
+
+    let next = node.next.clone().or_else(|| {
+        visible_options(node, &variables)
+            .first()
+            .and_then(|option| option.next.clone())
+    });
+    let destination = match next.as_deref().map(RatTarget::parse) {
+        Some(Ok(RatTarget::Goto(node_ref) | RatTarget::Call(node_ref))) => {
+            resolve_node_ref(&library, &conversation.script_id, &node_ref)
+        }
+        Some(Ok(RatTarget::Return)) | None => None,
+        Some(Err(error)) => {
+            warn!("ratspinner bad conversation target: {error}");
+            None
+        }
+    };
+    if let Some((script_id, node_id)) = destination {
+        conversation.script_id = script_id;
+        conversation.node_id = Some(node_id);
+    }
+    runtime.conversation = Some(conversation);
+}
+
+/// runs a node's effects, visit and hooks, then `speaker` says its line with
+/// a floating subtitle. roughly how long that takes, `None` for no line.
+/// lines with a speaker play from the cached samples at their spot, lines
+/// nobody in the world says have no spot and stream instead
+fn say_line(
+    commands: &mut Commands,
+    hooks: &mut MessageWriter<RatHookTriggered>,
+    bark_commands: &mut MessageWriter<UiBarkCommand>,
+    library: &RatLibrary,
+    script: &RatScript,
+    node: &RatNode,
+    variables: &mut RatVariables,
+    discovery_db: &UiDiscoveryDb,
+    resolvers: &RatTextResolvers,
+    speaker: Option<Entity>,
+) -> Option<f32> {
+    variables.apply_all(&node.effects);
+    let line = pick_line(node, variables.visit(&script.id, &node.id));
+    variables.record_visit(&script.id, &node.id, line);
+    write_node_entered(commands, script, node, line, speaker);
+    for hook in &node.hooks {
+        hooks.write(
+            RatHookTriggered::new(hook)
+                .script(script.id.clone())
+                .node(node.id.clone())
+                .target(speaker),
+        );
+    }
+
+    let ctx = library.context(script, node, variables, discovery_db, resolvers);
+    let markup = RatMarkup::lenient(&resolvers.fill_line(node.line(line), &ctx));
+    let text = markup.plain();
+    if text.trim().is_empty() {
+        return None;
+    }
+    let params = node.voice_params();
+    let segments = markup.speak_segments();
+    let duration_secs = estimate_segments_duration_secs(&segments, &params);
+    bark_commands.write(UiBarkCommand::Show(UiBarkRequest {
+        target: speaker,
+        speaker: node.speaker.clone(),
+        text: text.clone(),
+        duration_secs,
+    }));
+    let speak_msg = Speak::new(text).params(params).segments(segments);
+    commands.write_message(match speaker {
+        Some(speaker) => speak_msg.target(speaker),
+        None => speak_msg.streamed(),
+    });
+    Some(duration_secs)
+}
+
+/// `RatNodeEntered`, plus the node's cues for `target` to act out
+fn write_node_entered(
+    commands: &mut Commands,
+    script: &RatScript,
+    node: &RatNode,
+    line: usize,
+    target: Option<Entity>,
+) {
+    commands.write_message(RatNodeEntered {
+        script_id: script.id.clone(),
+        node_id: node.id.clone(),
+        line,
+    });
+    if node.cues.acts() {
+        commands.write_message(RatCueTriggered {
+            script_id: script.id.clone(),
+            node_id: node.id.clone(),
+            target,
+            cues: node.cues.clone(),
+        });
+    }
+}
+
+fn resolve_start_target(library: &RatLibrary, start: &RatStart) -> Option<(String, String)> {
+    if start.script_id.starts_with('@') {
+        return match RatTarget::parse(&start.script_id) {
+            Ok(RatTarget::Goto(mut node_ref)) => {
+                node_ref.node = start.entry.clone().or(node_ref.node);
+                resolve_node_ref(library, "", &node_ref)
+            }
+            _ => {
+                warn!(
+                    "ratspinner start '{}' is not a script target",
+                    start.script_id
+                );
+                None
+            }
+        };
+    }
+
+    if let Some(script) = library.scripts.get(&start.script_id) {
+        let entry = start.entry.clone().unwrap_or_else(|| script.entry.clone());
+        if !script.nodes.contains_key(&entry) {
+            warn!(
+                "ratspinner entry '{}' not found in script '{}'",
+                entry, script.id
+            );
+            return None;
         }
         return Some((start.script_id.clone(), entry));
     }
@@ -649,7 +1822,10 @@ fn advance_dialogue(
     library: &RatLibrary,
     runtime: &mut RatRuntime,
     state: &mut RatDialogueState,
+    variables: &mut RatVariables,
     discovery_db: &UiDiscoveryDb,
+    providers: &RatOptionProviders,
+    resolvers: &RatTextResolvers,
 ) {
     let Some(active) = runtime.active.clone() else {
         return;
@@ -663,7 +1839,10 @@ fn advance_dialogue(
         return;
     };
 
-    if !node.options.is_empty() {
+    // under a reply advancing goes back to the node, like its `back` does.
+    // `options_from:` counts, a node can get all its options from providers
+    let ctx = library.context(script, node, variables, discovery_db, resolvers);
+    if active.overlay == DialogueOverlay::Reply || !providers.node_options(&ctx).is_empty() {
         choose_option(
             commands,
             hooks,
@@ -672,18 +1851,17 @@ fn advance_dialogue(
             library,
             runtime,
             state,
+            variables,
             discovery_db,
+            providers,
+            resolvers,
             0,
         );
         return;
     }
 
     if let Some(next) = node.next.clone() {
-        if let Some(active_mut) = runtime.active.as_mut() {
-            active_mut.node_id = next;
-            active_mut.overlay = DialogueOverlay::None;
-        }
-        show_current_node(
+        follow_target(
             commands,
             hooks,
             ui_commands,
@@ -691,8 +1869,11 @@ fn advance_dialogue(
             library,
             runtime,
             state,
+            variables,
             discovery_db,
-            true,
+            providers,
+            resolvers,
+            &next,
         );
     } else {
         close_dialogue(commands, runtime, state, ui_commands);
@@ -707,7 +1888,10 @@ fn choose_option(
     library: &RatLibrary,
     runtime: &mut RatRuntime,
     state: &mut RatDialogueState,
+    variables: &mut RatVariables,
     discovery_db: &UiDiscoveryDb,
+    providers: &RatOptionProviders,
+    resolvers: &RatTextResolvers,
     index: usize,
 ) {
     let Some(active_snapshot) = runtime.active.clone() else {
@@ -721,26 +1905,13 @@ fn choose_option(
         close_dialogue(commands, runtime, state, ui_commands);
         return;
     };
+    let ctx = library.context(script, node, variables, discovery_db, resolvers);
 
-    if active_snapshot.overlay == DialogueOverlay::InventoryPicker {
-        let items = discovery_db.entries(DiscoveryKind::Item);
-        if index < items.len() {
-            let item = &items[index];
-            let specific_response_id = format!("response_{}", item.id);
-            hooks.write(RatHookTriggered {
-                hook: "dialogue.show_item".to_string(),
-                script_id: active_snapshot.script_id.clone(),
-                node_id: active_snapshot.node_id.clone(),
-                option_id: Some(item.id.clone()),
-                target: active_snapshot.target,
-            });
-
-            if script.nodes.contains_key(&specific_response_id) {
-                if let Some(active_mut) = runtime.active.as_mut() {
-                    active_mut.node_id = specific_response_id;
-                    active_mut.overlay = DialogueOverlay::None;
-                }
-                show_current_node(
+    let option = match &active_snapshot.overlay {
+        DialogueOverlay::None => {
+            let options = providers.node_options(&ctx);
+            if options.is_empty() {
+                advance_dialogue(
                     commands,
                     hooks,
                     ui_commands,
@@ -748,49 +1919,22 @@ fn choose_option(
                     library,
                     runtime,
                     state,
+                    variables,
                     discovery_db,
-                    true,
+                    providers,
+                    resolvers,
                 );
                 return;
             }
-
-            if let Some(active_mut) = runtime.active.as_mut() {
-                active_mut.overlay = DialogueOverlay::ItemResponse;
-            }
-            discovery_commands.write(UiDiscoveryCommand::SetSeen {
-                kind: DiscoveryKind::Item,
-                id: item.id.clone(),
-                seen: true,
-            });
-            let already_shared = discovery_db.was_item_shared_with_speaker(
-                &item.id,
-                &active_snapshot.script_id,
-                &node.speaker,
-            );
-            discovery_commands.write(UiDiscoveryCommand::RecordInteraction {
-                interaction: DiscoveryInteraction::new(
-                    DiscoveryKind::Item,
-                    item.id.clone(),
-                    DiscoveryInteractionAction::Shared,
-                    DiscoveryInteractionActor::Speaker(node.speaker.clone()),
-                )
-                .script(active_snapshot.script_id.clone())
-                .node(active_snapshot.node_id.clone())
-                .option(item.id.clone())
-                .note("dialogue.show_item"),
-            });
-            let headless = is_headless(Some(&active_snapshot));
-            let line = show_item_response(ui_commands, node, item, already_shared, headless);
-            if !headless {
-                commands.write_message(StopVoice);
-                let mut speak_msg = Speak::new(line).voice(node.voice);
-                if let Some(target) = active_snapshot.target {
-                    speak_msg = speak_msg.target(target);
-                }
-                commands.write_message(speak_msg);
-            }
-            return;
+            let first = options.first().cloned();
+            options.into_iter().nth(index).or(first)
         }
+        // past the last entry is `back`
+        DialogueOverlay::Menu(provider) => providers.options(provider, &ctx).into_iter().nth(index),
+        DialogueOverlay::Reply => None,
+    };
+    let Some(option) = option else {
+        // back to the node from a menu or reply
         if let Some(active_mut) = runtime.active.as_mut() {
             active_mut.overlay = DialogueOverlay::None;
         }
@@ -802,17 +1946,38 @@ fn choose_option(
             library,
             runtime,
             state,
+            variables,
             discovery_db,
+            providers,
+            resolvers,
             true,
         );
         return;
+    };
+    if !option.enabled {
+        return;
     }
 
-    if active_snapshot.overlay == DialogueOverlay::ItemResponse {
-        if let Some(active_mut) = runtime.active.as_mut() {
-            active_mut.overlay = DialogueOverlay::None;
-        }
-        show_current_node(
+    variables.record_choice(
+        &active_snapshot.script_id,
+        &active_snapshot.node_id,
+        option.id.as_deref().unwrap_or(&option.text),
+    );
+    variables.apply_all(&option.effects);
+    for hook in &option.hooks {
+        hooks.write(
+            RatHookTriggered::new(hook)
+                .script(active_snapshot.script_id.clone())
+                .node(active_snapshot.node_id.clone())
+                .option(option.id.clone())
+                .target(active_snapshot.target),
+        );
+    }
+    discovery_commands.write_batch(option.discovery.iter().cloned());
+
+    let headless = is_headless(Some(&active_snapshot));
+    match option.action {
+        RatOptionAction::Goto(Some(next)) => follow_target(
             commands,
             hooks,
             ui_commands,
@@ -820,35 +1985,90 @@ fn choose_option(
             library,
             runtime,
             state,
+            variables,
             discovery_db,
-            true,
-        );
-        return;
-    }
-
-    let inventory_idx = node.options.len();
-    if can_show_items_in_dialogue(&active_snapshot.script_id) && index == inventory_idx {
-        if discovery_db.entries(DiscoveryKind::Item).is_empty() {
-            return;
-        }
-        open_inventory_picker(
-            ui_commands,
-            discovery_db,
-            &active_snapshot.script_id,
-            &node.speaker,
-            is_headless(Some(&active_snapshot)),
-        );
-        if let Some(active_mut) = runtime.active.as_mut() {
-            active_mut.overlay = DialogueOverlay::InventoryPicker;
+            providers,
+            resolvers,
+            &next,
+        ),
+        RatOptionAction::Goto(None) => close_dialogue(commands, runtime, state, ui_commands),
+        RatOptionAction::Reply(line) => {
+            if let Some(active_mut) = runtime.active.as_mut() {
+                active_mut.overlay = DialogueOverlay::Reply;
+            }
+            if !headless {
+                let ctx = library.context(script, node, variables, discovery_db, resolvers);
+                let line = resolvers.fill_line(&line, &ctx);
+                let voice = runtime.next_speak_id();
+                show_reply(ui_commands, node, &line, option.preview, "back", voice);
+                commands.write_message(StopVoice);
+                let mut speak_msg = Speak::new(RatMarkup::lenient(&line).plain())
+                    .id(voice)
+                    .params(node.voice_params());
+                if let Some(target) = active_snapshot.target {
+                    speak_msg = speak_msg.target(target);
+                }
+                commands.write_message(speak_msg);
+            }
         }
-        if !is_headless(Some(&active_snapshot)) {
-            commands.write_message(StopVoice);
+        RatOptionAction::Menu {
+            provider,
+            title,
+            prompt,
+        } => {
+            if !headless {
+                let ctx = library.context(script, node, variables, discovery_db, resolvers);
+                let options = providers.options(&provider, &ctx);
+                open_menu(ui_commands, &options, title, prompt);
+                commands.write_message(StopVoice);
+            }
+            if let Some(active_mut) = runtime.active.as_mut() {
+                active_mut.overlay = DialogueOverlay::Menu(provider);
+            }
         }
+    }
+}
+
+/// picks the node's `timeout:` option for the player, or follows it as a
+/// target when no option has that id or the option can't be picked
+fn time_out(
+    commands: &mut Commands,
+    hooks: &mut MessageWriter<RatHookTriggered>,
+    ui_commands: &mut MessageWriter<UiDialogueCommand>,
+    discovery_commands: &mut MessageWriter<UiDiscoveryCommand>,
+    library: &RatLibrary,
+    runtime: &mut RatRuntime,
+    state: &mut RatDialogueState,
+    variables: &mut RatVariables,
+    discovery_db: &UiDiscoveryDb,
+    providers: &RatOptionProviders,
+    resolvers: &RatTextResolvers,
+) {
+    let Some(active) = runtime.active.as_mut() else {
+        return;
+    };
+    // a pick in the same frame may have moved on already
+    if !active.time_left.is_some_and(|left| left <= 0.0) {
         return;
     }
+    active.time_left = None;
+    let Some(script) = library.scripts.get(&active.script_id) else {
+        return;
+    };
+    let Some(node) = script.nodes.get(&active.node_id) else {
+        return;
+    };
+    let Some(timeout) = node.timeout.clone() else {
+        return;
+    };
 
-    if node.options.is_empty() {
-        advance_dialogue(
+    let ctx = library.context(script, node, variables, discovery_db, resolvers);
+    let picked = providers
+        .node_options(&ctx)
+        .iter()
+        .position(|option| option.enabled && option.id.as_deref() == Some(timeout.next.as_str()));
+    match picked {
+        Some(index) => choose_option(
             commands,
             hooks,
             ui_commands,
@@ -856,37 +2076,13 @@ fn choose_option(
             library,
             runtime,
             state,
+            variables,
             discovery_db,
-        );
-        return;
-    }
-
-    let option = node
-        .options
-        .get(index)
-        .or_else(|| node.options.first())
-        .cloned();
-    let Some(option) = option else {
-        close_dialogue(commands, runtime, state, ui_commands);
-        return;
-    };
-
-    for hook in &option.hooks {
-        hooks.write(RatHookTriggered {
-            hook: hook.clone(),
-            script_id: active_snapshot.script_id.clone(),
-            node_id: active_snapshot.node_id.clone(),
-            option_id: option.id.clone(),
-            target: active_snapshot.target,
-        });
-    }
-
-    if let Some(next) = option.next.or_else(|| node.next.clone()) {
-        if let Some(active_mut) = runtime.active.as_mut() {
-            active_mut.node_id = next;
-            active_mut.overlay = DialogueOverlay::None;
-        }
-        show_current_node(
+            providers,
+            resolvers,
+            index,
+        ),
+        None => follow_target(
             commands,
             hooks,
             ui_commands,
@@ -894,26 +2090,119 @@ fn choose_option(
             library,
             runtime,
             state,
+            variables,
             discovery_db,
-            true,
-        );
-    } else {
+            providers,
+            resolvers,
+            &timeout.next,
+        ),
+    }
+}
+
+/// moves the active dialogue along a `next`/`->` target, which may leave the
+/// current script, `call` into another one or `return` to the caller
+fn follow_target(
+    commands: &mut Commands,
+    hooks: &mut MessageWriter<RatHookTriggered>,
+    ui_commands: &mut MessageWriter<UiDialogueCommand>,
+    discovery_commands: &mut MessageWriter<UiDiscoveryCommand>,
+    library: &RatLibrary,
+    runtime: &mut RatRuntime,
+    state: &mut RatDialogueState,
+    variables: &mut RatVariables,
+    discovery_db: &UiDiscoveryDb,
+    providers: &RatOptionProviders,
+    resolvers: &RatTextResolvers,
+    target: &str,
+) {
+    let Some(active) = runtime.active.as_mut() else {
+        return;
+    };
+    let destination = match RatTarget::parse(target) {
+        Ok(RatTarget::Goto(node_ref)) => resolve_node_ref(library, &active.script_id, &node_ref),
+        Ok(RatTarget::Call(node_ref)) => {
+            let destination = resolve_node_ref(library, &active.script_id, &node_ref);
+            if destination.is_some() {
+                active
+                    .return_stack
+                    .push((active.script_id.clone(), active.node_id.clone()));
+            }
+            destination
+        }
+        Ok(RatTarget::Return) => {
+            let caller = active.return_stack.pop();
+            if caller.is_none() {
+                warn!(
+                    "ratspinner '{}' returned with nothing on the call stack",
+                    active.script_id
+                );
+            }
+            caller
+        }
+        Err(error) => {
+            warn!("ratspinner bad target '{target}': {error}");
+            None
+        }
+    };
+
+    let Some((script_id, node_id)) = destination else {
         close_dialogue(commands, runtime, state, ui_commands);
+        return;
+    };
+    active.jump(script_id, node_id);
+    show_current_node(
+        commands,
+        hooks,
+        ui_commands,
+        discovery_commands,
+        library,
+        runtime,
+        state,
+        variables,
+        discovery_db,
+        providers,
+        resolvers,
+        true,
+    );
+}
+
+/// (script_id, node_id) a target points at, falling back to the script entry
+fn resolve_node_ref(
+    library: &RatLibrary,
+    current_script: &str,
+    node_ref: &RatNodeRef,
+) -> Option<(String, String)> {
+    let script_id = node_ref.script.as_deref().unwrap_or(current_script);
+    let Some(script) = library.scripts.get(script_id) else {
+        warn!("ratspinner script '{}' not found", script_id);
+        return None;
+    };
+    let node_id = node_ref.node.as_ref().unwrap_or(&script.entry);
+    if !script.nodes.contains_key(node_id) {
+        warn!(
+            "ratspinner node '{}' not found in script '{}'",
+            node_id, script.id
+        );
+        return None;
     }
+    Some((script.id.clone(), node_id.clone()))
 }
 
 fn show_current_node(
     commands: &mut Commands,
     hooks: &mut MessageWriter<RatHookTriggered>,
     ui_commands: &mut MessageWriter<UiDialogueCommand>,
-    _discovery_commands: &mut MessageWriter<UiDiscoveryCommand>,
+    discovery_commands: &mut MessageWriter<UiDiscoveryCommand>,
     library: &RatLibrary,
     runtime: &mut RatRuntime,
     state: &mut RatDialogueState,
+    variables: &mut RatVariables,
     discovery_db: &UiDiscoveryDb,
+    providers: &RatOptionProviders,
+    resolvers: &RatTextResolvers,
     speak: bool,
 ) {
-    let Some(active) = runtime.active.clone() else {
+    let Some(mut active) = runtime.active.clone() else {
         close_dialogue(commands, runtime, state, ui_commands);
         return;
     };
@@ -921,107 +2210,282 @@ fn show_current_node(
         close_dialogue(commands, runtime, state, ui_commands);
         return;
     };
-    let Some(node) = script.nodes.get(&active.node_id) else {
+    // guards are only checked on the way in, the node's own effects or visit
+    // must not skip it on a redraw
+    let node_id = if active.entered {
+        Some(active.node_id.clone())
+    } else {
+        resolve_guarded_node(script, &active.node_id, variables)
+    };
+    let Some(node) = node_id.and_then(|node_id| script.nodes.get(&node_id)) else {
         close_dialogue(commands, runtime, state, ui_commands);
         return;
     };
 
+    active.node_id = node.id.clone();
+    active.silent_hops = if node.is_silent() {
+        active.silent_hops + 1
+    } else {
+        0
+    };
+    if active.silent_hops > MAX_SILENT_HOPS {
+        error!(
+            "ratspinner silent nodes loop forever through '{}' in script '{}', closing the dialogue",
+            node.id, script.id
+        );
+        close_dialogue(commands, runtime, state, ui_commands);
+        return;
+    }
+    if !active.entered {
+        variables.apply_all(&node.effects);
+        active.line = pick_line(node, variables.visit(&script.id, &node.id));
+        active.read = variables.seen().read(&script.id, &node.id, active.line);
+        active.time_left = node.timeout.as_ref().map(|timeout| timeout.secs);
+        variables.record_visit(&script.id, &node.id, active.line);
+        active.entered = true;
+        write_node_entered(commands, script, node, active.line, active.target);
+    }
+    runtime.active = Some(active.clone());
+    let ctx = library.context(script, node, variables, discovery_db, resolvers);
+    let markup = RatMarkup::lenient(&resolvers.fill_line(node.line(active.line), &ctx));
+
     for hook in &node.hooks {
-        hooks.write(RatHookTriggered {
-            hook: hook.clone(),
-            script_id: active.script_id.clone(),
-            node_id: active.node_id.clone(),
-            option_id: None,
-            target: active.target,
-        });
+        hooks.write(
+            RatHookTriggered::new(hook)
+                .script(active.script_id.clone())
+                .node(active.node_id.clone())
+                .target(active.target),
+        );
+    }
+
+    if node.is_silent() {
+        advance_dialogue(
+            commands,
+            hooks,
+            ui_commands,
+            discovery_commands,
+            library,
+            runtime,
+            state,
+            variables,
+            discovery_db,
+            providers,
+            resolvers,
+        );
+        return;
     }
 
+    let voice = (speak && !is_headless(Some(&active))).then(|| runtime.next_speak_id());
     if !is_headless(Some(&active)) {
-        ui_commands.write(UiDialogueCommand::Start(UiDialogueRequest {
-            mode: UiDialogueMode::Standard,
-            speaker: node.speaker.clone(),
-            text: node.text.clone(),
-            portrait_path: node.portrait_path.clone(),
-            preview: None,
-            options: dialogue_options_for_node(node, discovery_db, &active.script_id),
-            reveal_duration_secs: if speak {
-                estimate_speech_duration_secs(&node.text, node.voice)
-            } else {
-                0.0
-            },
-        }));
+        // first words from a speaker with contact info add them to the gallery
+        if let Some(speaker_id) = &node.speaker_id
+            && let Some(entry) = library
+                .speakers
+                .get(speaker_id)
+                .and_then(|speaker| speaker.discovery_entry(speaker_id))
+            && !discovery_db
+                .entries(DiscoveryKind::Npc)
+                .iter()
+                .any(|known| known.id == entry.id)
+        {
+            discovery_commands.write(UiDiscoveryCommand::Upsert {
+                kind: DiscoveryKind::Npc,
+                entry,
+            });
+        }
+
+        let reveal_duration_secs = if speak {
+            estimate_segments_duration_secs(&markup.speak_segments(), &node.voice_params())
+        } else {
+            0.0
+        };
+        let ctx = library.context(script, node, variables, discovery_db, resolvers);
+        ui_commands.write(UiDialogueCommand::Start(node_dialogue_request(
+            &ctx,
+            providers,
+            active.line,
+            active.read,
+            active.ui_timeout(node),
+            reveal_duration_secs,
+            voice,
+        )));
     }
 
-    if speak && !is_headless(Some(&active)) {
+    if let Some(voice) = voice {
         commands.write_message(StopVoice);
-        let mut speak_msg = Speak::new(node.text.clone()).voice(node.voice);
+        let mut speak_msg = Speak::new(markup.plain())
+            .id(voice)
+            .params(node.voice_params())
+            .segments(markup.speak_segments());
         if let Some(target) = active.target {
             speak_msg = speak_msg.target(target);
         }
         commands.write_message(speak_msg);
+        prewarm_next_lines(
+            commands,
+            library,
+            script,
+            node,
+            variables,
+            discovery_db,
+            providers,
+            resolvers,
+        );
     }
 }
 
-fn dialogue_options_for_node(
+/// has the voice synthesize the lines one step past `node` while this one
+/// plays: its `next`, its options' targets, provided ones too, and its
+/// timeout. guards and variants are judged as things stand now, a wrong guess
+/// only costs the synth
+fn prewarm_next_lines(
+    commands: &mut Commands,
+    library: &RatLibrary,
+    script: &RatScript,
     node: &RatNode,
+    variables: &RatVariables,
     discovery_db: &UiDiscoveryDb,
-    script_id: &str,
-) -> Vec<UiDialogueOption> {
-    let mut options: Vec<UiDialogueOption> = node
-        .options
+    providers: &RatOptionProviders,
+    resolvers: &RatTextResolvers,
+) {
+    let ctx = library.context(script, node, variables, discovery_db, resolvers);
+    let options = providers.node_options(&ctx);
+    let targets = node
+        .next
         .iter()
-        .map(|option| UiDialogueOption {
-            text: option.text.clone(),
-            preview: None,
-            item_id: None,
-            seen: false,
-            enabled: true,
-        })
-        .collect();
+        .chain(options.iter().filter_map(|option| match &option.action {
+            RatOptionAction::Goto(next) => next.as_ref(),
+            _ => None,
+        }))
+        .chain(node.timeout_target());
+    for target in targets {
+        let node_ref = match RatTarget::parse(target) {
+            Ok(RatTarget::Goto(node_ref) | RatTarget::Call(node_ref)) => node_ref,
+            _ => continue,
+        };
+        let script_id = node_ref.script.as_deref().unwrap_or(&script.id);
+        let Some(next_script) = library.scripts.get(script_id) else {
+            continue;
+        };
+        let node_id = node_ref.node.as_ref().unwrap_or(&next_script.entry);
+        let Some(next) = resolve_guarded_node(next_script, node_id, variables)
+            .and_then(|node_id| next_script.nodes.get(&node_id))
+        else {
+            continue;
+        };
+        if next.is_silent() {
+            continue;
+        }
+        let line = pick_line(next, variables.visit(&next_script.id, &next.id));
+        let ctx = library.context(next_script, next, variables, discovery_db, resolvers);
+        let markup = RatMarkup::lenient(&resolvers.fill_line(next.line(line), &ctx));
+        commands.write_message(PrewarmVoice(
+            Speak::new(markup.plain())
+                .params(next.voice_params())
+                .segments(markup.speak_segments()),
+        ));
+    }
+}
 
-    if can_show_items_in_dialogue(script_id) {
-        options.push(UiDialogueOption {
-            text: "Show item...".to_string(),
-            preview: None,
-            item_id: None,
-            seen: false,
-            enabled: !discovery_db.entries(DiscoveryKind::Item).is_empty(),
-        });
+fn node_dialogue_request(
+    ctx: &RatOptionContext,
+    providers: &RatOptionProviders,
+    line: usize,
+    read: bool,
+    timeout: Option<UiDialogueTimeout>,
+    reveal_duration_secs: f32,
+    voice: Option<SpeakId>,
+) -> UiDialogueRequest {
+    let node = ctx.node;
+    UiDialogueRequest {
+        mode: UiDialogueMode::Standard,
+        speaker: node.speaker.clone(),
+        text: ctx.resolvers.fill_line(node.line(line), ctx),
+        portrait_path: node.portrait_path.clone(),
+        preview: None,
+        options: providers
+            .node_options(ctx)
+            .iter()
+            .map(|option| UiDialogueOption {
+                text: ctx.resolvers.fill_plain(&option.text, ctx),
+                ..option.ui_option()
+            })
+            .collect(),
+        reveal_duration_secs,
+        voice,
+        read,
+        timeout,
     }
-    options
 }
 
-fn can_show_items_in_dialogue(script_id: &str) -> bool {
-    !script_id.starts_with("npc.phone")
+/// walks `next` links (`else` for a failed guard, if set) past nodes whose
+/// `when:` guard does not hold or whose `once` lines are used up. only falls
+/// through within the same script
+fn resolve_guarded_node(
+    script: &RatScript,
+    node_id: &str,
+    variables: &RatVariables,
+) -> Option<String> {
+    let mut current = node_id.to_string();
+    for _ in 0..=script.nodes.len() {
+        let node = script.nodes.get(&current)?;
+        let visits = variables.visit(&script.id, &current).count;
+        let guard_holds = variables.check(&node.when);
+        if guard_holds && !node.used_up(visits) {
+            return Some(current);
+        }
+        let skip_to = match &node.else_next {
+            Some(else_next) if !guard_holds => else_next,
+            _ => node.next.as_ref()?,
+        };
+        let next = RatTarget::parse(skip_to).ok()?;
+        current = next.local_node()?.to_string();
+    }
+    warn!(
+        "ratspinner guards in script '{}' loop forever from '{}'",
+        script.id, node_id
+    );
+    None
 }
 
-fn open_inventory_picker(
-    ui_commands: &mut MessageWriter<UiDialogueCommand>,
-    discovery_db: &UiDiscoveryDb,
-    script_id: &str,
-    speaker: &str,
-    headless: bool,
-) {
-    if headless {
-        return;
+/// which text line a node shows on this visit
+fn pick_line(node: &RatNode, visit: RatVisit) -> usize {
+    let count = node.line_count();
+    let visits = visit.count as usize;
+    match node.variant_mode {
+        RatVariantMode::Sequence | RatVariantMode::Once => visits.min(count - 1),
+        RatVariantMode::Cycle => visits % count,
+        RatVariantMode::Random if count == 1 => 0,
+        RatVariantMode::Random => match visit.last_line {
+            // roll among the others, then step over the last one
+            Some(last) => {
+                let line = rand::rng().random_range(0..count - 1);
+                if line >= last { line + 1 } else { line }
+            }
+            None => rand::rng().random_range(0..count),
+        },
     }
-    let mut options: Vec<UiDialogueOption> = discovery_db
-        .entries(DiscoveryKind::Item)
+}
+
+pub(super) fn visible_options<'a>(
+    node: &'a RatNode,
+    variables: &RatVariables,
+) -> Vec<&'a RatOption> {
+    node.options
         .iter()
-        .map(|entry| UiDialogueOption {
-            text: entry.title.clone(),
-            preview: Some(UiDialoguePreview {
-                title: entry.title.clone(),
-                subtitle: entry.subtitle.clone(),
-                description: entry.description.clone(),
-                image_path: entry.image_path.clone(),
-                model_path: entry.model_path.clone(),
-            }),
-            item_id: Some(entry.id.clone()),
-            seen: discovery_db.was_item_shared_with_speaker(&entry.id, script_id, speaker),
-            enabled: true,
-        })
-        .collect();
+        .filter(|option| variables.check(&option.conditions))
+        .collect()
+}
+
+/// a provider's options in the picker, with `back` at the end
+fn open_menu(
+    ui_commands: &mut MessageWriter<UiDialogueCommand>,
+    options: &[RatProvidedOption],
+    title: String,
+    prompt: String,
+) {
+    let mut options: Vec<UiDialogueOption> =
+        options.iter().map(RatProvidedOption::ui_option).collect();
     options.push(UiDialogueOption {
         text: "back".to_string(),
         preview: None,
@@ -1034,55 +2498,44 @@ fn open_inventory_picker(
 
     ui_commands.write(UiDialogueCommand::Start(UiDialogueRequest {
         mode: UiDialogueMode::Inventory,
-        speaker: "inventory".to_string(),
-        text: "pick an item to show".to_string(),
+        speaker: title,
+        text: prompt,
         portrait_path: "models/npc_a/npc_a.png".to_string(),
         preview: initial_preview,
         options,
         reveal_duration_secs: 0.0,
+        voice: None,
+        read: false,
+        timeout: None,
     }));
 }
 
-fn show_item_response(
+fn show_reply(
     ui_commands: &mut MessageWriter<UiDialogueCommand>,
     node: &RatNode,
-    item: &crate::ui::DiscoveryEntry,
-    already_shared: bool,
-    headless: bool,
-) -> String {
-    let line = format!(
-        "hmm... {}. {}",
-        item.title,
-        if already_shared {
-            "i remember this"
-        } else {
-            "what is this?"
-        }
-    );
-    if !headless {
-        ui_commands.write(UiDialogueCommand::Start(UiDialogueRequest {
-            mode: UiDialogueMode::Standard,
-            speaker: node.speaker.clone(),
-            text: line.clone(),
-            portrait_path: node.portrait_path.clone(),
-            preview: Some(UiDialoguePreview {
-                title: item.title.clone(),
-                subtitle: item.subtitle.clone(),
-                description: item.description.clone(),
-                image_path: item.image_path.clone(),
-                model_path: item.model_path.clone(),
-            }),
-            options: vec![UiDialogueOption {
-                text: "back".to_string(),
-                preview: None,
-                item_id: None,
-                seen: false,
-                enabled: true,
-            }],
-            reveal_duration_secs: estimate_speech_duration_secs(&line, node.voice),
-        }));
-    }
-    line
+    line: &str,
+    preview: Option<UiDialoguePreview>,
+    back: &str,
+    voice: SpeakId,
+) {
+    ui_commands.write(UiDialogueCommand::Start(UiDialogueRequest {
+        mode: UiDialogueMode::Standard,
+        speaker: node.speaker.clone(),
+        text: line.to_string(),
+        portrait_path: node.portrait_path.clone(),
+        preview,
+        options: vec![UiDialogueOption {
+            text: back.to_string(),
+            preview: None,
+            item_id: None,
+            seen: false,
+            enabled: true,
+        }],
+        reveal_duration_secs: estimate_speech_duration_secs(line, &node.voice_params()),
+        voice: Some(voice),
+        read: false,
+        timeout: None,
+    }));
 }
 
 fn close_dialogue(
@@ -1094,6 +2547,7 @@ fn close_dialogue(
     let headless = is_headless(runtime.active.as_ref());
     runtime.active = None;
     state.active = false;
+    state.target = None;
     if !headless {
         ui_commands.write(UiDialogueCommand::Close);
         commands.write_message(StopVoice);
@@ -1103,3 +2557,830 @@ fn close_dialogue(
 fn is_headless(active: Option<&ActiveDialogue>) -> bool {
     active.is_some_and(|dialogue| dialogue.presentation == RatDialoguePresentation::Headless)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ratspinner::{
+        harness::RatHarness,
+        lint::read_speakers,
+        printer::write_rat,
+        providers::RatOptionsAppExt,
+        vars::{RatCompare, RatValue},
+    };
+
+    const CELLAR: &str = "\
+// script: cellar
+// entry: door
+
+[door]
+text: the door gives a little.
+when: has_key && tries < 3
+set: opened = true
+inc: tries 2
+-> locked
+> knock. -> door [id: knock] [if: !opened] [inc: knocks]
+> go down. -> stairs [id: down] [if: tries >= 2] [set: mood = \"sour\"]
+
+[locked]
+text: locked.
+
+[stairs]
+text: it's dark.
+";
+
+    /// strict, like `rat-lint`
+    fn parse(file: &str, source: &str) -> Result<Vec<RatScript>, RatScriptAssetLoaderError> {
+        parse_script_bytes(
+            source.as_bytes(),
+            Path::new(file),
+            true,
+            &RatSpeakers::default(),
+        )
+    }
+
+    fn cellar() -> RatScript {
+        parse("cellar.rat", CELLAR)
+            .expect("cellar should parse")
+            .remove(0)
+    }
+
+    #[test]
+    fn guards_and_effects_parse() {
+        let script = cellar();
+        let door = &script.nodes["door"];
+        assert_eq!(
+            door.when,
+            [
+                RatCondition::truthy("has_key"),
+                RatCondition::Compare {
+                    var: "tries".into(),
+                    op: RatCompare::Lt,
+                    value: RatValue::Int(3),
+                },
+            ]
+        );
+        assert_eq!(
+            door.effects,
+            [
+                RatEffect::set("opened", RatValue::Bool(true)),
+                RatEffect::inc("tries", 2),
+            ]
+        );
+        assert_eq!(
+            door.options[0].conditions,
+            [RatCondition::Falsy("opened".into())]
+        );
+        assert_eq!(door.options[0].effects, [RatEffect::inc("knocks", 1)]);
+        assert_eq!(
+            door.options[1].effects,
+            [RatEffect::set("mood", RatValue::Str("sour".into()))]
+        );
+    }
+
+    #[test]
+    fn failed_guards_fall_through_to_next() {
+        let script = cellar();
+        let door = &script.nodes["door"];
+        let mut variables = RatVariables::default();
+        let resolve = |variables: &RatVariables| resolve_guarded_node(&script, "door", variables);
+        assert_eq!(resolve(&variables).as_deref(), Some("locked"));
+
+        variables.set("has_key", RatValue::Bool(true));
+        assert_eq!(resolve(&variables).as_deref(), Some("door"));
+        variables.apply_all(&door.effects);
+        assert_eq!(variables.get("tries"), Some(&RatValue::Int(2)));
+        let ids: Vec<Option<&str>> = visible_options(door, &variables)
+            .iter()
+            .map(|option| option.id.as_deref())
+            .collect();
+        assert_eq!(ids, [Some("down")], "`knock` needs !opened");
+
+        // `tries` goes up by two a visit, the second time through it's locked
+        variables.apply_all(&door.effects);
+        assert_eq!(resolve(&variables).as_deref(), Some("locked"));
+    }
+
+    #[test]
+    fn bad_guards_name_their_line() {
+        let error = parse("bad_if.rat", "[start]\ntext: hi.\n> ok. [if: lies told]\n")
+            .expect_err("a variable name can't have spaces");
+        assert!(error.to_string().contains("bad_if.rat:3:"), "{error}");
+    }
+
+    const TYPOS: &str = "\
+// script: typos
+// entry: start
+
+[start]
+speakr: mr. d.
+text: close enough.
+> fine. -> end [hook: typos.fine] [iff: never]
+
+[end]
+text: bye.
+";
+
+    #[test]
+    fn parse_errors_point_at_the_line_and_column() {
+        let error = parse("typos.rat", TYPOS).expect_err("strict parsing stops at the typo");
+        assert_eq!(
+            error.to_string(),
+            "unknown key 'speakr' at typos.rat:5:1 (did you mean 'speaker'?)"
+        );
+
+        // lines count from the top of the file, columns from the start of the line
+        let source = "\
+// script: first
+[start]
+text: fine.
+
+// script: second
+[start]
+text: hm.
+  voice: squeaky
+";
+        let error = parse("two.rat", source).expect_err("squeaky is not a voice");
+        assert!(
+            error
+                .to_string()
+                .starts_with("unknown voice 'squeaky' at two.rat:8:10"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn lenient_parsing_skips_typos() {
+        let scripts = parse_script_bytes(
+            TYPOS.as_bytes(),
+            Path::new("typos.rat"),
+            false,
+            &RatSpeakers::default(),
+        )
+        .expect("the game loads past typos");
+        let start = &scripts[0].nodes["start"];
+        assert_eq!(start.text, "close enough.");
+        assert_eq!(
+            start.options[0].next.as_deref(),
+            Some("end"),
+            "the unknown annotation goes, not the option"
+        );
+        assert_eq!(start.options[0].hooks, ["typos.fine"]);
+    }
+
+    const DESK: &str = "\
+// script: desk
+// entry: hub
+
+[hub]
+text: anything else?
+> who are you? -> call @shared:identity [id: who]
+> where am i? -> @shared [id: where]
+
+// script: shared
+// entry: lobby
+
+[lobby]
+text: the lobby.
+-> return
+
+[identity]
+text: i'm the night clerk.
+-> more
+
+[more]
+text: been here for years.
+-> return
+";
+
+    #[test]
+    fn targets_reach_into_other_scripts() {
+        let mut library = RatLibrary::default();
+        for script in parse("desk.rat", DESK).expect("desk should parse") {
+            library.scripts.insert(script.id.clone(), script);
+        }
+        let shared = |node: Option<&str>| RatNodeRef {
+            script: Some("shared".into()),
+            node: node.map(str::to_string),
+        };
+        let local = |node: &str| RatNodeRef {
+            script: None,
+            node: Some(node.into()),
+        };
+        let targets: Vec<RatTarget> = library.scripts["desk"].nodes["hub"]
+            .options
+            .iter()
+            .map(|option| {
+                RatTarget::parse(option.next.as_deref().unwrap_or_default())
+                    .expect("target should parse")
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                RatTarget::Call(shared(Some("identity"))),
+                RatTarget::Goto(shared(None)),
+            ]
+        );
+        assert_eq!(
+            RatTarget::parse(
+                library.scripts["shared"].nodes["more"]
+                    .next
+                    .as_deref()
+                    .unwrap_or_default()
+            ),
+            Ok(RatTarget::Return)
+        );
+
+        // a bare `@script` is its entry, local refs stay in the current script
+        assert_eq!(
+            resolve_node_ref(&library, "desk", &shared(None)),
+            Some(("shared".into(), "lobby".into()))
+        );
+        assert_eq!(
+            resolve_node_ref(&library, "shared", &local("more")),
+            Some(("shared".into(), "more".into()))
+        );
+        assert_eq!(resolve_node_ref(&library, "desk", &local("more")), None);
+        assert_eq!(
+            resolve_node_ref(
+                &library,
+                "desk",
+                &RatNodeRef {
+                    script: Some("attic".into()),
+                    node: None,
+                }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn calls_return_to_the_calling_node() {
+        let mut harness = RatHarness::new();
+        for script in parse("desk.rat", DESK).expect("desk should parse") {
+            harness.register(script);
+        }
+
+        harness.start(RatStart::new("desk"));
+        harness.choose_option("who").expect("who is listed");
+        assert_eq!(
+            harness.current(),
+            Some(("shared".into(), "identity".into()))
+        );
+        harness.advance();
+        assert_eq!(harness.current(), Some(("shared".into(), "more".into())));
+        harness.advance();
+        assert_eq!(harness.current(), Some(("desk".into(), "hub".into())));
+
+        // a plain jump leaves nothing to return to, `return` ends the dialogue
+        harness.choose_option("where").expect("where is listed");
+        assert_eq!(harness.current(), Some(("shared".into(), "lobby".into())));
+        harness.advance();
+        assert!(!harness.is_open());
+    }
+
+    #[test]
+    fn silent_loops_close_the_dialogue() {
+        let source = "\
+// script: stairwell
+// entry: landing
+
+[landing]
+text: the stairs go on.
+> down. -> down [id: down]
+
+[down]
+inc: floors
+-> turn
+
+[turn]
+-> down
+";
+        let mut harness = RatHarness::new();
+        for script in parse("stairwell.rat", source).expect("stairwell should parse") {
+            harness.register(script);
+        }
+
+        harness.start(RatStart::new("stairwell"));
+        harness.choose_option("down").expect("down is listed");
+        assert!(!harness.is_open(), "the loop ends the dialogue");
+        assert_eq!(
+            harness.variables().get("floors"),
+            Some(&RatValue::Int(MAX_SILENT_HOPS as i64 / 2))
+        );
+    }
+
+    const MOODS: &str = "\
+// script: moods
+// entry: sequence
+
+[sequence]
+text: one.
+text: two.
+variants: sequence
+
+[cycle]
+text: one.
+text: two.
+variants: cycle
+
+[shuffle]
+text: one.
+text: two.
+text: three.
+variants: shuffle
+
+[once]
+text: one.
+text: two.
+variants: once
+-> after
+
+[after]
+text: nothing new.
+";
+
+    #[test]
+    fn variant_modes_pick_a_line_per_visit() {
+        let script = parse("moods.rat", MOODS)
+            .expect("moods should parse")
+            .remove(0);
+        assert_eq!(script.nodes["shuffle"].variant_mode, RatVariantMode::Random);
+        let mut variables = RatVariables::default();
+        // what `show_current_node` does on every entry
+        let mut visit = |node_id: &str, visits: usize| -> Vec<String> {
+            (0..visits)
+                .map(|_| {
+                    let shown = resolve_guarded_node(&script, node_id, &variables)
+                        .expect("every mood leads somewhere");
+                    let node = &script.nodes[&shown];
+                    let line = pick_line(node, variables.visit(&script.id, &shown));
+                    variables.record_visit(&script.id, &shown, line);
+                    node.line(line).to_string()
+                })
+                .collect()
+        };
+
+        assert_eq!(visit("sequence", 4), ["one.", "two.", "two.", "two."]);
+        assert_eq!(visit("cycle", 4), ["one.", "two.", "one.", "two."]);
+        let shuffled = visit("shuffle", 12);
+        assert!(
+            shuffled.windows(2).all(|pair| pair[0] != pair[1]),
+            "random never says the same line twice in a row: {shuffled:?}"
+        );
+        assert_eq!(visit("once", 3), ["one.", "two.", "nothing new."]);
+    }
+
+    #[test]
+    fn timeout_picks_the_default_option() {
+        let source = "\
+// script: interrogation
+// entry: alibi
+
+[alibi]
+text: where were you at nine?
+timeout: 6 -> silence
+> at home. -> home [id: home]
+> ... -> stare [id: silence] [hook: suspect.silent]
+
+[home]
+text: alone?
+timeout: 3 -> stare
+> yes. [id: yes]
+
+[stare]
+text: ...
+";
+        let scripts = parse("interrogation.rat", source).expect("script should parse");
+        assert!(write_rat(&scripts, &RatSpeakers::default()).contains("timeout: 6 -> silence\n"));
+
+        let mut harness = RatHarness::new();
+        harness.register(scripts[0].clone());
+        harness.start(RatStart::new("interrogation"));
+        harness.wait(5.5);
+        assert_eq!(
+            harness.current(),
+            Some(("interrogation".into(), "alibi".into()))
+        );
+        harness.wait(1.0);
+        assert_eq!(
+            harness.current(),
+            Some(("interrogation".into(), "stare".into()))
+        );
+        assert!(harness.transcript().fired("suspect.silent"));
+
+        // no option with that id, the countdown follows it as a target
+        harness.start(RatStart::new("interrogation"));
+        harness.choose_option("home").expect("home is listed");
+        harness.wait(3.5);
+        assert_eq!(
+            harness.current(),
+            Some(("interrogation".into(), "stare".into()))
+        );
+    }
+
+    #[test]
+    fn barks_leave_the_dialogue_closed() {
+        let mut harness = RatHarness::new();
+        harness.register(
+            RatScriptBuilder::new("hallway")
+                .entry("idle")
+                .node(
+                    RatNodeBuilder::new("idle")
+                        .text("just me here.")
+                        .variant("still just me.")
+                        .variant_mode(RatVariantMode::Cycle)
+                        .hook("npc.fidget"),
+                )
+                .node(RatNodeBuilder::new("talk").text("yes?"))
+                .build(),
+        );
+        harness.bark(RatStart::new("hallway"));
+        assert!(!harness.is_open());
+        assert!(harness.transcript().visited("hallway", "idle"));
+        assert!(harness.transcript().fired("npc.fidget"));
+
+        // a headless dialogue has no voice to talk over
+        harness.start(RatStart::new("hallway").entry("talk"));
+        harness.bark(RatStart::new("hallway"));
+        assert_eq!(harness.current(), Some(("hallway".into(), "talk".into())));
+        let lines: Vec<usize> = harness
+            .transcript()
+            .nodes
+            .iter()
+            .filter(|node| node.node_id == "idle")
+            .map(|node| node.line)
+            .collect();
+        assert_eq!(lines, [0, 1]);
+    }
+
+    fn shipped_speakers() -> RatSpeakers {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join(SPEAKERS_PATH);
+        read_speakers(&path).expect("speakers.ron should load")
+    }
+
+    #[test]
+    fn overheard_lines_go_to_each_speaker_in_turn() {
+        let source = "\
+// script: couch
+// entry: ask
+
+[ask]
+speaker: marcus.lounging
+text: did you see the new one?
+hook: npc.talk
+-> answer
+
+[answer]
+speaker: marcus.leaning
+text: there's just me.
+set: overheard_couch = true
+hook: npc.talk
+";
+        let scripts = parse_script_bytes(
+            source.as_bytes(),
+            Path::new("couch.rat"),
+            true,
+            &shipped_speakers(),
+        )
+        .expect("script should parse");
+
+        let mut harness = RatHarness::new();
+        harness.register(scripts[0].clone());
+        let world = harness.app_mut().world_mut();
+        let lounging = world.spawn_empty().id();
+        let leaning = world.spawn_empty().id();
+        harness.overhear(
+            RatStart::new("couch")
+                .cast("marcus.lounging", lounging)
+                .cast("marcus.leaning", leaning),
+        );
+        assert_eq!(harness.overhearing().as_deref(), Some("couch"));
+        assert!(!harness.is_open());
+
+        // the second line waits for the first to be said
+        harness.wait(0.1);
+        assert!(harness.transcript().visited("couch", "ask"));
+        assert!(!harness.transcript().visited("couch", "answer"));
+        harness.wait(10.0);
+        assert!(harness.transcript().visited("couch", "answer"));
+        assert_eq!(harness.overhearing(), None);
+
+        let speakers: Vec<_> = harness
+            .transcript()
+            .hooks
+            .iter()
+            .map(|hook| hook.target)
+            .collect();
+        assert_eq!(speakers, [Some(lounging), Some(leaning)]);
+        assert_eq!(
+            harness.variables().get("overheard_couch"),
+            Some(&RatValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn cues_go_to_the_dialogue_target() {
+        let source = "\
+// script: wall
+// entry: glare
+
+[glare]
+speaker: marcus.leaning
+text: you again.
+emote: angry
+anim: idle_lean
+look: player
+sfx: door_locked
+-> shrug
+
+[shrug]
+speaker: marcus.leaning
+text: whatever.
+";
+        let mut speakers = shipped_speakers();
+        speakers
+            .0
+            .get_mut("marcus.leaning")
+            .expect("marcus.leaning should be a speaker")
+            .portraits
+            .insert("angry".into(), "sprites/npc_cowering.png".into());
+        let scripts = parse_script_bytes(source.as_bytes(), Path::new("wall.rat"), true, &speakers)
+            .expect("script should parse");
+        // the emote brings its portrait along, no `portrait:` needed either way
+        assert_eq!(
+            scripts[0].nodes["glare"].portrait_path,
+            "sprites/npc_cowering.png"
+        );
+        let written = write_rat(&scripts, &speakers);
+        assert!(written.contains("emote: angry\n"));
+        assert!(written.contains("sfx: door_locked\n"));
+        assert!(!written.contains("portrait:"));
+
+        let mut harness = RatHarness::new();
+        harness.register(scripts[0].clone());
+        let npc = harness.app_mut().world_mut().spawn_empty().id();
+        harness.start(RatStart::new("wall").target(npc));
+        harness.advance();
+        assert!(harness.transcript().visited("wall", "shrug"));
+
+        let cues = &harness.transcript().cues;
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].node_id, "glare");
+        assert_eq!(cues[0].target, Some(npc));
+        assert_eq!(cues[0].cues.anim.as_deref(), Some("idle_lean"));
+        assert_eq!(cues[0].cues.look.as_deref(), Some("player"));
+    }
+
+    #[test]
+    fn interrupted_dialogue_picks_up_where_it_left_off() {
+        let mut harness = RatHarness::new();
+        harness.register(
+            RatScriptBuilder::new("story")
+                .entry("start")
+                .node(
+                    RatNodeBuilder::new("start")
+                        .text("once upon a time.")
+                        .next("middle"),
+                )
+                .node(
+                    RatNodeBuilder::new("middle")
+                        .text("there was a rat.")
+                        .next("end"),
+                )
+                .node(RatNodeBuilder::new("end").text("the end."))
+                .build(),
+        );
+        let npc = harness.app_mut().world_mut().spawn_empty().id();
+        harness.start(RatStart::new("story").target(npc)).advance();
+        harness.interrupt();
+        assert!(!harness.is_open());
+        let interrupted = harness
+            .transcript()
+            .hooks
+            .last()
+            .expect("interrupting should fire a hook");
+        assert_eq!(interrupted.hook, INTERRUPTED_HOOK);
+        assert_eq!(interrupted.node_id, "middle");
+        assert_eq!(interrupted.target, Some(npc));
+
+        // back on the same node behind the resume line, without entering it again
+        harness.start(RatStart::new("story").target(npc));
+        assert_eq!(harness.current(), Some(("story".into(), "middle".into())));
+        harness.advance();
+        assert_eq!(harness.current(), Some(("story".into(), "middle".into())));
+        harness.advance();
+        assert_eq!(harness.current(), Some(("story".into(), "end".into())));
+        let middles = harness
+            .transcript()
+            .nodes
+            .iter()
+            .filter(|node| node.node_id == "middle")
+            .count();
+        assert_eq!(middles, 1);
+
+        // it only picks up once
+        harness.close();
+        harness.start(RatStart::new("story").target(npc));
+        assert_eq!(harness.current(), Some(("story".into(), "start".into())));
+    }
+
+    #[test]
+    fn prewarm_reaches_one_step_ahead() {
+        let mut harness = RatHarness::new();
+        harness.register(
+            RatScriptBuilder::new("steps")
+                .entry("start")
+                .node(
+                    RatNodeBuilder::new("start")
+                        .text("first.")
+                        .option(RatOptionBuilder::new("left").goto("left"))
+                        .option(RatOptionBuilder::new("right").goto("right")),
+                )
+                .node(RatNodeBuilder::new("left").text("left side.").next("far"))
+                .node(RatNodeBuilder::new("right").text("right side."))
+                .node(RatNodeBuilder::new("far").text("too far."))
+                .build(),
+        );
+        // `start` is headless, only a dialogue on screen speaks
+        harness.send(RatCommand::Start(RatStart::new("steps")));
+
+        let messages = harness
+            .app_mut()
+            .world()
+            .resource::<bevy::ecs::message::Messages<PrewarmVoice>>();
+        let mut warmed: Vec<&str> = messages
+            .get_cursor()
+            .read(messages)
+            .map(|PrewarmVoice(speak)| speak.text.as_str())
+            .collect();
+        warmed.sort_unstable();
+        assert_eq!(warmed, ["left side.", "right side."]);
+    }
+
+    #[test]
+    fn each_line_on_screen_waits_for_its_own_speak() {
+        let mut harness = RatHarness::new();
+        harness.register(
+            RatScriptBuilder::new("pair")
+                .entry("first")
+                .node(RatNodeBuilder::new("first").text("one.").next("second"))
+                .node(RatNodeBuilder::new("second").text("two."))
+                .build(),
+        );
+        harness
+            .send(RatCommand::Start(RatStart::new("pair")))
+            .advance();
+
+        let world = harness.app_mut().world();
+        let speaks = world.resource::<bevy::ecs::message::Messages<Speak>>();
+        let spoken: Vec<(&str, Option<SpeakId>)> = speaks
+            .get_cursor()
+            .read(speaks)
+            .map(|speak| (speak.text.as_str(), speak.id))
+            .collect();
+        let shown = world.resource::<bevy::ecs::message::Messages<UiDialogueCommand>>();
+        let shown: Vec<(&str, Option<SpeakId>)> = shown
+            .get_cursor()
+            .read(shown)
+            .filter_map(|command| match command {
+                UiDialogueCommand::Start(request) => Some((request.text.as_str(), request.voice)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(spoken, shown);
+        assert_eq!(spoken.len(), 2);
+        assert_ne!(spoken[0].1, spoken[1].1, "same speaker, different lines");
+    }
+
+    fn memo(nodes: &[(&str, &str)], entry: &str) -> RatScriptAsset {
+        let mut script = RatScriptBuilder::new("memo").entry(entry);
+        for (id, text) in nodes {
+            script = script.node(RatNodeBuilder::new(*id).text(*text));
+        }
+        RatScriptAsset::new(vec![script.build()])
+    }
+
+    fn edit(harness: &mut RatHarness, id: AssetId<RatScriptAsset>, asset: RatScriptAsset) {
+        let app = harness.app_mut();
+        app.world_mut()
+            .resource_mut::<Assets<RatScriptAsset>>()
+            .get_mut(id)
+            .expect("memo should still be loaded")
+            .scripts = asset.scripts;
+        // the modified event goes out in PostUpdate, the reload reads it next frame
+        app.update();
+        app.update();
+    }
+
+    #[test]
+    fn reload_keeps_the_open_node_or_restarts_at_the_entry() {
+        let mut harness = RatHarness::new();
+        let world = harness.app_mut().world_mut();
+        let id = world
+            .resource_mut::<Assets<RatScriptAsset>>()
+            .add(memo(&[("start", "first draft.")], "start"))
+            .id();
+        world.resource_scope(|world, mut library: Mut<RatLibrary>| {
+            let assets = world.resource::<Assets<RatScriptAsset>>();
+            library.replace_source(id, assets.get(id).expect("memo was just added"));
+        });
+
+        harness.start(RatStart::new("memo"));
+        assert_eq!(harness.line().as_deref(), Some("first draft."));
+
+        edit(
+            &mut harness,
+            id,
+            memo(&[("start", "second draft.")], "start"),
+        );
+        assert_eq!(harness.current(), Some(("memo".into(), "start".into())));
+        assert_eq!(harness.line().as_deref(), Some("second draft."));
+        let entered = harness
+            .transcript()
+            .nodes
+            .iter()
+            .filter(|node| node.node_id == "start")
+            .count();
+        assert_eq!(entered, 1, "a redraw doesn't enter the node again");
+
+        edit(&mut harness, id, memo(&[("intro", "rewritten.")], "intro"));
+        assert_eq!(harness.current(), Some(("memo".into(), "intro".into())));
+    }
+
+    /// an option that's on screen but can't be picked, like "Show item..."
+    /// with nothing to show
+    fn doors(_ctx: &RatOptionContext) -> Vec<RatProvidedOption> {
+        vec![RatProvidedOption::new(
+            "the hall.",
+            RatOptionAction::Goto(Some("hall".into())),
+        )]
+    }
+
+    #[test]
+    fn provided_options_are_advanced_into_and_prewarmed() {
+        let mut harness = RatHarness::new();
+        harness.app_mut().add_rat_options("doors", doors);
+        harness.register(
+            RatScriptBuilder::new("house")
+                .entry("porch")
+                .node(
+                    RatNodeBuilder::new("porch")
+                        .text("which way?")
+                        .options_from("doors"),
+                )
+                .node(RatNodeBuilder::new("hall").text("a long hall."))
+                .build(),
+        );
+        harness.send(RatCommand::Start(RatStart::new("house")));
+        let messages = harness
+            .app_mut()
+            .world()
+            .resource::<bevy::ecs::message::Messages<PrewarmVoice>>();
+        let warmed: Vec<&str> = messages
+            .get_cursor()
+            .read(messages)
+            .map(|PrewarmVoice(speak)| speak.text.as_str())
+            .collect();
+        assert_eq!(warmed, ["a long hall."]);
+
+        // advancing picks the first option, it doesn't end the dialogue
+        harness.advance();
+        assert_eq!(harness.current(), Some(("house".into(), "hall".into())));
+    }
+
+    fn stay_quiet(_ctx: &RatOptionContext) -> Vec<RatProvidedOption> {
+        vec![RatProvidedOption {
+            id: Some("silence".into()),
+            enabled: false,
+            ..RatProvidedOption::new("...", RatOptionAction::Goto(None))
+        }]
+    }
+
+    #[test]
+    fn timeout_on_a_disabled_option_follows_it_as_a_target() {
+        let mut harness = RatHarness::new();
+        harness.app_mut().add_rat_options("quiet", stay_quiet);
+        harness.register(
+            RatScriptBuilder::new("interrogation")
+                .entry("alibi")
+                .node(
+                    RatNodeBuilder::new("alibi")
+                        .text("where were you at nine?")
+                        .timeout(RatTimeout::new(2.0, "silence").expect("timeout is valid"))
+                        .options_from("quiet"),
+                )
+                .node(RatNodeBuilder::new("silence").text("nothing to say?"))
+                .build(),
+        );
+        harness.start(RatStart::new("interrogation"));
+        harness.wait(2.5);
+        assert_eq!(
+            harness.current(),
+            Some(("interrogation".into(), "silence".into()))
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::vars::RatVariables;
+use crate::settings::SaveSlot;
+
+/// feverish has no save games or profiles, every run starts over from the
+/// beginning, so there is one history per install like `settings.ron`
+const SEEN_SLOT: SaveSlot = SaveSlot {
+    path: "saves/dialogue.ron",
+    storage_key: "feverish.dialogue",
+};
+
+/// lines the player has read and options they picked, across every run. the
+/// ui dims picked options and `skip_read_lines` fast-forwards read lines
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RatSeen {
+    /// (script_id, node_id, text line)
+    lines: BTreeSet<(String, String, usize)>,
+    /// (script_id, node_id, option id or text)
+    options: BTreeSet<(String, String, String)>,
+}
+
+impl RatSeen {
+    pub fn read(&self, script_id: &str, node_id: &str, line: usize) -> bool {
+        self.lines
+            .contains(&(script_id.to_string(), node_id.to_string(), line))
+    }
+
+    pub fn chose(&self, script_id: &str, node_id: &str, option: &str) -> bool {
+        self.options.contains(&(
+            script_id.to_string(),
+            node_id.to_string(),
+            option.to_string(),
+        ))
+    }
+
+    pub fn record_read(&mut self, script_id: &str, node_id: &str, line: usize) {
+        self.lines
+            .insert((script_id.to_string(), node_id.to_string(), line));
+    }
+
+    pub fn record_choice(&mut self, script_id: &str, node_id: &str, option: &str) {
+        self.options.insert((
+            script_id.to_string(),
+            node_id.to_string(),
+            option.to_string(),
+        ));
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len() + self.options.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// keeps `RatSeen` in `saves/dialogue.ron` (localStorage on the web). left
+/// out of `RatSpinnerPlugin` so the headless harness never touches the save
+pub struct RatSeenSavePlugin;
+
+impl Plugin for RatSeenSavePlugin {
+    fn build(&self, app: &mut App) {
+        let seen = load_persisted_seen().unwrap_or_else(|error| {
+            warn!("{error}");
+            RatSeen::default()
+        });
+        app.world_mut()
+            .get_resource_or_init::<RatVariables>()
+            .set_seen(seen);
+        app.add_systems(Update, save_seen_on_change);
+    }
+}
+
+fn load_persisted_seen() -> Result<RatSeen, String> {
+    let Some(content) = SEEN_SLOT.read()? else {
+        return Ok(RatSeen::default());
+    };
+
+    ron::from_str::<RatSeen>(&content).map_err(|error| {
+        format!(
+            "failed to parse '{}' as dialogue history RON: {}",
+            SEEN_SLOT.location(),
+            error
+        )
+    })
+}
+
+/// the history only grows, so a different size means something new was seen
+fn save_seen_on_change(variables: Res<RatVariables>, mut saved_len: Local<Option<usize>>) {
+    if !variables.is_changed() {
+        return;
+    }
+    let seen = variables.seen();
+    // the first look is at what was just loaded
+    let unchanged = saved_len.is_none_or(|len| len == seen.len());
+    *saved_len = Some(seen.len());
+    if unchanged {
+        return;
+    }
+
+    let Ok(content) = ron::ser::to_string_pretty(seen, ron::ser::PrettyConfig::new()) else {
+        warn!("failed to serialize dialogue history to RON");
+        return;
+    };
+    if let Err(error) = SEEN_SLOT.write(&content) {
+        warn!("{error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ratspinner::{
+        harness::RatHarness,
+        types::{RatCommand, RatNodeBuilder, RatOptionBuilder, RatScriptBuilder, RatStart},
+        vars::RatValue,
+    };
+
+    #[test]
+    fn seen_lines_and_choices_outlive_the_run() {
+        let mut harness = RatHarness::new();
+        harness.register(
+            RatScriptBuilder::new("demo")
+                .entry("start")
+                .node(
+                    RatNodeBuilder::new("start")
+                        .text("choose.")
+                        .option(RatOptionBuilder::new("left").id("left").goto("left"))
+                        .option(RatOptionBuilder::new("right").goto("right")),
+                )
+                .node(RatNodeBuilder::new("left").text("left chosen."))
+                .node(RatNodeBuilder::new("right").text("right chosen."))
+                .build(),
+        );
+        harness.play(
+            RatStart::new("demo"),
+            [RatCommand::Choose(0), RatCommand::Advance],
+        );
+        harness
+            .variables_mut()
+            .set("met_mr_d", RatValue::Bool(true));
+
+        // an ending starts a new run with `RatVariables::clear`, like
+        // `reset_game_on_ending` does. visits and variables go, not what was read
+        // or picked
+        harness.variables_mut().clear();
+        let variables = harness.variables();
+        assert_eq!(variables.visit("demo", "start").count, 0);
+        assert_eq!(variables.get("met_mr_d"), None);
+        let seen = variables.seen();
+        assert!(seen.read("demo", "start", 0));
+        assert!(seen.read("demo", "left", 0));
+        assert!(!seen.read("demo", "right", 0));
+        assert!(seen.chose("demo", "start", "left"));
+        assert!(!seen.chose("demo", "start", "right"));
+
+        // the next run adds to it, and so on through every ending
+        harness.play(
+            RatStart::new("demo"),
+            [RatCommand::Choose(1), RatCommand::Advance],
+        );
+        harness.variables_mut().clear();
+        let seen = harness.variables().seen();
+        assert!(seen.read("demo", "left", 0));
+        assert!(seen.read("demo", "right", 0));
+        assert!(seen.chose("demo", "start", "left"));
+        assert!(seen.chose("demo", "start", "right"));
+    }
+}
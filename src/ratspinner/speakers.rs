@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, reflect::TypePath};
+use serde::{Deserialize, Serialize};
+
+use super::types::RatVoiceRon;
+use crate::{
+    ui::DiscoveryEntry,
+    voice::{VoiceParams, VoicePreset},
+};
+
+/// where the .rat loader and the linter look for speaker definitions
+pub const SPEAKERS_PATH: &str = "ratspinner/speakers.ron";
+
+/// every character that talks, keyed by the id nodes use in `speaker:`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RatSpeakers(pub HashMap<String, RatSpeaker>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatSpeaker {
+    /// shown in the dialogue box
+    pub name: String,
+    /// default portrait
+    pub portrait: String,
+    /// extra portraits a node can pick with `portrait: <key>`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub portraits: HashMap<String, String>,
+    #[serde(default)]
+    pub voice: RatVoiceRon,
+    #[serde(default)]
+    pub tuning: RatVoiceTuning,
+    /// script an npc with this speaker starts when it has no `script_id`
+    #[serde(default)]
+    pub script: Option<String>,
+    /// added to the contacts gallery the first time they talk
+    #[serde(default)]
+    pub contact: Option<RatSpeakerContact>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RatSpeakerContact {
+    pub subtitle: String,
+    pub description: String,
+}
+
+/// per-speaker tweaks on top of the voice preset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RatVoiceTuning {
+    pub pitch_hz: Option<f32>,
+    pub speed: Option<f32>,
+}
+
+impl RatVoiceTuning {
+    pub fn apply(self, preset: VoicePreset) -> VoiceParams {
+        let mut params = preset.params();
+        if let Some(pitch_hz) = self.pitch_hz {
+            params.pitch_hz = pitch_hz;
+        }
+        if let Some(speed) = self.speed {
+            params.speed = speed;
+        }
+        params
+    }
+}
+
+impl RatSpeakers {
+    pub fn get(&self, id: &str) -> Option<&RatSpeaker> {
+        self.0.get(id)
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.0.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        ids
+    }
+}
+
+impl RatSpeaker {
+    /// named portrait from the set, otherwise `key` is taken as a path
+    pub fn portrait(&self, key: &str) -> String {
+        self.portraits
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// what a node wears when it names no portrait, the `emote:` one if the
+    /// set has it
+    pub fn default_portrait(&self, emote: Option<&str>) -> &str {
+        emote
+            .and_then(|emote| self.portraits.get(emote))
+            .unwrap_or(&self.portrait)
+    }
+
+    pub fn voice_params(&self) -> VoiceParams {
+        self.tuning.apply(self.voice.into())
+    }
+
+    pub fn discovery_entry(&self, id: &str) -> Option<DiscoveryEntry> {
+        let contact = self.contact.as_ref()?;
+        Some(
+            DiscoveryEntry::new(id, self.name.clone())
+                .subtitle(contact.subtitle.clone())
+                .description(contact.description.clone())
+                .image_path(self.portrait.clone()),
+        )
+    }
+}
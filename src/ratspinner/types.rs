@@ -1,17 +1,33 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::voice::VoicePreset;
+use super::{
+    speakers::{RatSpeaker, RatSpeakers, RatVoiceTuning},
+    vars::{RatCondition, RatEffect, parse_conditions},
+};
+use crate::voice::{VoiceParams, VoicePreset};
 
 #[derive(Message, Debug, Clone)]
 pub enum RatCommand {
     Start(RatStart),
     Advance,
     Choose(usize),
+    /// the node's `timeout:` ran out, the runtime's clock sends this itself
+    TimeOut,
     Close,
+    /// the player or the target walked off: closes the dialogue, fires
+    /// `dialogue.interrupted` and keeps the node for the next start on the
+    /// same target, which picks up with "as I was saying..."
+    Interrupt,
     Register(RatScript),
+    /// says one line of the start node over its target with a floating
+    /// subtitle, the dialogue ui stays shut
+    Bark(RatStart),
+    /// plays the script out loud between the npcs in `cast`, line by line,
+    /// for the player to overhear
+    Overhear(RatStart),
 }
 
 #[derive(Debug, Clone)]
@@ -20,6 +36,9 @@ pub struct RatStart {
     pub entry: Option<String>,
     pub target: Option<Entity>,
     pub presentation: RatDialoguePresentation,
+    /// speakers.ron id -> the entity saying that speaker's lines, `target`
+    /// says the rest
+    pub cast: HashMap<String, Entity>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -35,6 +54,7 @@ impl RatStart {
             entry: None,
             target: None,
             presentation: RatDialoguePresentation::Ui,
+            cast: HashMap::new(),
         }
     }
 
@@ -52,44 +72,142 @@ impl RatStart {
         self.presentation = RatDialoguePresentation::Headless;
         self
     }
+
+    pub fn cast(mut self, speaker_id: impl Into<String>, entity: Entity) -> Self {
+        self.cast.insert(speaker_id.into(), entity);
+        self
+    }
 }
 
 #[derive(Message, Debug, Clone)]
-#[allow(dead_code)]
 pub struct RatHookTriggered {
+    /// hook name without arguments, `door.open` for `door.open(front_door)`
     pub hook: String,
+    pub args: Vec<String>,
     pub script_id: String,
     pub node_id: String,
     pub option_id: Option<String>,
     pub target: Option<Entity>,
 }
 
-#[allow(dead_code)]
-pub trait RatCommandsExt {
-    fn rat(&mut self, cmd: RatCommand);
-    fn rat_start(&mut self, start: RatStart);
-    fn rat_register(&mut self, script: RatScript);
-}
+impl RatHookTriggered {
+    /// parses `name` or `name(arg, ...)`. malformed args are kept as part of
+    /// the name so nothing matches them, the parser reports those at load
+    pub fn new(raw: impl AsRef<str>) -> Self {
+        let raw = raw.as_ref();
+        let (hook, args) =
+            parse_hook_call(raw).unwrap_or_else(|_| (raw.trim().to_string(), Vec::new()));
+        Self {
+            hook,
+            args,
+            script_id: String::new(),
+            node_id: String::new(),
+            option_id: None,
+            target: None,
+        }
+    }
+
+    pub fn script(mut self, script_id: impl Into<String>) -> Self {
+        self.script_id = script_id.into();
+        self
+    }
 
-impl RatCommandsExt for Commands<'_, '_> {
-    fn rat(&mut self, cmd: RatCommand) {
-        self.write_message(cmd);
+    pub fn node(mut self, node_id: impl Into<String>) -> Self {
+        self.node_id = node_id.into();
+        self
+    }
+
+    pub fn option(mut self, option_id: Option<String>) -> Self {
+        self.option_id = option_id;
+        self
     }
 
-    fn rat_start(&mut self, start: RatStart) {
-        self.write_message(RatCommand::Start(start));
+    pub fn target(mut self, target: Option<Entity>) -> Self {
+        self.target = target;
+        self
     }
 
-    fn rat_register(&mut self, script: RatScript) {
-        self.write_message(RatCommand::Register(script));
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(String::as_str)
     }
 }
 
-#[derive(Debug, Clone)]
+/// written when the dialogue enters a node or a bark plays one, after its
+/// effects ran. redraws and overlays returning to the node don't count
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct RatNodeEntered {
+    pub script_id: String,
+    pub node_id: String,
+    /// text line picked for this visit
+    pub line: usize,
+}
+
+/// a node's `anim:`, `look:` and `sfx:` for whoever says it, written next to
+/// `RatNodeEntered` when the node has any
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct RatCueTriggered {
+    pub script_id: String,
+    pub node_id: String,
+    pub target: Option<Entity>,
+    pub cues: RatCues,
+}
+
+/// splits `door.open(bathroom_door)` into its name and arguments
+pub fn parse_hook_call(raw: &str) -> Result<(String, Vec<String>), String> {
+    let raw = raw.trim();
+    let Some((name, rest)) = raw.split_once('(') else {
+        if raw.contains(')') {
+            return Err(format!("unmatched ')' in hook '{raw}'"));
+        }
+        return Ok((raw.to_string(), Vec::new()));
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("hook '{raw}' is missing a name"));
+    }
+    let Some(args) = rest.strip_suffix(')') else {
+        return Err(format!("hook '{raw}' is missing a closing ')'"));
+    };
+    if args.contains(['(', ')']) {
+        return Err(format!("hook '{raw}' has nested parentheses"));
+    }
+    let args = args
+        .split(',')
+        .map(str::trim)
+        .filter(|arg| !arg.is_empty())
+        .map(str::to_string)
+        .collect();
+    Ok((name.to_string(), args))
+}
+
+/// splits `a, b(x, y)` on the commas outside of parentheses
+pub fn split_hook_list(raw: &str) -> Vec<&str> {
+    let mut hooks = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (index, ch) in raw.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                hooks.push(raw[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    hooks.push(raw[start..].trim());
+    hooks.retain(|hook| !hook.is_empty());
+    hooks
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct RatScript {
     pub id: String,
     pub entry: String,
     pub nodes: HashMap<String, RatNode>,
+    /// node ids in the order they were written, for writing the script back out
+    pub order: Vec<String>,
 }
 
 #[derive(Asset, TypePath, Debug, Clone)]
@@ -103,44 +221,282 @@ impl RatScriptAsset {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RatNode {
     pub id: String,
+    /// speakers.ron id the name, portrait and voice came from
+    pub speaker_id: Option<String>,
     pub speaker: String,
     pub text: String,
+    /// extra lines after `text`, picked per visit by `variant_mode`
+    pub variants: Vec<String>,
+    pub variant_mode: RatVariantMode,
     pub portrait_path: String,
     pub voice: VoicePreset,
+    pub voice_tuning: RatVoiceTuning,
     pub next: Option<String>,
+    /// where a failed `when` guard goes instead of `next`
+    pub else_next: Option<String>,
     pub hooks: Vec<String>,
+    /// node is skipped (falling through to `next`) unless all of these hold
+    pub when: Vec<RatCondition>,
+    pub effects: Vec<RatEffect>,
     pub options: Vec<RatOption>,
+    /// `options_from:` providers adding options after the written ones
+    pub options_from: Vec<String>,
+    pub timeout: Option<RatTimeout>,
+    pub cues: RatCues,
+    /// `//` lines in front of the node, only kept so rat-fmt doesn't drop them
+    pub comments: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+impl RatNode {
+    pub fn voice_params(&self) -> VoiceParams {
+        self.voice_tuning.apply(self.voice)
+    }
+
+    pub fn line_count(&self) -> usize {
+        1 + self.variants.len()
+    }
+
+    /// line `index` with 0 being `text`, past the end gives the last line
+    pub fn line(&self, index: usize) -> &str {
+        match index.checked_sub(1) {
+            Some(variant) => self
+                .variants
+                .get(variant)
+                .or(self.variants.last())
+                .unwrap_or(&self.text),
+            None => &self.text,
+        }
+    }
+
+    /// `once` nodes are skipped after every line was shown
+    pub fn used_up(&self, visits: u32) -> bool {
+        self.variant_mode == RatVariantMode::Once && visits as usize >= self.line_count()
+    }
+
+    /// where `timeout:` leads when it doesn't name one of the node's options
+    pub fn timeout_target(&self) -> Option<&String> {
+        let timeout = self.timeout.as_ref()?;
+        let picks_option = self
+            .options
+            .iter()
+            .any(|option| option.id.as_ref() == Some(&timeout.next));
+        (!picks_option).then_some(&timeout.next)
+    }
+
+    /// nothing to show: entering runs effects and hooks, then moves on to `next`
+    pub fn is_silent(&self) -> bool {
+        self.text.is_empty()
+            && self.variants.is_empty()
+            && self.options.is_empty()
+            && self.options_from.is_empty()
+    }
+}
+
+/// `timeout: 6 -> silence`: once the node was up for `secs`, the option with
+/// id `next` is picked for the player, or `next` is followed as a target if
+/// no option has that id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RatTimeout {
+    pub secs: f32,
+    pub next: String,
+}
+
+impl RatTimeout {
+    pub fn new(secs: f32, next: impl Into<String>) -> Result<Self, String> {
+        if !secs.is_finite() || secs <= 0.0 {
+            return Err(format!("timeout '{secs}' must be above zero"));
+        }
+        let next = next.into();
+        RatTarget::parse(&next)?;
+        Ok(Self { secs, next })
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let Some((secs, next)) = raw.split_once("->") else {
+            return Err("timeout is missing its `-> target`".to_string());
+        };
+        let secs = secs
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| format!("timeout '{}' is not a number", secs.trim()))?;
+        Self::new(secs, next.trim())
+    }
+}
+
+impl fmt::Display for RatTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.secs, self.next)
+    }
+}
+
+/// what the speaker does when the node comes up. `emote` picks the portrait
+/// from the speaker's `portraits` set when the node has no `portrait:`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RatCues {
+    /// animation clip played once before going back to idle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anim: Option<String>,
+    /// `player`, a targetname, or `none` to stop looking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub look: Option<String>,
+    /// `audio/<sfx>.ogg` played from the speaker
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sfx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emote: Option<String>,
+}
+
+impl RatCues {
+    pub fn is_empty(&self) -> bool {
+        self.anim.is_none() && self.look.is_none() && self.sfx.is_none() && self.emote.is_none()
+    }
+
+    /// anything for the world to do, `emote` alone only changes the portrait
+    pub fn acts(&self) -> bool {
+        self.anim.is_some() || self.look.is_some() || self.sfx.is_some()
+    }
+}
+
+/// how a node with several `text:` lines picks one, like ink's
+/// sequence/cycle/shuffle/once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatVariantMode {
+    /// one line per visit in order, then the last line forever
+    #[default]
+    Sequence,
+    /// one line per visit in order, starting over after the last
+    Cycle,
+    /// any line except the one shown last time
+    Random,
+    /// one line per visit in order, then the node falls through to `->`
+    Once,
+}
+
+impl RatVariantMode {
+    pub const NAMES: [&'static str; 4] = ["sequence", "cycle", "random", "once"];
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw.trim() {
+            "sequence" => Ok(Self::Sequence),
+            "cycle" => Ok(Self::Cycle),
+            "random" | "shuffle" => Ok(Self::Random),
+            "once" => Ok(Self::Once),
+            other => Err(format!("unknown variant mode '{other}'")),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sequence => "sequence",
+            Self::Cycle => "cycle",
+            Self::Random => "random",
+            Self::Once => "once",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct RatOption {
     pub id: Option<String>,
     pub text: String,
     pub next: Option<String>,
     pub hooks: Vec<String>,
+    /// option is hidden unless all of these hold
+    pub conditions: Vec<RatCondition>,
+    pub effects: Vec<RatEffect>,
 }
 
-impl RatScript {
-    #[allow(dead_code)]
-    pub fn single(
-        id: impl Into<String>,
-        speaker: impl Into<String>,
-        text: impl Into<String>,
-    ) -> Self {
-        let id = id.into();
-        let entry = "start".to_string();
-        let node = RatNodeBuilder::new("start")
-            .speaker(speaker)
-            .text(text)
-            .build();
-        Self {
-            id,
-            entry,
-            nodes: HashMap::from([(node.id.clone(), node)]),
+/// parsed form of a `next`/`->` string: `node`, `@script:node`, `@script`
+/// (its entry), `call <target>` (come back to the calling node on `return`)
+/// or `return`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RatTarget {
+    Goto(RatNodeRef),
+    Call(RatNodeRef),
+    Return,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RatNodeRef {
+    /// `None` means the current script
+    pub script: Option<String>,
+    /// `None` means the script's entry
+    pub node: Option<String>,
+}
+
+impl RatTarget {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        if raw == "return" {
+            return Ok(Self::Return);
+        }
+        if let Some(rest) = raw.strip_prefix("call ") {
+            return Ok(Self::Call(RatNodeRef::parse(rest)?));
+        }
+        Ok(Self::Goto(RatNodeRef::parse(raw)?))
+    }
+
+    /// node id inside the same script, if this target stays local
+    pub fn local_node(&self) -> Option<&str> {
+        match self {
+            Self::Goto(node_ref) | Self::Call(node_ref) if node_ref.script.is_none() => {
+                node_ref.node.as_deref()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl RatNodeRef {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        let Some(external) = raw.strip_prefix('@') else {
+            if raw.is_empty() {
+                return Err("missing target node".to_string());
+            }
+            return Ok(Self {
+                script: None,
+                node: Some(raw.to_string()),
+            });
+        };
+        let (script, node) = match external.split_once(':') {
+            Some((script, node)) => (script.trim(), Some(node.trim())),
+            None => (external, None),
+        };
+        if script.is_empty() {
+            return Err(format!("'{raw}' is missing a script id"));
+        }
+        if node.is_some_and(str::is_empty) {
+            return Err(format!("'{raw}' is missing a node id after ':'"));
         }
+        Ok(Self {
+            script: Some(script.to_string()),
+            node: node.map(str::to_string),
+        })
+    }
+}
+
+impl RatScript {
+    /// nodes in `order`, then any added without it sorted by id
+    pub fn ordered_nodes(&self) -> Vec<&RatNode> {
+        let mut nodes: Vec<&RatNode> = self
+            .order
+            .iter()
+            .filter_map(|node_id| self.nodes.get(node_id))
+            .collect();
+        let mut rest: Vec<&RatNode> = self
+            .nodes
+            .values()
+            .filter(|node| !self.order.contains(&node.id))
+            .collect();
+        rest.sort_by(|a, b| a.id.cmp(&b.id));
+        nodes.extend(rest);
+        nodes
     }
 }
 
@@ -172,14 +528,17 @@ impl RatScriptBuilder {
 
     pub fn build(self) -> RatScript {
         let mut nodes = HashMap::new();
+        let mut order = Vec::new();
         for node in self.nodes {
             let built = node.build();
+            order.push(built.id.clone());
             nodes.insert(built.id.clone(), built);
         }
         RatScript {
             id: self.id,
             entry: self.entry,
             nodes,
+            order,
         }
     }
 }
@@ -187,26 +546,46 @@ impl RatScriptBuilder {
 #[derive(Debug, Clone)]
 pub struct RatNodeBuilder {
     id: String,
+    speaker_id: Option<String>,
     speaker: String,
     text: String,
+    variants: Vec<String>,
+    variant_mode: RatVariantMode,
     portrait_path: String,
     voice: VoicePreset,
+    voice_tuning: RatVoiceTuning,
     next: Option<String>,
+    else_next: Option<String>,
     hooks: Vec<String>,
+    when: Vec<RatCondition>,
+    effects: Vec<RatEffect>,
     options: Vec<RatOptionBuilder>,
+    options_from: Vec<String>,
+    timeout: Option<RatTimeout>,
+    cues: RatCues,
 }
 
 impl RatNodeBuilder {
     pub fn new(id: impl Into<String>) -> Self {
         Self {
             id: id.into(),
+            speaker_id: None,
             speaker: "unknown".to_string(),
             text: String::new(),
+            variants: Vec::new(),
+            variant_mode: RatVariantMode::default(),
             portrait_path: "models/npc_a/npc_a.png".to_string(),
             voice: VoicePreset::NeutralNpc,
+            voice_tuning: RatVoiceTuning::default(),
             next: None,
+            else_next: None,
             hooks: Vec::new(),
+            when: Vec::new(),
+            effects: Vec::new(),
             options: Vec::new(),
+            options_from: Vec::new(),
+            timeout: None,
+            cues: RatCues::default(),
         }
     }
 
@@ -215,17 +594,37 @@ impl RatNodeBuilder {
         self
     }
 
+    /// name, portrait and voice from a speakers.ron entry
+    pub fn speaker_from(mut self, id: impl Into<String>, speaker: &RatSpeaker) -> Self {
+        self.speaker_id = Some(id.into());
+        self.speaker = speaker.name.clone();
+        self.portrait_path = speaker.portrait.clone();
+        self.voice = speaker.voice.into();
+        self.voice_tuning = speaker.tuning;
+        self
+    }
+
     pub fn text(mut self, text: impl Into<String>) -> Self {
         self.text = text.into();
         self
     }
 
+    /// another line for later visits, see `variant_mode`
+    pub fn variant(mut self, text: impl Into<String>) -> Self {
+        self.variants.push(text.into());
+        self
+    }
+
+    pub fn variant_mode(mut self, mode: RatVariantMode) -> Self {
+        self.variant_mode = mode;
+        self
+    }
+
     pub fn portrait(mut self, path: impl Into<String>) -> Self {
         self.portrait_path = path.into();
         self
     }
 
-    #[allow(dead_code)]
     pub fn voice(mut self, preset: VoicePreset) -> Self {
         self.voice = preset;
         self
@@ -236,30 +635,91 @@ impl RatNodeBuilder {
         self
     }
 
+    /// target for when the `when` guard fails, `next` otherwise
+    pub fn else_next(mut self, target: impl Into<String>) -> Self {
+        self.else_next = Some(target.into());
+        self
+    }
+
     pub fn hook(mut self, hook: impl Into<String>) -> Self {
         self.hooks.push(hook.into());
         self
     }
 
+    pub fn when(mut self, condition: RatCondition) -> Self {
+        self.when.push(condition);
+        self
+    }
+
+    pub fn effect(mut self, effect: RatEffect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+
     pub fn option(mut self, option: RatOptionBuilder) -> Self {
         self.options.push(option);
         self
     }
 
+    /// options generated by a provider registered with `add_rat_options`
+    pub fn options_from(mut self, provider: impl Into<String>) -> Self {
+        self.options_from.push(provider.into());
+        self
+    }
+
+    /// picks option `next` (or follows it as a target) after `secs`.
+    /// `RatTimeout::new` turns down countdowns that would never run
+    pub fn timeout(mut self, timeout: RatTimeout) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn anim(mut self, clip: impl Into<String>) -> Self {
+        self.cues.anim = Some(clip.into());
+        self
+    }
+
+    pub fn look(mut self, at: impl Into<String>) -> Self {
+        self.cues.look = Some(at.into());
+        self
+    }
+
+    pub fn sfx(mut self, sound: impl Into<String>) -> Self {
+        self.cues.sfx = Some(sound.into());
+        self
+    }
+
+    /// only names the emote, set the matching `portrait` yourself
+    pub fn emote(mut self, emote: impl Into<String>) -> Self {
+        self.cues.emote = Some(emote.into());
+        self
+    }
+
     pub fn build(self) -> RatNode {
         RatNode {
             id: self.id,
+            speaker_id: self.speaker_id,
             speaker: self.speaker,
             text: self.text,
+            variants: self.variants,
+            variant_mode: self.variant_mode,
             portrait_path: self.portrait_path,
             voice: self.voice,
+            voice_tuning: self.voice_tuning,
             next: self.next,
+            else_next: self.else_next,
             hooks: self.hooks,
+            when: self.when,
+            effects: self.effects,
             options: self
                 .options
                 .into_iter()
                 .map(RatOptionBuilder::build)
                 .collect(),
+            options_from: self.options_from,
+            timeout: self.timeout,
+            cues: self.cues,
+            comments: Vec::new(),
         }
     }
 }
@@ -270,6 +730,8 @@ pub struct RatOptionBuilder {
     text: String,
     next: Option<String>,
     hooks: Vec<String>,
+    conditions: Vec<RatCondition>,
+    effects: Vec<RatEffect>,
 }
 
 impl RatOptionBuilder {
@@ -279,6 +741,8 @@ impl RatOptionBuilder {
             text: text.into(),
             next: None,
             hooks: Vec::new(),
+            conditions: Vec::new(),
+            effects: Vec::new(),
         }
     }
 
@@ -297,12 +761,24 @@ impl RatOptionBuilder {
         self
     }
 
+    pub fn when(mut self, condition: RatCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn effect(mut self, effect: RatEffect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+
     pub fn build(self) -> RatOption {
         RatOption {
             id: self.id,
             text: self.text,
             next: self.next,
             hooks: self.hooks,
+            conditions: self.conditions,
+            effects: self.effects,
         }
     }
 }
@@ -317,16 +793,40 @@ pub struct RatScriptRon {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RatNodeRon {
     pub id: String,
-    pub speaker: String,
+    /// speakers.ron id, fills in whichever of `speaker`, `portrait_path`,
+    /// `voice` and `voice_tuning` are left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
     pub text: String,
-    pub portrait_path: String,
-    #[serde(default)]
-    pub voice: RatVoiceRon,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant_mode: Option<RatVariantMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub portrait_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<RatVoiceRon>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice_tuning: Option<RatVoiceTuning>,
     pub next: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub else_next: Option<String>,
     #[serde(default)]
     pub hooks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub when: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<String>,
     #[serde(default)]
     pub options: Vec<RatOptionRon>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options_from: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<RatTimeout>,
+    #[serde(default, skip_serializing_if = "RatCues::is_empty")]
+    pub cues: RatCues,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -336,6 +836,10 @@ pub struct RatOptionRon {
     pub next: Option<String>,
     #[serde(default)]
     pub hooks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<String>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
@@ -372,22 +876,28 @@ impl From<VoicePreset> for RatVoiceRon {
 
 impl From<&RatScript> for RatScriptRon {
     fn from(value: &RatScript) -> Self {
-        let mut nodes: Vec<&RatNode> = value.nodes.values().collect();
-        nodes.sort_by(|a, b| a.id.cmp(&b.id));
-
         Self {
             id: value.id.clone(),
             entry: value.entry.clone(),
-            nodes: nodes
+            nodes: value
+                .ordered_nodes()
                 .into_iter()
                 .map(|node| RatNodeRon {
                     id: node.id.clone(),
-                    speaker: node.speaker.clone(),
+                    speaker_id: node.speaker_id.clone(),
+                    speaker: Some(node.speaker.clone()),
                     text: node.text.clone(),
-                    portrait_path: node.portrait_path.clone(),
-                    voice: node.voice.into(),
+                    variants: node.variants.clone(),
+                    variant_mode: (node.variant_mode != RatVariantMode::default())
+                        .then_some(node.variant_mode),
+                    portrait_path: Some(node.portrait_path.clone()),
+                    voice: Some(node.voice.into()),
+                    voice_tuning: Some(node.voice_tuning),
                     next: node.next.clone(),
+                    else_next: node.else_next.clone(),
                     hooks: node.hooks.clone(),
+                    when: node.when.iter().map(ToString::to_string).collect(),
+                    effects: node.effects.iter().map(ToString::to_string).collect(),
                     options: node
                         .options
                         .iter()
@@ -396,56 +906,163 @@ impl From<&RatScript> for RatScriptRon {
                             text: option.text.clone(),
                             next: option.next.clone(),
                             hooks: option.hooks.clone(),
+                            conditions: option.conditions.iter().map(ToString::to_string).collect(),
+                            effects: option.effects.iter().map(ToString::to_string).collect(),
                         })
                         .collect(),
+                    options_from: node.options_from.clone(),
+                    timeout: node.timeout.clone(),
+                    cues: node.cues.clone(),
                 })
                 .collect(),
         }
     }
 }
 
-impl TryFrom<RatScriptRon> for RatScript {
-    type Error = String;
-
-    fn try_from(value: RatScriptRon) -> Result<Self, Self::Error> {
+impl RatScriptRon {
+    /// the script it describes, with `speaker_id`s looked up like `speaker:`
+    /// lines in .rat files
+    pub(super) fn into_script(self, speakers: &RatSpeakers) -> Result<RatScript, String> {
         let mut nodes = HashMap::new();
-        for node in value.nodes {
+        let mut order = Vec::new();
+        for node in self.nodes {
             if nodes.contains_key(&node.id) {
                 return Err(format!("duplicate node id '{}'", node.id));
             }
+            let context = |error: String| format!("node '{}': {error}", node.id);
+            let mut builder = RatNodeBuilder::new(node.id.clone());
+            if let Some(speaker_id) = &node.speaker_id {
+                let def = speakers
+                    .get(speaker_id)
+                    .ok_or_else(|| context(format!("unknown speaker '{speaker_id}'")))?;
+                builder = builder
+                    .speaker_from(speaker_id, def)
+                    .portrait(def.default_portrait(node.cues.emote.as_deref()));
+            }
+            let defaults = builder.build();
+            for target in node
+                .next
+                .iter()
+                .chain(&node.else_next)
+                .chain(node.options.iter().filter_map(|opt| opt.next.as_ref()))
+            {
+                RatTarget::parse(target).map_err(context)?;
+            }
+            let mut options = Vec::with_capacity(node.options.len());
+            for opt in node.options {
+                options.push(RatOption {
+                    id: opt.id,
+                    text: opt.text,
+                    next: opt.next,
+                    hooks: opt.hooks,
+                    conditions: parse_condition_list(&opt.conditions).map_err(context)?,
+                    effects: parse_effect_list(&opt.effects).map_err(context)?,
+                });
+            }
             let built = RatNode {
                 id: node.id.clone(),
-                speaker: node.speaker,
+                speaker_id: node.speaker_id,
+                speaker: node.speaker.unwrap_or(defaults.speaker),
                 text: node.text,
-                portrait_path: node.portrait_path,
-                voice: node.voice.into(),
+                variants: node.variants,
+                variant_mode: node.variant_mode.unwrap_or_default(),
+                portrait_path: node.portrait_path.unwrap_or(defaults.portrait_path),
+                voice: node.voice.map_or(defaults.voice, Into::into),
+                voice_tuning: node.voice_tuning.unwrap_or(defaults.voice_tuning),
                 next: node.next,
+                else_next: node.else_next,
                 hooks: node.hooks,
-                options: node
-                    .options
-                    .into_iter()
-                    .map(|opt| RatOption {
-                        id: opt.id,
-                        text: opt.text,
-                        next: opt.next,
-                        hooks: opt.hooks,
-                    })
-                    .collect(),
+                when: parse_condition_list(&node.when).map_err(context)?,
+                effects: parse_effect_list(&node.effects).map_err(context)?,
+                options,
+                options_from: node.options_from,
+                timeout: node
+                    .timeout
+                    .map(|timeout| RatTimeout::new(timeout.secs, timeout.next))
+                    .transpose()
+                    .map_err(context)?,
+                cues: node.cues,
+                comments: Vec::new(),
             };
+            order.push(node.id.clone());
             nodes.insert(node.id, built);
         }
 
-        if !nodes.contains_key(&value.entry) {
+        if !nodes.contains_key(&self.entry) {
             return Err(format!(
                 "entry node '{}' not found in script '{}'",
-                value.entry, value.id
+                self.entry, self.id
             ));
         }
 
         Ok(RatScript {
-            id: value.id,
-            entry: value.entry,
+            id: self.id,
+            entry: self.entry,
             nodes,
+            order,
         })
     }
 }
+
+fn parse_condition_list(raw: &[String]) -> Result<Vec<RatCondition>, String> {
+    let mut conditions = Vec::new();
+    for entry in raw {
+        conditions.extend(parse_conditions(entry)?);
+    }
+    Ok(conditions)
+}
+
+fn parse_effect_list(raw: &[String]) -> Result<Vec<RatEffect>, String> {
+    raw.iter().map(|entry| RatEffect::parse(entry)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_parse_jumps_calls_and_returns() {
+        let external = |script: &str, node: Option<&str>| RatNodeRef {
+            script: Some(script.into()),
+            node: node.map(str::to_string),
+        };
+        assert_eq!(
+            RatTarget::parse("hub"),
+            Ok(RatTarget::Goto(RatNodeRef {
+                script: None,
+                node: Some("hub".into()),
+            }))
+        );
+        assert_eq!(
+            RatTarget::parse(" @shared "),
+            Ok(RatTarget::Goto(external("shared", None)))
+        );
+        assert_eq!(
+            RatTarget::parse("call @shared:identity"),
+            Ok(RatTarget::Call(external("shared", Some("identity"))))
+        );
+        assert_eq!(RatTarget::parse("return"), Ok(RatTarget::Return));
+        assert_eq!(
+            RatTarget::parse("call more").map(|target| target.local_node().map(str::to_string)),
+            Ok(Some("more".to_string()))
+        );
+        for bad in ["", "@", "@:lobby", "@shared:"] {
+            assert!(RatTarget::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn timeouts_need_time_left_to_count_down() {
+        assert_eq!(
+            RatTimeout::parse("6 -> silence"),
+            Ok(RatTimeout {
+                secs: 6.0,
+                next: "silence".into(),
+            })
+        );
+        for secs in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(RatTimeout::new(secs, "silence").is_err(), "{secs}");
+        }
+        assert!(RatTimeout::new(6.0, "@shared:").is_err());
+    }
+}
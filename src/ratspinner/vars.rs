@@ -0,0 +1,448 @@
+use std::{collections::HashMap, fmt};
+
+use bevy::prelude::*;
+
+use super::seen::RatSeen;
+
+/// dialogue variable value, loosely typed like yarn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RatValue {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+}
+
+impl RatValue {
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        match raw {
+            "true" => return Self::Bool(true),
+            "false" => return Self::Bool(false),
+            _ => {}
+        }
+        if let Ok(value) = raw.parse::<i64>() {
+            return Self::Int(value);
+        }
+        let unquoted = raw
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(raw);
+        Self::Str(unquoted.to_string())
+    }
+
+    pub fn truthy(&self) -> bool {
+        match self {
+            Self::Bool(value) => *value,
+            Self::Int(value) => *value != 0,
+            Self::Str(value) => !value.is_empty(),
+        }
+    }
+
+    pub fn as_int(&self) -> i64 {
+        match self {
+            Self::Bool(value) => i64::from(*value),
+            Self::Int(value) => *value,
+            Self::Str(value) => value.parse().unwrap_or(0),
+        }
+    }
+}
+
+impl fmt::Display for RatValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Str(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatCompare {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl RatCompare {
+    // longest tokens first so `>=` never parses as `>`
+    const TOKENS: [(&'static str, Self); 6] = [
+        ("==", Self::Eq),
+        ("!=", Self::Ne),
+        (">=", Self::Ge),
+        ("<=", Self::Le),
+        (">", Self::Gt),
+        ("<", Self::Lt),
+    ];
+
+    fn token(self) -> &'static str {
+        match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+        }
+    }
+}
+
+/// single guard like `met_mr_d`, `!met_mr_d` or `lies_told >= 2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RatCondition {
+    Truthy(String),
+    Falsy(String),
+    Compare {
+        var: String,
+        op: RatCompare,
+        value: RatValue,
+    },
+}
+
+impl RatCondition {
+    pub fn truthy(var: impl Into<String>) -> Self {
+        Self::Truthy(var.into())
+    }
+
+    pub fn falsy(var: impl Into<String>) -> Self {
+        Self::Falsy(var.into())
+    }
+
+    pub fn compare(var: impl Into<String>, op: RatCompare, value: RatValue) -> Self {
+        Self::Compare {
+            var: var.into(),
+            op,
+            value,
+        }
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        for (token, op) in RatCompare::TOKENS {
+            if let Some((var, value)) = raw.split_once(token) {
+                let var = parse_var_name(var)?;
+                return Ok(Self::Compare {
+                    var,
+                    op,
+                    value: RatValue::parse(value),
+                });
+            }
+        }
+        if let Some(var) = raw.strip_prefix('!').or_else(|| raw.strip_prefix("not ")) {
+            return Ok(Self::Falsy(parse_var_name(var)?));
+        }
+        Ok(Self::Truthy(parse_var_name(raw)?))
+    }
+
+    pub fn eval(&self, vars: &RatVariables) -> bool {
+        match self {
+            Self::Truthy(var) => vars.get(var).is_some_and(RatValue::truthy),
+            Self::Falsy(var) => !vars.get(var).is_some_and(RatValue::truthy),
+            Self::Compare { var, op, value } => {
+                let current = vars.get(var);
+                let ordering = || {
+                    current
+                        .map(RatValue::as_int)
+                        .unwrap_or(0)
+                        .cmp(&value.as_int())
+                };
+                match op {
+                    RatCompare::Eq => compare_eq(current, value),
+                    RatCompare::Ne => !compare_eq(current, value),
+                    RatCompare::Gt => ordering().is_gt(),
+                    RatCompare::Ge => ordering().is_ge(),
+                    RatCompare::Lt => ordering().is_lt(),
+                    RatCompare::Le => ordering().is_le(),
+                }
+            }
+        }
+    }
+
+    pub fn var(&self) -> &str {
+        match self {
+            Self::Truthy(var) | Self::Falsy(var) => var,
+            Self::Compare { var, .. } => var,
+        }
+    }
+}
+
+impl fmt::Display for RatCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truthy(var) => write!(f, "{var}"),
+            Self::Falsy(var) => write!(f, "!{var}"),
+            Self::Compare { var, op, value } => write!(f, "{var} {} {value}", op.token()),
+        }
+    }
+}
+
+/// assignment directive applied when a node is shown or an option is picked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RatEffect {
+    Set { var: String, value: RatValue },
+    Inc { var: String, amount: i64 },
+}
+
+impl RatEffect {
+    pub fn set(var: impl Into<String>, value: RatValue) -> Self {
+        Self::Set {
+            var: var.into(),
+            value,
+        }
+    }
+
+    pub fn inc(var: impl Into<String>, amount: i64) -> Self {
+        Self::Inc {
+            var: var.into(),
+            amount,
+        }
+    }
+
+    /// parses the value part of `set: var = value` (bare `set: var` means true)
+    pub fn parse_set(raw: &str) -> Result<Self, String> {
+        match raw.split_once('=') {
+            Some((var, value)) => Ok(Self::set(parse_var_name(var)?, RatValue::parse(value))),
+            None => Ok(Self::set(parse_var_name(raw)?, RatValue::Bool(true))),
+        }
+    }
+
+    /// parses the value part of `inc: var` or `inc: var 2`
+    pub fn parse_inc(raw: &str) -> Result<Self, String> {
+        let mut parts = raw.split_whitespace();
+        let var = parse_var_name(parts.next().unwrap_or_default())?;
+        let amount = match parts.next() {
+            Some(amount) => amount
+                .parse::<i64>()
+                .map_err(|_| format!("'{amount}' is not a valid increment"))?,
+            None => 1,
+        };
+        if let Some(extra) = parts.next() {
+            return Err(format!("unexpected '{extra}' after increment"));
+        }
+        Ok(Self::inc(var, amount))
+    }
+
+    /// parses a whole directive, e.g. `set: met_mr_d = true` or `inc: lies_told`
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let Some((key, value)) = raw.split_once(':') else {
+            return Err(format!("'{}' is not a set/inc directive", raw.trim()));
+        };
+        match key.trim() {
+            "set" => Self::parse_set(value),
+            "inc" => Self::parse_inc(value),
+            other => Err(format!("unknown effect '{other}'")),
+        }
+    }
+
+    pub fn var(&self) -> &str {
+        match self {
+            Self::Set { var, .. } | Self::Inc { var, .. } => var,
+        }
+    }
+}
+
+impl fmt::Display for RatEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Set { var, value } => write!(f, "set: {var} = {value}"),
+            Self::Inc { var, amount: 1 } => write!(f, "inc: {var}"),
+            Self::Inc { var, amount } => write!(f, "inc: {var} {amount}"),
+        }
+    }
+}
+
+/// splits `a && b` into separate guards
+pub fn parse_conditions(raw: &str) -> Result<Vec<RatCondition>, String> {
+    raw.split("&&").map(RatCondition::parse).collect()
+}
+
+fn parse_var_name(raw: &str) -> Result<String, String> {
+    let name = raw.trim();
+    if name.is_empty() {
+        return Err("missing variable name".to_string());
+    }
+    if !name
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '.'))
+    {
+        return Err(format!("'{name}' is not a valid variable name"));
+    }
+    Ok(name.to_string())
+}
+
+fn compare_eq(current: Option<&RatValue>, value: &RatValue) -> bool {
+    match (current, value) {
+        (Some(RatValue::Int(lhs)), RatValue::Int(rhs)) => lhs == rhs,
+        (Some(current), RatValue::Bool(rhs)) => current.truthy() == *rhs,
+        (None, RatValue::Bool(rhs)) => !rhs,
+        (None, RatValue::Int(rhs)) => *rhs == 0,
+        (None, RatValue::Str(rhs)) => rhs.is_empty(),
+        (Some(current), rhs) => current.to_string() == rhs.to_string(),
+    }
+}
+
+/// dialogue variable store shared by every script, plus how often each node
+/// was shown. both reset with the run, what the player has seen doesn't
+#[derive(Resource, Debug, Default)]
+pub struct RatVariables {
+    values: HashMap<String, RatValue>,
+    visits: HashMap<(String, String), RatVisit>,
+    seen: RatSeen,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RatVisit {
+    /// times the node was entered
+    pub count: u32,
+    /// text line shown on the latest visit
+    pub last_line: Option<usize>,
+}
+
+impl RatVariables {
+    pub fn get(&self, var: &str) -> Option<&RatValue> {
+        self.values.get(var)
+    }
+
+    pub fn set(&mut self, var: impl Into<String>, value: RatValue) {
+        self.values.insert(var.into(), value);
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.visits.clear();
+    }
+
+    pub fn seen(&self) -> &RatSeen {
+        &self.seen
+    }
+
+    pub fn set_seen(&mut self, seen: RatSeen) {
+        self.seen = seen;
+    }
+
+    pub fn visit(&self, script_id: &str, node_id: &str) -> RatVisit {
+        self.visits
+            .get(&(script_id.to_string(), node_id.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn record_visit(&mut self, script_id: &str, node_id: &str, line: usize) {
+        let visit = self
+            .visits
+            .entry((script_id.to_string(), node_id.to_string()))
+            .or_default();
+        visit.count += 1;
+        visit.last_line = Some(line);
+        self.seen.record_read(script_id, node_id, line);
+    }
+
+    /// `option` is the option's id, or its text without one
+    pub fn record_choice(&mut self, script_id: &str, node_id: &str, option: &str) {
+        self.seen.record_choice(script_id, node_id, option);
+    }
+
+    pub fn check(&self, conditions: &[RatCondition]) -> bool {
+        conditions.iter().all(|condition| condition.eval(self))
+    }
+
+    pub fn apply(&mut self, effect: &RatEffect) {
+        match effect {
+            RatEffect::Set { var, value } => {
+                self.values.insert(var.clone(), value.clone());
+            }
+            RatEffect::Inc { var, amount } => {
+                let current = self.values.get(var).map(RatValue::as_int).unwrap_or(0);
+                self.values
+                    .insert(var.clone(), RatValue::Int(current + amount));
+            }
+        }
+    }
+
+    pub fn apply_all(&mut self, effects: &[RatEffect]) {
+        for effect in effects {
+            self.apply(effect);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions_parse_and_reject_bad_input() {
+        assert_eq!(
+            RatCondition::parse("not met_mr_d"),
+            Ok(RatCondition::Falsy("met_mr_d".into()))
+        );
+        // `>=` wins over `>`, quotes come off strings
+        assert_eq!(
+            RatCondition::parse("lies_told >= 2"),
+            Ok(RatCondition::Compare {
+                var: "lies_told".into(),
+                op: RatCompare::Ge,
+                value: RatValue::Int(2),
+            })
+        );
+        assert_eq!(
+            RatCondition::parse("mood == \"sour\""),
+            Ok(RatCondition::Compare {
+                var: "mood".into(),
+                op: RatCompare::Eq,
+                value: RatValue::Str("sour".into()),
+            })
+        );
+        assert_eq!(
+            parse_conditions("has_key && !opened"),
+            Ok(vec![
+                RatCondition::truthy("has_key"),
+                RatCondition::Falsy("opened".into()),
+            ])
+        );
+        assert!(RatCondition::parse("").is_err());
+        assert!(RatCondition::parse("two words").is_err());
+        assert!(RatCondition::parse("== 2").is_err());
+    }
+
+    #[test]
+    fn effects_parse_and_reject_bad_input() {
+        assert_eq!(
+            RatEffect::parse("set: met_mr_d"),
+            Ok(RatEffect::set("met_mr_d", RatValue::Bool(true)))
+        );
+        assert_eq!(
+            RatEffect::parse("inc: lies_told -1"),
+            Ok(RatEffect::inc("lies_told", -1))
+        );
+        assert!(RatEffect::parse("inc: lies_told lots").is_err());
+        assert!(RatEffect::parse("inc: lies_told 1 2").is_err());
+        assert!(RatEffect::parse("dec: lies_told").is_err());
+        assert!(RatEffect::parse("lies_told = 2").is_err());
+    }
+
+    #[test]
+    fn unset_variables_read_as_false_zero_and_empty() {
+        let mut vars = RatVariables::default();
+        let holds = |vars: &RatVariables, raw: &str| {
+            vars.check(&parse_conditions(raw).expect("guard should parse"))
+        };
+        assert!(holds(&vars, "!met_mr_d && lies_told == 0 && mood == \"\""));
+        assert!(!holds(&vars, "lies_told > 0"));
+
+        vars.apply_all(&[
+            RatEffect::inc("lies_told", 2),
+            RatEffect::parse("set: mood = \"sour\"").expect("set should parse"),
+            RatEffect::parse("set: met_mr_d").expect("set should parse"),
+        ]);
+        assert!(holds(
+            &vars,
+            "met_mr_d && lies_told >= 2 && mood != \"sweet\""
+        ));
+        vars.apply(&RatEffect::inc("lies_told", -1));
+        assert_eq!(vars.get("lies_told"), Some(&RatValue::Int(1)));
+    }
+}
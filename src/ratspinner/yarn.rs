@@ -0,0 +1,800 @@
+use std::collections::HashMap;
+
+use super::{
+    interpolate::without_placeholders,
+    markup::RatMarkup,
+    runtime::{RatParseContext, RatParseError, column_in, suggestion},
+    speakers::RatSpeakers,
+    types::{RatNode, RatNodeBuilder, RatOption, RatScript, parse_hook_call},
+    vars::{RatCondition, RatEffect},
+};
+
+/// one `title: ..` / `---` / `===` block
+struct YarnNode<'a> {
+    title: &'a str,
+    line: usize,
+    body: Vec<YarnLine<'a>>,
+}
+
+#[derive(Clone, Copy)]
+struct YarnLine<'a> {
+    number: usize,
+    indent: usize,
+    /// trimmed, a slice of `line` so errors can point at columns
+    raw: &'a str,
+    line: &'a str,
+}
+
+enum YarnStatement {
+    Line {
+        speaker: Option<String>,
+        text: String,
+        when: Vec<RatCondition>,
+        hooks: Vec<String>,
+    },
+    Options(Vec<YarnOption>),
+    Set(RatEffect),
+    Jump(String),
+    Stop,
+    If {
+        branches: Vec<(Vec<RatCondition>, Vec<YarnStatement>)>,
+        otherwise: Option<Vec<YarnStatement>>,
+    },
+}
+
+struct YarnOption {
+    text: String,
+    conditions: Vec<RatCondition>,
+    hooks: Vec<String>,
+    body: Vec<YarnStatement>,
+}
+
+/// imports a .yarn file as one script named after the file. every title
+/// becomes a node (`Start` is the entry if there is one), longer bodies are
+/// split into `Title.1`, `Title.2`, .. nodes. `->` options, `<<jump>>`,
+/// `<<stop>>`, `<<set>>` and `<<if>>`/`<<elseif>>`/`<<else>>` map onto
+/// options, targets, effects and guards, `#tags` on lines and options fire as
+/// hooks. `or`, functions and other commands have no ratspinner equivalent and
+/// are rejected
+pub(super) fn parse_yarn_script(
+    content: &str,
+    script_id: &str,
+    ctx: &RatParseContext,
+) -> Result<RatScript, RatParseError> {
+    let yarn_nodes = split_nodes(content, ctx)?;
+    let titles: Vec<&str> = yarn_nodes.iter().map(|node| node.title).collect();
+
+    let mut compiler = YarnCompiler {
+        title: "",
+        count: 0,
+        nodes: Vec::new(),
+        speakers: ctx.speakers,
+    };
+    for yarn_node in &yarn_nodes {
+        let mut body = YarnBody {
+            lines: &yarn_node.body,
+            pos: 0,
+            titles: &titles,
+            ctx,
+        };
+        let statements = body.block(0)?;
+        if let Some(line) = body.lines.get(body.pos) {
+            return Err(ctx
+                .error(
+                    line.number,
+                    column_in(line.line, line.raw),
+                    "unexpected line",
+                )
+                .hint("`<<elseif>>`, `<<else>>` and `<<endif>>` need an open `<<if>>`"));
+        }
+
+        compiler.title = yarn_node.title;
+        compiler.count = 0;
+        let exits = compiler.block(&statements, vec![Exit::Entry]);
+        compiler.close(exits);
+    }
+
+    let entry = titles
+        .iter()
+        .find(|title| **title == "Start")
+        .or(titles.first())
+        .map(|title| title.to_string())
+        .unwrap_or_default();
+    let order = compiler.nodes.iter().map(|node| node.id.clone()).collect();
+    let nodes: HashMap<String, RatNode> = compiler
+        .nodes
+        .into_iter()
+        .map(|node| (node.id.clone(), node))
+        .collect();
+    Ok(RatScript {
+        id: script_id.to_string(),
+        entry,
+        nodes,
+        order,
+    })
+}
+
+fn split_nodes<'a>(
+    content: &'a str,
+    ctx: &RatParseContext,
+) -> Result<Vec<YarnNode<'a>>, RatParseError> {
+    let mut nodes: Vec<YarnNode> = Vec::new();
+    let mut title: Option<(&str, usize)> = None;
+    let mut body: Option<Vec<YarnLine>> = None;
+
+    for (line_index, line) in content.lines().enumerate() {
+        let number = line_index + 1;
+        let raw = line.trim();
+        if raw.is_empty() || raw.starts_with("//") {
+            continue;
+        }
+
+        if let Some(lines) = body.as_mut() {
+            if raw != "===" {
+                lines.push(YarnLine {
+                    number,
+                    indent: line.len() - line.trim_start().len(),
+                    raw,
+                    line,
+                });
+                continue;
+            }
+            let (title, title_line) = title.take().unwrap_or_default();
+            nodes.push(YarnNode {
+                title,
+                line: title_line,
+                body: body.take().unwrap_or_default(),
+            });
+            continue;
+        }
+
+        if raw == "---" {
+            if title.is_none() {
+                return Err(ctx
+                    .error(number, 1, "node body without a title")
+                    .hint("start every node with a `title:` header"));
+            }
+            body = Some(Vec::new());
+            continue;
+        }
+
+        // tags, position, colorID and friends only matter to the editor
+        let Some((key, value)) = raw.split_once(':') else {
+            ctx.reject(
+                ctx.error(number, column_in(line, raw), "unrecognized header")
+                    .hint("headers look like `title: Start`, the body starts after `---`"),
+            )?;
+            continue;
+        };
+        if key.trim() != "title" {
+            continue;
+        }
+        let value = value.trim();
+        if value.is_empty()
+            || !value
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        {
+            return Err(ctx
+                .error(
+                    number,
+                    column_in(line, value),
+                    format!("invalid title '{value}'"),
+                )
+                .hint("titles are letters, digits and `_`"));
+        }
+        if let Some(existing) = nodes.iter().find(|node| node.title == value) {
+            return Err(ctx.error(
+                number,
+                column_in(line, value),
+                format!(
+                    "duplicate title '{value}', first used on line {}",
+                    existing.line
+                ),
+            ));
+        }
+        title = Some((value, number));
+    }
+
+    if let Some((title, line)) = title {
+        let message = match body {
+            Some(_) => format!("node '{title}' is not closed with `===`"),
+            None => format!("node '{title}' has no `---` before its body"),
+        };
+        return Err(ctx.error(line, 1, message));
+    }
+    if nodes.is_empty() {
+        return Err(ctx.error(1, 1, "file does not define any nodes"));
+    }
+    Ok(nodes)
+}
+
+/// reads one node body into statements, options own the lines indented
+/// below them
+struct YarnBody<'a> {
+    lines: &'a [YarnLine<'a>],
+    pos: usize,
+    titles: &'a [&'a str],
+    ctx: &'a RatParseContext<'a>,
+}
+
+impl YarnBody<'_> {
+    /// statements until the indent drops below `indent` or an `<<elseif>>`,
+    /// `<<else>>` or `<<endif>>` ends the enclosing `<<if>>`
+    fn block(&mut self, indent: usize) -> Result<Vec<YarnStatement>, RatParseError> {
+        let mut statements = Vec::new();
+        while let Some(line) = self.lines.get(self.pos).copied() {
+            if line.indent < indent {
+                break;
+            }
+            if line.raw.starts_with("->") {
+                statements.push(YarnStatement::Options(self.options()?));
+                continue;
+            }
+            self.pos += 1;
+            if !line.raw.starts_with("<<") {
+                statements.push(self.line(line)?);
+                continue;
+            }
+
+            let ctx = self.ctx;
+            let error_at = |part: &str, message: String| {
+                ctx.error(line.number, column_in(line.line, part), message)
+            };
+            let Some((name, args)) = split_command(line.raw) else {
+                return Err(error_at(line.raw, "unclosed command".to_string())
+                    .hint("commands look like `<<jump Start>>`"));
+            };
+            match name {
+                "elseif" | "else" | "endif" => {
+                    // handed back to `if_chain`
+                    self.pos -= 1;
+                    break;
+                }
+                "if" => statements.push(self.if_chain(line, args)?),
+                "set" => statements.push(YarnStatement::Set(
+                    translate_set(args).map_err(|error| error_at(args, error))?,
+                )),
+                "jump" => {
+                    if !self.titles.contains(&args) {
+                        ctx.reject(
+                            error_at(args, format!("unknown title '{args}'"))
+                                .hint(suggestion(args, self.titles)),
+                        )?;
+                    }
+                    statements.push(YarnStatement::Jump(args.to_string()));
+                }
+                "stop" => statements.push(YarnStatement::Stop),
+                _ => ctx.reject(
+                    error_at(name, format!("unsupported command '{name}'"))
+                        .hint("use a #tag on the line for hooks"),
+                )?,
+            }
+        }
+        Ok(statements)
+    }
+
+    fn if_chain(
+        &mut self,
+        start: YarnLine,
+        condition: &str,
+    ) -> Result<YarnStatement, RatParseError> {
+        let ctx = self.ctx;
+        let invalid = |line: YarnLine, part: &str, error: String| {
+            ctx.error(line.number, column_in(line.line, part), error)
+        };
+        let condition =
+            translate_condition(condition).map_err(|error| invalid(start, condition, error))?;
+        let mut branches = vec![(condition, self.block(start.indent)?)];
+        let mut otherwise = None;
+        loop {
+            let Some(line) = self.lines.get(self.pos).copied() else {
+                return Err(invalid(
+                    start,
+                    start.raw,
+                    "`<<if>>` without `<<endif>>`".into(),
+                ));
+            };
+            self.pos += 1;
+            match split_command(line.raw) {
+                Some(("elseif", condition)) if otherwise.is_none() => {
+                    let condition = translate_condition(condition)
+                        .map_err(|error| invalid(line, condition, error))?;
+                    branches.push((condition, self.block(start.indent)?));
+                }
+                Some(("else", "")) if otherwise.is_none() => {
+                    otherwise = Some(self.block(start.indent)?);
+                }
+                Some(("endif", "")) => break,
+                _ => {
+                    return Err(invalid(line, line.raw, "expected `<<endif>>`".into())
+                        .hint(format!("the `<<if>>` is on line {}", start.number)));
+                }
+            }
+        }
+        Ok(YarnStatement::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    /// a run of `->` lines at the same indent
+    fn options(&mut self) -> Result<Vec<YarnOption>, RatParseError> {
+        let indent = self.lines[self.pos].indent;
+        let mut options = Vec::new();
+        while let Some(line) = self.lines.get(self.pos).copied()
+            && line.indent == indent
+            && let Some(raw) = line.raw.strip_prefix("->")
+        {
+            self.pos += 1;
+            let (text, conditions, hooks) = self.split_line(line, raw.trim())?;
+            if text.is_empty() {
+                return Err(self.ctx.error(
+                    line.number,
+                    column_in(line.line, line.raw),
+                    "empty option text",
+                ));
+            }
+            options.push(YarnOption {
+                text: yarn_placeholders(text),
+                conditions,
+                hooks,
+                body: self.block(indent + 1)?,
+            });
+        }
+        Ok(options)
+    }
+
+    /// `Name: text <<if cond>> #tag`, the name is a speakers.ron id or shown
+    /// as is
+    fn line(&self, line: YarnLine) -> Result<YarnStatement, RatParseError> {
+        let (body, when, hooks) = self.split_line(line, line.raw)?;
+        let (speaker, text) = match body.split_once(':') {
+            Some((name, text))
+                if !name.trim().is_empty() && !name.contains(['{', '[', '<', '"']) =>
+            {
+                (Some(name.trim().to_string()), text.trim())
+            }
+            _ => (None, body),
+        };
+        let interpolated = yarn_placeholders(text);
+        if let Err(error) = RatMarkup::parse(&without_placeholders(&interpolated)) {
+            self.ctx.reject(
+                self.ctx
+                    .error(line.number, column_in(line.line, text), error)
+                    .hint("braces are ratspinner tags, `{$var}` says a variable"),
+            )?;
+        }
+        Ok(YarnStatement::Line {
+            speaker,
+            text: interpolated,
+            when,
+            hooks,
+        })
+    }
+
+    /// peels trailing `#tags` and an `<<if ..>>` off a line or option
+    fn split_line<'l>(
+        &self,
+        line: YarnLine,
+        raw: &'l str,
+    ) -> Result<(&'l str, Vec<RatCondition>, Vec<String>), RatParseError> {
+        let error_at = |part: &str, message: String| {
+            self.ctx
+                .error(line.number, column_in(line.line, part), message)
+        };
+
+        let tags_start = raw
+            .char_indices()
+            .find(|(index, ch)| {
+                *ch == '#' && raw[..*index].chars().last().is_none_or(char::is_whitespace)
+            })
+            .map_or(raw.len(), |(index, _)| index);
+        let mut hooks = Vec::new();
+        for tag in raw[tags_start..].split_whitespace() {
+            let Some(tag) = tag.strip_prefix('#') else {
+                return Err(error_at(tag, format!("unexpected '{tag}' after tags")));
+            };
+            // line ids are for yarn's localisation, not hooks
+            if tag.starts_with("line:") || tag == "lastline" {
+                continue;
+            }
+            parse_hook_call(tag).map_err(|error| error_at(tag, error))?;
+            hooks.push(tag.to_string());
+        }
+
+        let mut body = raw[..tags_start].trim_end();
+        let mut conditions = Vec::new();
+        if let Some(inner) = body.strip_suffix(">>")
+            && let Some(start) = inner.rfind("<<if ")
+        {
+            let condition = &inner[start + "<<if ".len()..];
+            conditions =
+                translate_condition(condition).map_err(|error| error_at(condition, error))?;
+            body = inner[..start].trim_end();
+        }
+        Ok((body, conditions, hooks))
+    }
+}
+
+/// `<<name args>>` to `(name, args)`
+fn split_command(raw: &str) -> Option<(&str, &str)> {
+    let inner = raw.strip_prefix("<<")?.strip_suffix(">>")?.trim();
+    Some(match inner.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim()),
+        None => (inner, ""),
+    })
+}
+
+/// yarn's `{$gold}` into ratspinner's `{gold}`
+fn yarn_placeholders(text: &str) -> String {
+    text.replace("{$", "{")
+}
+
+/// `$gold gte 5 and not $met` into `gold >= 5` and `!met`
+fn translate_condition(raw: &str) -> Result<Vec<RatCondition>, String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    for token in raw.split_whitespace() {
+        let token = match token {
+            "and" | "&&" => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            "or" | "||" | "xor" | "^" => {
+                return Err(format!("'{token}' is not supported, guards can only `and`"));
+            }
+            "is" | "eq" | "==" => "==",
+            "neq" | "!=" => "!=",
+            "gt" => ">",
+            "lt" => "<",
+            "gte" => ">=",
+            "lte" => "<=",
+            "not" => "!",
+            token if token.contains(['(', ')', '{']) => {
+                return Err("parentheses and functions are not supported".to_string());
+            }
+            token => token,
+        };
+        if !current.is_empty() && !current.ends_with('!') {
+            current.push(' ');
+        }
+        current.push_str(&token.replace('$', ""));
+    }
+    parts.push(current);
+    parts.iter().map(|part| RatCondition::parse(part)).collect()
+}
+
+/// `$x to 2`, `$x = "hi"` or `$x to $x + 1`
+fn translate_set(raw: &str) -> Result<RatEffect, String> {
+    let name_end = raw
+        .find(|ch: char| ch.is_whitespace() || ch == '=')
+        .unwrap_or(raw.len());
+    let (var, rest) = raw.split_at(name_end);
+    let Some(var) = var.strip_prefix('$') else {
+        return Err(format!("'{var}' is not a $variable"));
+    };
+    let rest = rest.trim_start();
+    let Some(value) = rest.strip_prefix("to ").or_else(|| rest.strip_prefix('=')) else {
+        return Err("expected `<<set $var to value>>`".to_string());
+    };
+    let value = value.trim();
+    match value.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [lhs, op @ ("+" | "-"), amount] if lhs.strip_prefix('$') == Some(var) => {
+            let amount: i64 = amount
+                .parse()
+                .map_err(|_| format!("'{amount}' is not a valid increment"))?;
+            Ok(RatEffect::inc(
+                var,
+                if *op == "-" { -amount } else { amount },
+            ))
+        }
+        _ if value.contains('$') => {
+            Err("only plain values and `$var + n` / `$var - n` are supported".to_string())
+        }
+        _ => RatEffect::parse_set(&format!("{var} = {value}")),
+    }
+}
+
+/// a dangling link waiting for whatever comes next
+#[derive(Debug, Clone, Copy)]
+enum Exit {
+    /// the title itself, before it has a node
+    Entry,
+    Next(usize),
+    /// a branch guard that failed
+    Else(usize),
+    /// picking one of a node's options
+    Choice(usize, usize),
+}
+
+/// lays statements out as nodes, one forward pass per title
+struct YarnCompiler<'a> {
+    title: &'a str,
+    /// nodes made for the current title so far
+    count: usize,
+    nodes: Vec<RatNode>,
+    speakers: &'a RatSpeakers,
+}
+
+impl YarnCompiler<'_> {
+    /// returns the exits left open for whatever follows `statements`, none
+    /// after a `<<jump>>` or `<<stop>>`
+    fn block(&mut self, statements: &[YarnStatement], mut exits: Vec<Exit>) -> Vec<Exit> {
+        // `<<set>>`s ride along on the next plain line
+        let mut pending: Vec<RatEffect> = Vec::new();
+        // a line the options right after it can hang off
+        let mut last_line: Option<usize> = None;
+
+        for statement in statements {
+            match statement {
+                YarnStatement::Set(effect) => {
+                    pending.push(effect.clone());
+                    continue;
+                }
+                YarnStatement::Line {
+                    speaker,
+                    text,
+                    when,
+                    hooks,
+                } => {
+                    // a skipped line must not skip the sets before it
+                    if !when.is_empty() {
+                        exits = self.flush(exits, &mut pending);
+                    }
+                    let mut node = RatNodeBuilder::new(self.next_id());
+                    if let Some(speaker) = speaker {
+                        node = match self.speakers.get(speaker) {
+                            Some(def) => node.speaker_from(speaker, def),
+                            None => node.speaker(speaker),
+                        };
+                    }
+                    node = node.text(text);
+                    for hook in hooks {
+                        node = node.hook(hook);
+                    }
+                    for condition in when {
+                        node = node.when(condition.clone());
+                    }
+                    for effect in pending.drain(..) {
+                        node = node.effect(effect);
+                    }
+                    let index = self.push(node.build(), exits);
+                    exits = vec![Exit::Next(index)];
+                    last_line = Some(index);
+                    continue;
+                }
+                YarnStatement::Options(options) => {
+                    let index = match last_line {
+                        Some(index) if pending.is_empty() && self.nodes[index].when.is_empty() => {
+                            index
+                        }
+                        // options without a line in front get an empty one
+                        _ => {
+                            let mut node = RatNodeBuilder::new(self.next_id());
+                            for effect in pending.drain(..) {
+                                node = node.effect(effect);
+                            }
+                            self.push(node.build(), exits)
+                        }
+                    };
+                    exits = Vec::new();
+                    for (option_index, option) in options.iter().enumerate() {
+                        // sets right under an option apply when it is picked
+                        let sets = option
+                            .body
+                            .iter()
+                            .map_while(|statement| match statement {
+                                YarnStatement::Set(effect) => Some(effect.clone()),
+                                _ => None,
+                            })
+                            .collect::<Vec<_>>();
+                        let body = &option.body[sets.len()..];
+                        self.nodes[index].options.push(RatOption {
+                            id: None,
+                            text: option.text.clone(),
+                            next: None,
+                            hooks: option.hooks.clone(),
+                            conditions: option.conditions.clone(),
+                            effects: sets,
+                        });
+                        exits.extend(self.block(body, vec![Exit::Choice(index, option_index)]));
+                    }
+                }
+                YarnStatement::If {
+                    branches,
+                    otherwise,
+                } => {
+                    exits = self.flush(exits, &mut pending);
+                    let mut after = Vec::new();
+                    for (conditions, body) in branches {
+                        let mut router = RatNodeBuilder::new(self.next_id());
+                        for condition in conditions {
+                            router = router.when(condition.clone());
+                        }
+                        let index = self.push(router.build(), exits);
+                        after.extend(self.block(body, vec![Exit::Next(index)]));
+                        exits = vec![Exit::Else(index)];
+                    }
+                    match otherwise {
+                        Some(body) => after.extend(self.block(body, exits)),
+                        None => after.extend(exits),
+                    }
+                    exits = after;
+                }
+                YarnStatement::Jump(target) => {
+                    exits = self.flush(exits, &mut pending);
+                    self.link(exits, target);
+                    return Vec::new();
+                }
+                YarnStatement::Stop => {
+                    exits = self.flush(exits, &mut pending);
+                    self.close(exits);
+                    return Vec::new();
+                }
+            }
+            last_line = None;
+        }
+        self.flush(exits, &mut pending)
+    }
+
+    fn next_id(&mut self) -> String {
+        let id = match self.count {
+            0 => self.title.to_string(),
+            count => format!("{}.{count}", self.title),
+        };
+        self.count += 1;
+        id
+    }
+
+    fn push(&mut self, node: RatNode, exits: Vec<Exit>) -> usize {
+        self.link(exits, &node.id);
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    fn link(&mut self, exits: Vec<Exit>, target: &str) {
+        for exit in exits {
+            let slot = match exit {
+                Exit::Entry if target == self.title => continue,
+                // a title that only jumps still needs a node to jump from
+                Exit::Entry => {
+                    let node = RatNodeBuilder::new(self.next_id()).next(target).build();
+                    self.nodes.push(node);
+                    continue;
+                }
+                Exit::Next(index) => &mut self.nodes[index].next,
+                Exit::Else(index) => &mut self.nodes[index].else_next,
+                Exit::Choice(index, option) => &mut self.nodes[index].options[option].next,
+            };
+            *slot = Some(target.to_string());
+        }
+    }
+
+    /// pending sets get a silent node of their own
+    fn flush(&mut self, exits: Vec<Exit>, pending: &mut Vec<RatEffect>) -> Vec<Exit> {
+        if pending.is_empty() {
+            return exits;
+        }
+        let mut node = RatNodeBuilder::new(self.next_id());
+        for effect in pending.drain(..) {
+            node = node.effect(effect);
+        }
+        vec![Exit::Next(self.push(node.build(), exits))]
+    }
+
+    /// unset targets already close the dialogue, but a failed guard without
+    /// `else` would fall into its branch, so those get an empty node to end on
+    fn close(&mut self, exits: Vec<Exit>) {
+        if exits
+            .iter()
+            .any(|exit| matches!(exit, Exit::Entry | Exit::Else(_)))
+        {
+            let node = RatNodeBuilder::new(self.next_id()).build();
+            self.push(node, exits);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::ratspinner::{
+        harness::RatHarness, printer::write_rat, runtime::parse_script_bytes, types::RatStart,
+        vars::RatValue,
+    };
+
+    const WRONG_NUMBER: &str = "\
+title: Start
+tags: phone
+---
+// picks up on every ring
+<<set $calls to $calls + 1>>
+Caller: hello? is this the {shake}right{/shake} number? #phone.static #line:a1f0
+-> yes
+    <<set $answered to true>>
+    Caller: good. don't hang up.
+-> no <<if $calls gt 1>>
+    <<jump Hangup>>
+-> who is this? #dialog.ask
+    Caller: you know who.
+<<if $answered>>
+    Caller: meet me by the mailbox. #quest.mailbox
+<<elseif $calls gte 3>>
+    Caller: stop picking up.
+<<else>>
+    Caller: call me back.
+<<endif>>
+===
+
+title: Hangup
+---
+<<set $hung_up to true>>
+<<stop>>
+===
+";
+
+    fn parse(source: &str, path: &str) -> Vec<RatScript> {
+        parse_script_bytes(
+            source.as_bytes(),
+            Path::new(path),
+            false,
+            &RatSpeakers::default(),
+        )
+        .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    #[test]
+    fn imports_play_like_rat_scripts() {
+        let scripts = parse(WRONG_NUMBER, "wrong_number.yarn");
+        let mut harness = RatHarness::new();
+        for script in scripts {
+            harness.register(script);
+        }
+
+        harness.start(RatStart::new("wrong_number"));
+        assert_eq!(
+            harness.current(),
+            Some(("wrong_number".into(), "Start".into()))
+        );
+        assert_eq!(
+            harness.options().len(),
+            2,
+            "`no` is hidden on the first call"
+        );
+        harness.choose_option("yes").expect("yes is visible");
+        harness.advance();
+        assert!(harness.transcript().fired("quest.mailbox"));
+        harness.advance();
+        assert!(!harness.is_open());
+
+        let hooks: Vec<&str> = harness
+            .transcript()
+            .hooks
+            .iter()
+            .map(|hook| hook.hook.as_str())
+            .collect();
+        assert_eq!(hooks, ["phone.static", "quest.mailbox"]);
+        assert_eq!(harness.variables().get("calls"), Some(&RatValue::Int(1)));
+
+        harness.start(RatStart::new("wrong_number"));
+        harness
+            .choose_option("no")
+            .expect("no shows up on the second call");
+        assert!(!harness.is_open());
+        assert_eq!(
+            harness.variables().get("hung_up"),
+            Some(&RatValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn imports_convert_to_rat() {
+        let scripts = parse(WRONG_NUMBER, "wrong_number.yarn");
+        let text = write_rat(&scripts, &RatSpeakers::default());
+        assert!(text.starts_with("// script: wrong_number\n// entry: Start\n"));
+        assert_eq!(parse(&text, "wrong_number.rat"), scripts, "{text}");
+    }
+}
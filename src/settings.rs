@@ -26,6 +26,7 @@ pub enum SettingKey {
     WorldSfxVolume,
     VoiceVolume,
     DialogueSpeed,
+    SkipReadLines,
     UiScaleMode,
     UiScale,
     CursorMotion,
@@ -35,13 +36,14 @@ pub enum SettingKey {
 }
 
 impl SettingKey {
-    pub const ALL: [Self; 12] = [
+    pub const ALL: [Self; 13] = [
         Self::MasterVolume,
         Self::MusicVolume,
         Self::UiSfxVolume,
         Self::WorldSfxVolume,
         Self::VoiceVolume,
         Self::DialogueSpeed,
+        Self::SkipReadLines,
         Self::UiScaleMode,
         Self::UiScale,
         Self::CursorMotion,
@@ -58,6 +60,7 @@ impl SettingKey {
             Self::WorldSfxVolume => "world sfx volume",
             Self::VoiceVolume => "voice volume",
             Self::DialogueSpeed => "dialogue speed",
+            Self::SkipReadLines => "skip read lines",
             Self::UiScaleMode => "ui scale mode",
             Self::UiScale => "manual ui scale",
             Self::CursorMotion => "cursor motion",
@@ -77,6 +80,8 @@ pub struct GameSettings {
     pub world_sfx_volume: f32,
     pub voice_volume: f32,
     pub dialogue_speed: f32,
+    /// fast-forwards dialogue lines already read on an earlier visit or run
+    pub skip_read_lines: bool,
     pub ui_scale_auto: bool,
     pub manual_ui_scale: f32,
     pub cursor_motion: bool,
@@ -94,6 +99,7 @@ impl Default for GameSettings {
             world_sfx_volume: 0.95,
             voice_volume: 1.0,
             dialogue_speed: 1.0,
+            skip_read_lines: false,
             ui_scale_auto: true,
             manual_ui_scale: 1.0,
             cursor_motion: true,
@@ -130,6 +136,9 @@ impl GameSettings {
             SettingKey::DialogueSpeed => {
                 self.dialogue_speed = (self.dialogue_speed + step * 0.1).clamp(0.5, 2.0);
             }
+            SettingKey::SkipReadLines => {
+                self.skip_read_lines = !self.skip_read_lines;
+            }
             SettingKey::UiScaleMode => {
                 self.ui_scale_auto = !self.ui_scale_auto;
             }
@@ -159,6 +168,12 @@ impl GameSettings {
             SettingKey::WorldSfxVolume => percent_text(self.world_sfx_volume),
             SettingKey::VoiceVolume => percent_text(self.voice_volume),
             SettingKey::DialogueSpeed => percent_text(self.dialogue_speed),
+            SettingKey::SkipReadLines =>
+                if self.skip_read_lines {
+                    "on".to_string()
+                } else {
+                    "off".to_string()
+                },
             SettingKey::UiScaleMode =>
                 if self.ui_scale_auto {
                     "auto".to_string()
@@ -198,19 +213,28 @@ fn percent_text(value: f32) -> String {
     format!("{:.0}%", value * 100.0)
 }
 
-const SETTINGS_SAVE_PATH: &str = "saves/settings.ron";
-#[cfg(target_arch = "wasm32")]
-const SETTINGS_STORAGE_KEY: &str = "feverish.settings";
+/// a ron file under `saves/` natively, a localStorage key on the web
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SaveSlot {
+    pub path: &'static str,
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub storage_key: &'static str,
+}
+
+const SETTINGS_SLOT: SaveSlot = SaveSlot {
+    path: "saves/settings.ron",
+    storage_key: "feverish.settings",
+};
 
 fn load_persisted_settings() -> Result<GameSettings, String> {
-    let Some(content) = read_settings_source()? else {
+    let Some(content) = SETTINGS_SLOT.read()? else {
         return Ok(GameSettings::default());
     };
 
     ron::from_str::<GameSettings>(&content).map_err(|error| {
         format!(
             "failed to parse '{}' as settings RON: {}",
-            settings_location_hint(),
+            SETTINGS_SLOT.location(),
             error
         )
     })
@@ -227,79 +251,84 @@ fn save_settings_on_change(settings: Res<GameSettings>) {
         return;
     };
 
-    if let Err(error) = write_settings_source(&content) {
+    if let Err(error) = SETTINGS_SLOT.write(&content) {
         warn!("{error}");
     }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-fn read_settings_source() -> Result<Option<String>, String> {
-    let path = Path::new(SETTINGS_SAVE_PATH);
-    if !path.exists() {
-        return Ok(None);
-    }
-
-    fs::read_to_string(path)
-        .map(Some)
-        .map_err(|error| format!("failed to read '{}': {}", path.display(), error))
-}
+impl SaveSlot {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read(&self) -> Result<Option<String>, String> {
+        let path = Path::new(self.path);
+        if !path.exists() {
+            return Ok(None);
+        }
 
-#[cfg(target_arch = "wasm32")]
-fn read_settings_source() -> Result<Option<String>, String> {
-    let Some(window) = web_sys::window() else {
-        return Ok(None);
-    };
-    let Ok(Some(storage)) = window.local_storage() else {
-        return Ok(None);
-    };
+        fs::read_to_string(path)
+            .map(Some)
+            .map_err(|error| format!("failed to read '{}': {}", path.display(), error))
+    }
 
-    storage
-        .get_item(SETTINGS_STORAGE_KEY)
-        .map_err(|error| format!("failed to read settings from localStorage: {error:?}"))
-}
+    #[cfg(target_arch = "wasm32")]
+    pub fn read(&self) -> Result<Option<String>, String> {
+        let Some(window) = web_sys::window() else {
+            return Ok(None);
+        };
+        let Ok(Some(storage)) = window.local_storage() else {
+            return Ok(None);
+        };
 
-#[cfg(not(target_arch = "wasm32"))]
-fn write_settings_source(content: &str) -> Result<(), String> {
-    let path = Path::new(SETTINGS_SAVE_PATH);
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|error| {
+        storage.get_item(self.storage_key).map_err(|error| {
             format!(
-                "failed to create settings directory '{}': {}",
-                parent.display(),
-                error
+                "failed to read '{}' from localStorage: {error:?}",
+                self.storage_key
             )
-        })?;
+        })
     }
 
-    fs::write(path, content).map_err(|error| {
-        format!(
-            "failed to write settings file '{}': {}",
-            path.display(),
-            error
-        )
-    })
-}
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write(&self, content: &str) -> Result<(), String> {
+        let path = Path::new(self.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| {
+                format!(
+                    "failed to create save directory '{}': {}",
+                    parent.display(),
+                    error
+                )
+            })?;
+        }
 
-#[cfg(target_arch = "wasm32")]
-fn write_settings_source(content: &str) -> Result<(), String> {
-    let Some(window) = web_sys::window() else {
-        return Err("failed to access browser window for settings persistence".to_string());
-    };
-    let Ok(Some(storage)) = window.local_storage() else {
-        return Err("failed to access browser localStorage for settings persistence".to_string());
-    };
+        fs::write(path, content)
+            .map_err(|error| format!("failed to write save file '{}': {}", path.display(), error))
+    }
 
-    storage
-        .set_item(SETTINGS_STORAGE_KEY, content)
-        .map_err(|error| format!("failed to write settings to localStorage: {error:?}"))
-}
+    #[cfg(target_arch = "wasm32")]
+    pub fn write(&self, content: &str) -> Result<(), String> {
+        let Some(window) = web_sys::window() else {
+            return Err("failed to access browser window for persistence".to_string());
+        };
+        let Ok(Some(storage)) = window.local_storage() else {
+            return Err("failed to access browser localStorage for persistence".to_string());
+        };
 
-#[cfg(not(target_arch = "wasm32"))]
-fn settings_location_hint() -> &'static str {
-    SETTINGS_SAVE_PATH
-}
+        storage
+            .set_item(self.storage_key, content)
+            .map_err(|error| {
+                format!(
+                    "failed to write '{}' to localStorage: {error:?}",
+                    self.storage_key
+                )
+            })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn location(&self) -> &'static str {
+        self.path
+    }
 
-#[cfg(target_arch = "wasm32")]
-fn settings_location_hint() -> &'static str {
-    SETTINGS_STORAGE_KEY
+    #[cfg(target_arch = "wasm32")]
+    pub fn location(&self) -> &'static str {
+        self.storage_key
+    }
 }
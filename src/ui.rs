@@ -1,5 +1,6 @@
 // ui plugin ddefinition and reexports
 
+pub(super) mod bark;
 pub(super) mod components;
 pub(super) mod confirm_popup;
 pub(crate) mod dialogue;
@@ -13,22 +14,18 @@ pub(super) mod pause_menu;
 pub(super) mod systems;
 pub(super) mod theme;
 
+pub use bark::{UiBarkCommand, UiBarkRequest};
 use bevy::prelude::*;
-#[allow(unused_imports)]
 pub use components::{
     DialogueUiRoot, DiscoveryEntry, DiscoveryInteraction, DiscoveryInteractionAction,
-    DiscoveryInteractionActor, DiscoveryInteractionRecord, DiscoveryKind, InventoryUiRoot,
-    MainMenuUi, PauseMenuUi, SpawnDroppedItem, UiDialogueCommand, UiDialogueMode, UiDialogueOption,
-    UiDialoguePreview, UiDialogueRequest, UiDiscoveryCommand, UiDiscoveryDbSnapshot, UiMenuAction,
+    DiscoveryInteractionActor, DiscoveryKind, InventoryUiRoot, MainMenuUi, PauseMenuUi,
+    SpawnDroppedItem, UiDialogueCommand, UiDialogueMode, UiDialogueOption, UiDialoguePreview,
+    UiDialogueRequest, UiDialogueTimeout, UiDiscoveryCommand, UiDiscoveryDbSnapshot, UiMenuAction,
 };
-#[allow(unused_imports)]
-pub use discovery_api::DiscoveryCommandsExt;
-#[allow(unused_imports)]
 pub use ending::{
     EndingUiRoot, UiEndingCatalog, UiEndingCommand, UiEndingCommandsExt, UiEndingPayload,
 };
-#[allow(unused_imports)]
-pub use hint::{UiHintCommand, UiHintCommandsExt, UiHintRequest};
+pub use hint::{UiHintCommand, UiHintRequest};
 pub use systems::UiDiscoveryDb;
 use systems::{
     animate_dither_pixels, animate_main_menu_ticker, apply_discovery_commands,
@@ -60,8 +57,9 @@ impl Plugin for UiPlugin {
             .add_plugins(fx::UiMenuFxPlugin)
             .add_message::<UiMenuAction>()
             .add_message::<UiDialogueCommand>()
-            .add_message::<ending::UiEndingCommand>()
-            .add_message::<hint::UiHintCommand>()
+            .add_message::<UiBarkCommand>()
+            .add_message::<UiEndingCommand>()
+            .add_message::<UiHintCommand>()
             .add_message::<inventory::UiInventoryCommand>()
             .add_message::<UiDiscoveryCommand>()
             .add_observer(on_ui_scroll)
@@ -123,6 +121,8 @@ impl Plugin for UiPlugin {
                     inventory::apply_inventory_commands,
                     (
                         dialogue::update_typewriter_dialogue,
+                        dialogue::fast_forward_read_lines,
+                        dialogue::drain_dialogue_timeout_bar,
                         dialogue::sync_picker_preview_from_selection,
                         dialogue::sync_and_frame_dialogue_preview,
                         dialogue::rotate_dialogue_preview,
@@ -132,6 +132,7 @@ impl Plugin for UiPlugin {
                         dialogue::handle_dialogue_arrow_buttons,
                         dialogue::handle_dialogue_quick_action_buttons,
                         dialogue::handle_dialogue_confirm_button,
+                        dialogue::sync_option_slot_color,
                         dialogue::animate_option_slot_transition,
                         dialogue::animate_dialogue_glyphs,
                     ),
@@ -150,6 +151,16 @@ impl Plugin for UiPlugin {
                     .chain()
                     .run_if(in_state(AppState::Main)),
             )
+            .add_systems(
+                Update,
+                (
+                    bark::apply_bark_commands,
+                    bark::place_bark_subtitles,
+                    bark::fade_bark_subtitles,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Main)),
+            )
             .add_systems(
                 Update,
                 (
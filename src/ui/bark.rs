@@ -0,0 +1,168 @@
+use bevy::{
+    prelude::*,
+    text::{Justify, LineBreak, TextLayout},
+};
+
+use super::{dialogue::UiDialogueState, systems::UiFonts, theme};
+use crate::psx::PsxCamera;
+
+const BARK_FONT_SIZE: f32 = 20.0;
+/// how far over the speaker's feet the subtitle floats
+const BARK_HEAD_HEIGHT: f32 = 2.0;
+/// the subtitle stays up this long after the voice is done
+const BARK_HOLD_SECS: f32 = 1.2;
+const BARK_FADE_SECS: f32 = 0.4;
+const BARK_FRAME_BASE_ALPHA: f32 = 0.70;
+
+#[derive(Component, Debug, Clone)]
+pub(super) struct UiBark {
+    target: Option<Entity>,
+    timer: Timer,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub(super) struct UiBarkText;
+
+/// an ambient line, shown over `target` or low in the middle without one
+#[derive(Debug, Clone)]
+pub struct UiBarkRequest {
+    pub target: Option<Entity>,
+    pub speaker: String,
+    pub text: String,
+    /// how long the voice takes to say it
+    pub duration_secs: f32,
+}
+
+#[derive(Message, Debug, Clone)]
+pub enum UiBarkCommand {
+    Show(UiBarkRequest),
+    Clear,
+}
+
+/// only one voice plays at a time, so a new bark replaces the one on screen
+pub(super) fn apply_bark_commands(
+    mut commands: Commands,
+    mut msgs: MessageReader<UiBarkCommand>,
+    fonts: Res<UiFonts>,
+    barks: Query<Entity, With<UiBark>>,
+) {
+    for msg in msgs.read() {
+        for entity in &barks {
+            commands.entity(entity).try_despawn();
+        }
+        let UiBarkCommand::Show(request) = msg else {
+            continue;
+        };
+
+        let text = if request.speaker.is_empty() {
+            request.text.clone()
+        } else {
+            format!("{}: {}", request.speaker, request.text)
+        };
+        commands
+            .spawn((
+                Name::new("UI Bark"),
+                UiBark {
+                    target: request.target,
+                    timer: Timer::from_seconds(
+                        request.duration_secs.max(0.0) + BARK_HOLD_SECS,
+                        TimerMode::Once,
+                    ),
+                },
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(50.0),
+                    top: Val::Percent(78.0),
+                    max_width: Val::Px(360.0),
+                    padding: UiRect::new(Val::Px(8.0), Val::Px(8.0), Val::Px(4.0), Val::Px(4.0)),
+                    ..default()
+                },
+                // centered on the anchor, sitting on top of it
+                UiTransform::from_translation(Val2::percent(-50.0, -100.0)),
+                BackgroundColor(Color::srgba(0.04, 0.05, 0.09, BARK_FRAME_BASE_ALPHA)),
+                // placed before it shows up
+                Visibility::Hidden,
+                Pickable::IGNORE,
+                GlobalZIndex(105),
+            ))
+            .with_children(|frame| {
+                frame.spawn((
+                    Name::new("UI Bark Text"),
+                    UiBarkText,
+                    Text::new(text),
+                    TextFont {
+                        font: fonts.body.clone(),
+                        font_size: BARK_FONT_SIZE,
+                        ..default()
+                    },
+                    TextColor(theme::TEXT_LIGHT),
+                    TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+                ));
+            });
+    }
+}
+
+/// keeps each subtitle over its speaker's head, hidden while they're off
+/// screen or a dialogue is open
+pub(super) fn place_bark_subtitles(
+    dialogue: Res<UiDialogueState>,
+    cameras: Query<(&Camera, &GlobalTransform), With<PsxCamera>>,
+    targets: Query<&GlobalTransform>,
+    mut barks: Query<(&UiBark, &mut Node, &mut Visibility)>,
+) {
+    let camera = cameras.single().ok();
+    for (bark, mut node, mut visibility) in &mut barks {
+        if dialogue.active {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        let Some(target) = bark.target else {
+            *visibility = Visibility::Inherited;
+            continue;
+        };
+
+        let ndc = camera
+            .zip(targets.get(target).ok())
+            .and_then(|((camera, camera_transform), target)| {
+                camera.world_to_ndc(
+                    camera_transform,
+                    target.translation() + Vec3::Y * BARK_HEAD_HEIGHT,
+                )
+            })
+            .filter(|ndc| (0.0..=1.0).contains(&ndc.z) && ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0);
+        let Some(ndc) = ndc else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        node.left = Val::Percent((ndc.x + 1.0) * 50.0);
+        node.top = Val::Percent((1.0 - ndc.y) * 50.0);
+        *visibility = Visibility::Inherited;
+    }
+}
+
+pub(super) fn fade_bark_subtitles(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut barks: Query<(Entity, &mut UiBark, &mut BackgroundColor, &Children)>,
+    mut texts: Query<&mut TextColor, With<UiBarkText>>,
+    targets: Query<(), With<GlobalTransform>>,
+) {
+    for (entity, mut bark, mut background, children) in &mut barks {
+        bark.timer.tick(time.delta());
+        let speaker_gone = bark
+            .target
+            .is_some_and(|target| targets.get(target).is_err());
+        if bark.timer.is_finished() || speaker_gone {
+            commands.entity(entity).try_despawn();
+            continue;
+        }
+
+        let alpha = (bark.timer.remaining_secs() / BARK_FADE_SECS).clamp(0.0, 1.0);
+        background.0 = Color::srgba(0.04, 0.05, 0.09, BARK_FRAME_BASE_ALPHA * alpha);
+        for child in children.iter() {
+            if let Ok(mut color) = texts.get_mut(child) {
+                color.0 = theme::TEXT_LIGHT.with_alpha(alpha);
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::settings::SettingKey;
+use crate::{settings::SettingKey, voice::SpeakId};
 
 #[derive(Component, Debug, Clone, Copy)]
 pub struct MainMenuUi;
@@ -63,7 +63,6 @@ impl DiscoveryEntry {
         self
     }
 
-    #[allow(dead_code)]
     pub fn image_path(mut self, path: impl Into<String>) -> Self {
         self.image_path = Some(path.into());
         self
@@ -285,6 +284,19 @@ pub struct UiDialogueRequest {
     pub preview: Option<UiDialoguePreview>,
     pub options: Vec<UiDialogueOption>,
     pub reveal_duration_secs: f32,
+    /// the `Speak` that goes out with this line, the typewriter waits for its
+    /// voice to start
+    pub voice: Option<SpeakId>,
+    /// the player read this line before, `skip_read_lines` fast-forwards it
+    pub read: bool,
+    /// a timed choice, drawn as a draining bar
+    pub timeout: Option<UiDialogueTimeout>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiDialogueTimeout {
+    pub secs_left: f32,
+    pub secs_total: f32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -12,12 +12,16 @@ use bevy::{
 use super::{
     components::{
         DialogueUiRoot, UiDialogueCommand, UiDialogueMode, UiDialogueOption, UiDialoguePreview,
-        UiDialogueRequest, UiDiscoveryCommand,
+        UiDialogueRequest, UiDialogueTimeout, UiDiscoveryCommand,
     },
     systems::{UiDiscoveryDb, UiFonts},
     theme,
 };
-use crate::{ratspinner::RatCommand, settings::GameSettings};
+use crate::{
+    ratspinner::{RatCommand, RatGlyph, RatMarkup},
+    settings::GameSettings,
+    voice::{SpeakId, VoicePhoneme, VoiceStarted},
+};
 
 #[derive(Resource, Default)]
 pub(crate) struct UiDialogueState {
@@ -35,12 +39,23 @@ struct DialogueSession {
     prompt_text: Entity,
     line_row: Entity,
     slot_text: Entity,
-    text_chars: Vec<char>,
+    glyphs: Vec<RatGlyph>,
     options: Vec<UiDialogueOption>,
     selected_option: usize,
     revealed: usize,
     reveal_timer: f32,
     char_interval: f32,
+    /// the `Speak` saying this line, other voices don't move the typewriter
+    voice: Option<SpeakId>,
+    /// counts down until the voice starts, the typewriter holds meanwhile
+    voice_wait: Option<f32>,
+    /// glyphs the voice got to, the typewriter follows it instead of its clock
+    spoken: Option<usize>,
+    /// counts down on a read line under `skip_read_lines`, then moves on
+    skip_timer: Option<f32>,
+    /// mirrors the runtime's clock for the bar, the runtime picks on its own
+    timeout: Option<UiDialogueTimeout>,
+    timeout_bar: Option<Entity>,
     slot_anim_timer: f32,
     slot_anim_dir: f32,
     preview_viewport: Option<Entity>,
@@ -61,8 +76,16 @@ pub(super) struct DialogueGlyphFx {
     age: f32,
     duration: f32,
     amplitude: f32,
+    /// markup effects keep the component alive after the pop-in
+    shake: bool,
+    glitch: Option<char>,
+    /// what a glitched glyph goes back to, whisper included
+    color: Color,
+    seed: f32,
 }
 
+const GLITCH_GLYPHS: [char; 8] = ['#', '%', '&', '@', '?', '/', '\\', '_'];
+
 #[derive(Component, Debug, Clone, Copy)]
 pub(super) struct DialogueArrowButton {
     dir: i32,
@@ -94,6 +117,9 @@ pub(super) struct DialogueTextScrollArea;
 #[derive(Component)]
 pub(super) struct DialogueTextScrollHint;
 
+#[derive(Component)]
+pub(super) struct DialogueTimeoutBar;
+
 const PREVIEW_RENDER_LAYER: usize = 19;
 const DIALOGUE_SCROLL_HINT_THRESHOLD: usize = 95;
 const DIALOGUE_SLOT_FONT_MAX: f32 = 27.0;
@@ -106,6 +132,10 @@ const DIALOGUE_SLOT_CONTENT_HEIGHT: f32 = 44.0;
 const DIALOGUE_SLOT_LINE_HEIGHT_FACTOR: f32 = 1.22;
 const DIALOGUE_PROMPT_FONT_SIZE: f32 = 16.0;
 const DIALOGUE_QUICK_ACTION_FONT_SIZE: f32 = 12.0;
+/// how long a fast-forwarded line stays up
+const DIALOGUE_SKIP_HOLD_SECS: f32 = 0.25;
+/// the typewriter stops waiting for a voice that hasn't started by then
+const VOICE_START_TIMEOUT_SECS: f32 = 0.5;
 
 pub(super) fn apply_dialogue_commands(
     mut commands: Commands,
@@ -146,6 +176,13 @@ pub(super) fn apply_dialogue_commands(
                     settings.dialogue_speed,
                 );
                 reset_text(&mut commands, &mut session, &fonts, &children);
+                if req.read && settings.skip_read_lines {
+                    reveal_all_chars(&mut commands, &mut session, &fonts, &children);
+                    refresh_prompt(&mut commands, &session);
+                    if session.options.is_empty() {
+                        session.skip_timer = Some(DIALOGUE_SKIP_HOLD_SECS);
+                    }
+                }
                 state.active = true;
                 runtime.session = Some(session);
             }
@@ -153,7 +190,7 @@ pub(super) fn apply_dialogue_commands(
                 let Some(session) = runtime.session.as_mut() else {
                     continue;
                 };
-                if session.revealed < session.text_chars.len() {
+                if session.revealed < session.glyphs.len() {
                     reveal_all_chars(&mut commands, session, &fonts, &children);
                     refresh_prompt(&mut commands, session);
                     continue;
@@ -190,7 +227,7 @@ pub(super) fn advance_dialogue_with_mouse(
         return;
     }
 
-    if session.revealed < session.text_chars.len() {
+    if session.revealed < session.glyphs.len() {
         commands.write_message(UiDialogueCommand::Advance);
     } else if session.options.is_empty() {
         commands.write_message(RatCommand::Advance);
@@ -386,7 +423,7 @@ pub(super) fn handle_dialogue_shortcuts(
         return;
     }
 
-    if session.revealed < session.text_chars.len() {
+    if session.revealed < session.glyphs.len() {
         if any_pressed(&keys, &[KeyCode::KeyE, KeyCode::Enter, KeyCode::Space]) {
             commands.write_message(UiDialogueCommand::Advance);
         }
@@ -454,7 +491,7 @@ pub(super) fn handle_dialogue_arrow_buttons(
             Interaction::Pressed => {
                 *bg = BackgroundColor(theme::BUTTON_BG);
                 *border = theme::border(false);
-                if session.revealed >= session.text_chars.len() && !session.options.is_empty() {
+                if session.revealed >= session.glyphs.len() && !session.options.is_empty() {
                     cycle_option(&mut commands, session, arrow.dir, &fonts);
                 }
             }
@@ -501,7 +538,7 @@ pub(super) fn handle_dialogue_quick_action_buttons(
                 });
                 *border = theme::border(false);
                 if enabled
-                    && session.revealed >= session.text_chars.len()
+                    && session.revealed >= session.glyphs.len()
                     && action.option_index < session.options.len()
                 {
                     commands.write_message(RatCommand::Choose(action.option_index));
@@ -532,7 +569,6 @@ pub(super) fn handle_dialogue_confirm_button(
         (&Interaction, &mut BackgroundColor, &mut BorderColor),
         (Changed<Interaction>, With<DialogueConfirmButton>),
     >,
-    mut slot_colors: Query<&mut TextColor, With<DialogueOptionSlot>>,
     runtime: Res<UiDialogueRuntime>,
     mut commands: Commands,
 ) {
@@ -541,13 +577,11 @@ pub(super) fn handle_dialogue_confirm_button(
     };
 
     for (interaction, mut bg, mut border) in &mut interactions {
-        let mut slot_text_color = theme::TEXT_LIGHT;
         match *interaction {
             Interaction::Pressed => {
                 *bg = BackgroundColor(theme::BUTTON_BG);
                 *border = theme::border(false);
-                slot_text_color = theme::TEXT_DARK;
-                if session.revealed >= session.text_chars.len()
+                if session.revealed >= session.glyphs.len()
                     && session.selected_option < session.options.len()
                     && session
                         .options
@@ -560,17 +594,45 @@ pub(super) fn handle_dialogue_confirm_button(
             Interaction::Hovered => {
                 *bg = BackgroundColor(theme::BUTTON_HOVER);
                 *border = theme::border(true);
-                slot_text_color = theme::TEXT_DARK;
             }
             Interaction::None => {
                 *bg = BackgroundColor(Color::srgb(0.08, 0.10, 0.13));
                 *border = theme::border(true);
             }
         }
+    }
+}
 
-        if let Ok(mut color) = slot_colors.get_mut(session.slot_text) {
-            *color = TextColor(slot_text_color);
-        }
+/// dark on the lit confirm button, dimmed for an option picked before
+pub(super) fn sync_option_slot_color(
+    confirm: Query<&Interaction, With<DialogueConfirmButton>>,
+    mut slot_colors: Query<&mut TextColor, With<DialogueOptionSlot>>,
+    runtime: Res<UiDialogueRuntime>,
+) {
+    let Some(session) = runtime.session.as_ref() else {
+        return;
+    };
+    let Ok(mut color) = slot_colors.get_mut(session.slot_text) else {
+        return;
+    };
+
+    let lit = confirm
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    let seen = session.mode == UiDialogueMode::Standard
+        && session
+            .options
+            .get(session.selected_option)
+            .is_some_and(|option| option.seen);
+    let wanted = if lit {
+        theme::TEXT_DARK
+    } else if seen {
+        theme::TEXT_SEEN
+    } else {
+        theme::TEXT_LIGHT
+    };
+    if color.0 != wanted {
+        *color = TextColor(wanted);
     }
 }
 
@@ -616,52 +678,194 @@ pub(super) fn sync_picker_preview_from_selection(
     );
 }
 
+/// markup pauses are held as written, the rest of `speech_secs` is spread over
+/// the glyphs weighted by their `{speed=..}`
+fn reveal_char_interval(glyphs: &[RatGlyph], speech_secs: f32, dialogue_speed: f32) -> f32 {
+    let beats = glyphs
+        .iter()
+        .map(|glyph| 1.0 / glyph.style.speed)
+        .sum::<f32>()
+        .max(1.0);
+    let speed = dialogue_speed.clamp(0.5, 2.0);
+    (speech_secs.max(0.10) / beats / speed).clamp(0.008, 0.070)
+}
+
 pub(super) fn update_typewriter_dialogue(
     time: Res<Time>,
     mut commands: Commands,
     fonts: Res<UiFonts>,
+    settings: Res<GameSettings>,
     mut runtime: ResMut<UiDialogueRuntime>,
+    mut voice_started: MessageReader<VoiceStarted>,
+    mut voice_phonemes: MessageReader<VoicePhoneme>,
     _children: Query<&Children>,
 ) {
+    // a bark, an overheard line or the line before may still be playing, even
+    // from the same speaker
+    let voice = runtime.session.as_ref().and_then(|session| session.voice);
+    let voice_started = voice_started
+        .read()
+        .filter(|started| voice.is_some() && started.id == voice)
+        .last()
+        .copied();
+    let spoken_to = voice_phonemes
+        .read()
+        .filter(|phoneme| voice.is_some() && phoneme.id == voice)
+        .map(|phoneme| phoneme.char_index + phoneme.char_len.max(1))
+        .max();
     let Some(session) = runtime.session.as_mut() else {
         return;
     };
-    if session.revealed >= session.text_chars.len() {
+    if session.revealed >= session.glyphs.len() {
+        return;
+    }
+    // synthesis takes a few frames, the text starts with the voice and keeps
+    // to its actual length rather than the estimate
+    if let Some(wait) = session.voice_wait.as_mut() {
+        *wait -= time.delta_secs();
+        if let Some(started) = voice_started {
+            let pauses: f32 = session.glyphs.iter().map(|glyph| glyph.pause).sum();
+            session.char_interval = reveal_char_interval(
+                &session.glyphs,
+                started.duration_secs - pauses,
+                settings.dialogue_speed,
+            );
+            session.spoken = started.timed.then_some(session.revealed);
+        } else if *wait > 0.0 {
+            return;
+        }
+        session.voice_wait = None;
+    }
+
+    // each glyph shows as its phoneme is said, pauses are in the voice already
+    if let Some(spoken) = session.spoken.as_mut() {
+        *spoken = (*spoken).max(spoken_to.unwrap_or_default());
+        while session.revealed < (*spoken).min(session.glyphs.len()) {
+            spawn_char(
+                &mut commands,
+                session.line_row,
+                session.glyphs[session.revealed],
+                session.revealed,
+                &fonts,
+            );
+            session.revealed += 1;
+        }
+        if session.revealed >= session.glyphs.len() {
+            refresh_prompt(&mut commands, session);
+        }
         return;
     }
 
     session.reveal_timer += time.delta_secs();
-    while session.reveal_timer >= session.char_interval
-        && session.revealed < session.text_chars.len()
-    {
-        session.reveal_timer -= session.char_interval;
-        let ch = session.text_chars[session.revealed];
-        spawn_char(&mut commands, session.line_row, ch, &fonts);
+    while let Some(glyph) = session.glyphs.get(session.revealed).copied() {
+        // `{pause=..}` holds before the glyph, `{speed=..}` stretches its beat
+        let wait = glyph.pause + session.char_interval / glyph.style.speed;
+        if session.reveal_timer < wait {
+            break;
+        }
+        session.reveal_timer -= wait;
+        spawn_char(
+            &mut commands,
+            session.line_row,
+            glyph,
+            session.revealed,
+            &fonts,
+        );
         session.revealed += 1;
-        if matches!(ch, '.' | ',' | ';' | ':' | '!' | '?') {
+        if matches!(glyph.ch, '.' | ',' | ';' | ':' | '!' | '?') {
             session.reveal_timer -= session.char_interval * 0.55;
         }
     }
 
-    if session.revealed >= session.text_chars.len() {
+    if session.revealed >= session.glyphs.len() {
         refresh_prompt(&mut commands, session);
     }
 }
 
+/// shrinks the timed choice bar along with the runtime's countdown
+pub(super) fn drain_dialogue_timeout_bar(
+    time: Res<Time>,
+    mut runtime: ResMut<UiDialogueRuntime>,
+    mut bars: Query<&mut Node, With<DialogueTimeoutBar>>,
+) {
+    let Some(session) = runtime.session.as_mut() else {
+        return;
+    };
+    let (Some(timeout), Some(bar)) = (session.timeout.as_mut(), session.timeout_bar) else {
+        return;
+    };
+    let Ok(mut node) = bars.get_mut(bar) else {
+        return;
+    };
+
+    timeout.secs_left = (timeout.secs_left - time.delta_secs()).max(0.0);
+    node.width = Val::Percent(100.0 * timeout.secs_left / timeout.secs_total.max(0.001));
+}
+
+/// moves past a line the player already read once it had its moment
+pub(super) fn fast_forward_read_lines(
+    time: Res<Time>,
+    mut runtime: ResMut<UiDialogueRuntime>,
+    mut commands: Commands,
+) {
+    let Some(session) = runtime.session.as_mut() else {
+        return;
+    };
+    let Some(timer) = session.skip_timer.as_mut() else {
+        return;
+    };
+
+    *timer -= time.delta_secs();
+    if *timer <= 0.0 {
+        session.skip_timer = None;
+        commands.write_message(RatCommand::Advance);
+    }
+}
+
 pub(super) fn animate_dialogue_glyphs(
     time: Res<Time>,
-    mut glyphs: Query<(Entity, &mut UiTransform, &mut DialogueGlyphFx)>,
+    mut glyphs: Query<(
+        Entity,
+        &mut UiTransform,
+        &mut DialogueGlyphFx,
+        &mut Text,
+        &mut TextColor,
+    )>,
     mut commands: Commands,
 ) {
-    for (entity, mut transform, mut fx) in &mut glyphs {
+    for (entity, mut transform, mut fx, mut text, mut color) in &mut glyphs {
         fx.age += time.delta_secs();
         let t = (fx.age / fx.duration).clamp(0.0, 1.0);
         let inv = 1.0 - t;
         transform.translation = Val2::px(0.0, (-fx.amplitude * inv * inv).round());
         transform.scale = Vec2::splat(1.0 + inv * 0.24);
-        if t >= 1.0 {
-            transform.translation = Val2::ZERO;
-            transform.scale = Vec2::ONE;
+        if t < 1.0 {
+            continue;
+        }
+        transform.translation = Val2::ZERO;
+        transform.scale = Vec2::ONE;
+
+        if fx.shake {
+            // jitter in ~30ms steps so it reads as nerves, not blur
+            let step = (fx.age * 32.0).floor();
+            let x = glyph_noise(fx.seed, step) * 2.0 - 1.0;
+            let y = glyph_noise(fx.seed + 17.0, step) * 2.0 - 1.0;
+            transform.translation = Val2::px((x * 2.0).round(), (y * 2.0).round());
+        }
+        if let Some(ch) = fx.glitch {
+            let step = (fx.age * 14.0).floor();
+            let roll = glyph_noise(fx.seed, step);
+            if roll > 0.86 {
+                let pick = (glyph_noise(fx.seed + 5.0, step) * GLITCH_GLYPHS.len() as f32) as usize;
+                text.0 = GLITCH_GLYPHS[pick.min(GLITCH_GLYPHS.len() - 1)].to_string();
+                color.0 = theme::TEXT_GLITCH;
+                transform.translation = Val2::px(((roll - 0.93) * 40.0).round(), 0.0);
+            } else if text.0.chars().ne([ch]) {
+                text.0 = ch.to_string();
+                color.0 = fx.color;
+            }
+        }
+        if !fx.shake && fx.glitch.is_none() {
             commands.queue(move |world: &mut World| {
                 if let Ok(mut entity_mut) = world.get_entity_mut(entity) {
                     entity_mut.remove::<DialogueGlyphFx>();
@@ -671,6 +875,13 @@ pub(super) fn animate_dialogue_glyphs(
     }
 }
 
+/// cheap stable hash in 0..1, enough for per-glyph flicker
+fn glyph_noise(seed: f32, step: f32) -> f32 {
+    ((seed * 12.9898 + step * 78.233).sin() * 43_758.547)
+        .fract()
+        .abs()
+}
+
 pub(super) fn update_dialogue_text_scroll_hint(
     runtime: Res<UiDialogueRuntime>,
     mut hints: Query<&mut Text, With<DialogueTextScrollHint>>,
@@ -686,7 +897,7 @@ pub(super) fn update_dialogue_text_scroll_hint(
         return;
     };
 
-    if session.text_chars.len() <= DIALOGUE_SCROLL_HINT_THRESHOLD {
+    if session.glyphs.len() <= DIALOGUE_SCROLL_HINT_THRESHOLD {
         hint_text.0.clear();
         return;
     }
@@ -766,6 +977,7 @@ fn spawn_dialogue(
     let mut preview_label = Entity::PLACEHOLDER;
     let mut preview_card_root = Entity::PLACEHOLDER;
     let mut preview_viewport = None;
+    let mut timeout_bar = None;
 
     commands.entity(root).with_children(|overlay| {
         overlay
@@ -785,6 +997,26 @@ fn spawn_dialogue(
                 theme::border(true),
             ))
             .with_children(|frame| {
+                if req.timeout.is_some() {
+                    timeout_bar = Some(
+                        frame
+                            .spawn((
+                                Name::new("Dialogue Timeout Bar"),
+                                DialogueTimeoutBar,
+                                Node {
+                                    position_type: PositionType::Absolute,
+                                    left: Val::Px(0.0),
+                                    bottom: Val::Px(0.0),
+                                    width: Val::Percent(100.0),
+                                    height: Val::Px(4.0),
+                                    ..default()
+                                },
+                                BackgroundColor(theme::TEXT_GLITCH),
+                            ))
+                            .id(),
+                    );
+                }
+
                 frame
                     .spawn((
                         Name::new("Dialogue Text Panel"),
@@ -1050,10 +1282,12 @@ fn spawn_dialogue(
         .entity(quick_actions_row)
         .with_children(|row| spawn_quick_action_buttons(row, fonts, &req.options));
 
-    let char_count = req.text.chars().count().max(1) as f32;
-    let speed = dialogue_speed.clamp(0.5, 2.0);
-    let char_interval =
-        (req.reveal_duration_secs.max(0.10) / char_count / speed).clamp(0.008, 0.070);
+    let markup = RatMarkup::lenient(&req.text);
+    let char_interval = reveal_char_interval(
+        &markup.glyphs,
+        req.reveal_duration_secs - markup.total_pause(),
+        dialogue_speed,
+    );
 
     DialogueSession {
         mode: req.mode,
@@ -1061,12 +1295,18 @@ fn spawn_dialogue(
         prompt_text,
         line_row,
         slot_text,
-        text_chars: req.text.chars().collect(),
+        glyphs: markup.glyphs,
         options: req.options,
         selected_option: 0,
         revealed: 0,
         reveal_timer: 0.0,
         char_interval,
+        voice: req.voice,
+        voice_wait: req.voice.map(|_| VOICE_START_TIMEOUT_SECS),
+        spoken: None,
+        skip_timer: None,
+        timeout: req.timeout,
+        timeout_bar,
         slot_anim_timer: 0.0,
         slot_anim_dir: 0.0,
         preview_viewport,
@@ -1428,10 +1668,10 @@ fn reveal_all_chars(
     commands
         .entity(session.line_row)
         .insert(ScrollPosition(Vec2::ZERO));
-    for ch in &session.text_chars {
-        spawn_char(commands, session.line_row, *ch, fonts);
+    for (index, glyph) in session.glyphs.iter().enumerate() {
+        spawn_char(commands, session.line_row, *glyph, index, fonts);
     }
-    session.revealed = session.text_chars.len();
+    session.revealed = session.glyphs.len();
 }
 
 fn cycle_option(commands: &mut Commands, session: &mut DialogueSession, dir: i32, fonts: &UiFonts) {
@@ -1620,7 +1860,7 @@ fn refresh_prompt(commands: &mut Commands, session: &DialogueSession) {
     }
 
     let has_quick = has_quick_actions(&session.options);
-    if session.revealed < session.text_chars.len() {
+    if session.revealed < session.glyphs.len() {
         update_prompt(commands, session.prompt_text, "e/enter/click: skip");
     } else if session.options.is_empty() {
         update_prompt(commands, session.prompt_text, "e/enter/click: continue");
@@ -1708,9 +1948,17 @@ fn apply_preview_to_right_panel(
     session.active_preview = Some(preview);
 }
 
-fn spawn_char(commands: &mut Commands, row: Entity, ch: char, fonts: &UiFonts) {
+fn spawn_char(
+    commands: &mut Commands,
+    row: Entity,
+    glyph: RatGlyph,
+    index: usize,
+    fonts: &UiFonts,
+) {
     const DIALOGUE_BODY_FONT_SIZE: f32 = 34.0;
+    const DIALOGUE_WHISPER_FONT_SIZE: f32 = 28.0;
 
+    let ch = glyph.ch;
     commands.entity(row).with_children(|line| {
         if ch == '\n' {
             line.spawn(Node {
@@ -1721,20 +1969,29 @@ fn spawn_char(commands: &mut Commands, row: Entity, ch: char, fonts: &UiFonts) {
             return;
         }
 
+        let (font_size, color) = if glyph.style.whisper {
+            (DIALOGUE_WHISPER_FONT_SIZE, theme::TEXT_WHISPER)
+        } else {
+            (DIALOGUE_BODY_FONT_SIZE, theme::TEXT_LIGHT)
+        };
         line.spawn((
             Node::default(),
             Text::new(ch.to_string()),
             TextFont {
                 font: fonts.body.clone(),
-                font_size: DIALOGUE_BODY_FONT_SIZE,
+                font_size,
                 ..default()
             },
-            TextColor(theme::TEXT_LIGHT),
+            TextColor(color),
             UiTransform::IDENTITY,
             DialogueGlyphFx {
                 age: 0.0,
                 duration: 0.13,
                 amplitude: 8.0,
+                shake: glyph.style.shake,
+                glitch: glyph.style.glitch.then_some(ch),
+                color,
+                seed: index as f32,
             },
         ));
     });
@@ -1830,6 +2087,9 @@ fn inventory_request_from_db(discovery_db: &UiDiscoveryDb) -> UiDialogueRequest
         preview,
         options,
         reveal_duration_secs: 0.0,
+        voice: None,
+        read: false,
+        timeout: None,
     }
 }
 
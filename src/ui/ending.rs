@@ -171,7 +171,7 @@ pub(super) fn apply_ending_commands(
         match msg {
             UiEndingCommand::Upsert(payload) => catalog.upsert(payload.clone()),
             UiEndingCommand::Remove { id } => catalog.remove(id),
-            UiEndingCommand::Show { id } =>
+            UiEndingCommand::Show { id } => {
                 if let Some(payload) = catalog.get(id).cloned() {
                     open_ending(
                         &mut commands,
@@ -181,7 +181,8 @@ pub(super) fn apply_ending_commands(
                         &children,
                         payload,
                     );
-                },
+                }
+            }
             UiEndingCommand::ShowPayload(payload) => {
                 open_ending(
                     &mut commands,
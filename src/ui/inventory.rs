@@ -125,7 +125,7 @@ pub(super) fn apply_inventory_commands(
 ) {
     for msg in msgs.read() {
         match msg {
-            UiInventoryCommand::Toggle =>
+            UiInventoryCommand::Toggle => {
                 if runtime.root.is_some() {
                     close_inventory(&mut commands, &mut runtime, &roots, &children);
                 } else {
@@ -139,7 +139,8 @@ pub(super) fn apply_inventory_commands(
                         &assets,
                         &mut images,
                     );
-                },
+                }
+            }
             UiInventoryCommand::Open => {
                 open_inventory(
                     &mut commands,
@@ -329,12 +330,13 @@ pub(super) fn handle_inventory_item_interactions(
                 hover_target = Some(slot.index);
                 *bg = BackgroundColor(Color::srgb(0.82, 0.82, 0.79));
             }
-            Interaction::None =>
+            Interaction::None => {
                 if !is_original && !is_dragging && runtime.selected != slot.index {
                     *bg = BackgroundColor(theme::BUTTON_BG);
                 } else if runtime.selected == slot.index {
                     *bg = BackgroundColor(Color::srgb(0.82, 0.82, 0.79));
-                },
+                }
+            }
         }
     }
 
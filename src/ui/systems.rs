@@ -813,7 +813,7 @@ pub(super) fn apply_discovery_commands(
             UiDiscoveryCommand::MoveItem { id, to_index } => {
                 db.move_item(id, *to_index);
             }
-            UiDiscoveryCommand::DropItem { id } =>
+            UiDiscoveryCommand::DropItem { id } => {
                 if let Some(entry) = db.drop_item(id) {
                     if let Some(model_path) = entry.model_path {
                         commands.write_message(SpawnDroppedItem {
@@ -821,10 +821,12 @@ pub(super) fn apply_discovery_commands(
                             model_path,
                         });
                     }
-                },
+                }
+            }
             UiDiscoveryCommand::ClearKind { kind } => db.clear_kind(*kind),
-            UiDiscoveryCommand::RecordInteraction { interaction } =>
-                db.record_interaction(interaction.clone()),
+            UiDiscoveryCommand::RecordInteraction { interaction } => {
+                db.record_interaction(interaction.clone())
+            }
             UiDiscoveryCommand::ReplaceAll { snapshot } => db.replace_all(snapshot.clone()),
         }
     }
@@ -947,7 +949,7 @@ pub(super) fn handle_button_interactions(
                             }
                         }
                     }
-                    ButtonAction::SelectDiscovery(kind, index) =>
+                    ButtonAction::SelectDiscovery(kind, index) => {
                         for mut state in &mut states {
                             if state.owner != owner.0 {
                                 continue;
@@ -962,7 +964,8 @@ pub(super) fn handle_button_interactions(
                                     state.selected_npc = Some(index);
                                 }
                             }
-                        },
+                        }
+                    }
                     ButtonAction::AdjustSetting(key, step) => {
                         settings.adjust(key, step);
                     }
@@ -978,18 +981,20 @@ pub(super) fn handle_button_interactions(
                     ButtonAction::OpenInventory => {
                         actions.write(UiMenuAction::OpenInventory(owner.0));
                     }
-                    ButtonAction::BackToMainMenu =>
+                    ButtonAction::BackToMainMenu => {
                         for mut confirm in &mut confirms {
                             if confirm.owner == owner.0 {
                                 confirm.pending = Some(ConfirmAction::BackToMainMenu);
                             }
-                        },
-                    ButtonAction::QuitGame =>
+                        }
+                    }
+                    ButtonAction::QuitGame => {
                         for mut confirm in &mut confirms {
                             if confirm.owner == owner.0 {
                                 confirm.pending = Some(ConfirmAction::QuitGame);
                             }
-                        },
+                        }
+                    }
                     ButtonAction::ConfirmProceed => {
                         let mut confirmed = None;
                         for mut confirm in &mut confirms {
@@ -1007,12 +1012,13 @@ pub(super) fn handle_button_interactions(
                             None => {}
                         }
                     }
-                    ButtonAction::ConfirmCancel =>
+                    ButtonAction::ConfirmCancel => {
                         for mut confirm in &mut confirms {
                             if confirm.owner == owner.0 {
                                 confirm.pending = None;
                             }
-                        },
+                        }
+                    }
                 }
             }
             Interaction::Hovered => {
@@ -1392,11 +1398,13 @@ pub(super) fn refresh_button_highlights(
         let active = match button.action {
             ButtonAction::SelectPage(page) => state.page == page,
             ButtonAction::SelectDiscovery(kind, index) => match kind {
-                DiscoveryKind::Item =>
+                DiscoveryKind::Item => {
                     state.page == MainMenuPage::DiscoveredItems
-                        && state.selected_item == Some(index),
-                DiscoveryKind::Npc =>
-                    state.page == MainMenuPage::PhoneList && state.selected_npc == Some(index),
+                        && state.selected_item == Some(index)
+                }
+                DiscoveryKind::Npc => {
+                    state.page == MainMenuPage::PhoneList && state.selected_npc == Some(index)
+                }
             },
             _ => false,
         };
@@ -9,6 +9,9 @@ pub(super) const PANEL_BG: Color = Color::srgb(0.70, 0.70, 0.66);
 pub(super) const PANEL_ALT: Color = Color::srgb(0.60, 0.60, 0.56);
 pub(super) const TEXT_DARK: Color = Color::srgb(0.05, 0.05, 0.05);
 pub(super) const TEXT_LIGHT: Color = Color::srgb(0.93, 0.94, 0.91);
+pub(super) const TEXT_WHISPER: Color = Color::srgb(0.62, 0.63, 0.66);
+pub(super) const TEXT_SEEN: Color = Color::srgb(0.55, 0.56, 0.54);
+pub(super) const TEXT_GLITCH: Color = Color::srgb(0.86, 0.22, 0.30);
 pub(super) const BUTTON_BG: Color = Color::srgb(0.73, 0.73, 0.69);
 pub(super) const BUTTON_HOVER: Color = Color::srgb(0.82, 0.82, 0.79);
 pub(super) const BUTTON_DISABLED: Color = Color::srgb(0.57, 0.57, 0.54);
@@ -1,21 +1,40 @@
 //! procedural formant tts for bevy 0.18
+//!
+//! lines are synthesized on the async compute pool, `VoiceStarted` goes out
+//! once the sampler actually plays one. finished lines stay in a small lru cache, and
+//! `PrewarmVoice` fills it ahead of time with lines that might come next.
+//! `Speak::streamed` lines skip all that and go to a `VoiceStreamNode`, which
+//! synthesizes on the audio thread as they play.
+//!
+//! while a synthesized line plays, each of its phonemes goes out as a
+//! `VoicePhoneme` when it's reached, counted from when the sampler picked
+//! the line up, for mouths and the typewriter
 
 pub mod phonetic;
+pub mod stream;
 pub mod synth;
 
-use std::num::NonZeroU32;
+use std::{collections::HashMap, num::NonZeroU32};
 
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+};
 use bevy_seedling::{
-    prelude::VolumeNode,
+    pool::Sampler,
+    prelude::{AudioEvents, Connect, RegisterNode, VolumeNode},
     sample::{AudioSample, PlaybackSettings, SamplePlayer},
 };
+pub use stream::{VoiceStreamEvent, VoiceStreamNode};
 pub use synth::{VoiceParams, VoicePreset};
 
 use crate::{
-    audio::mixer::WorldSfxPool,
+    audio::mixer::{WorldSfxBus, WorldSfxPool},
     settings::GameSettings,
-    voice::{phonetic::PhoneticMapper, synth::VoiceSynth},
+    voice::{
+        phonetic::PhoneticMapper,
+        synth::{PhonemeMark, VoiceCue, VoiceSynth},
+    },
 };
 
 pub struct VoicePlugin;
@@ -24,26 +43,222 @@ impl Plugin for VoicePlugin {
     fn build(&self, app: &mut App) {
         app.add_message::<Speak>()
             .add_message::<StopVoice>()
+            .add_message::<PrewarmVoice>()
+            .add_message::<VoiceStarted>()
+            .add_message::<VoicePhoneme>()
             .init_resource::<VoiceRuntime>()
-            .add_systems(Update, (handle_stop_voice_messages, handle_speak_messages));
+            .register_node::<VoiceStreamNode>()
+            .add_systems(Startup, spawn_voice_stream)
+            .add_systems(
+                Update,
+                (
+                    handle_stop_voice_messages,
+                    handle_speak_messages,
+                    handle_prewarm_messages,
+                    finish_voice_synthesis,
+                    emit_voice_phonemes,
+                )
+                    .chain(),
+            );
     }
 }
 
+/// lines kept synthesized, about a minute of speech
+const VOICE_CACHE_LINES: usize = 48;
+/// lines synthesized ahead of time at once, more `PrewarmVoice`s are ignored
+const VOICE_PREWARM_LIMIT: usize = 8;
+
+/// samples of a line and where each phoneme starts in them
+type VoiceTask = Task<(Vec<f32>, Vec<PhonemeMark>)>;
+
 #[derive(Resource, Default)]
 struct VoiceRuntime {
-    mapper: PhoneticMapper,
+    /// the line being synthesized, a newer `Speak` or a `StopVoice` drops it
+    pending: Option<PendingVoice>,
+    /// lines synthesized ahead of time, a `Speak` for one of them takes it over
+    warming: HashMap<VoiceKey, VoiceTask>,
+    cache: VoiceCache,
+    playing: Option<PlayingVoice>,
+}
+
+struct PendingVoice {
+    key: VoiceKey,
+    task: VoiceTask,
+    line: VoiceLine,
+}
+
+/// who says a line, how loud, and how many chars its text has
+#[derive(Clone, Copy)]
+struct VoiceLine {
+    id: Option<SpeakId>,
+    target: Option<Entity>,
+    volume: f32,
+    chars: usize,
+}
+
+/// a synthesized line, as played and as cached
+#[derive(Clone)]
+struct VoiceClip {
+    sample: Handle<AudioSample>,
+    duration_secs: f32,
+    timeline: Vec<PhonemeMark>,
+}
+
+/// the line playing, its phonemes go out as `VoicePhoneme`s on the way
+struct PlayingVoice {
+    line: VoiceLine,
+    /// the `SamplePlayer`, a pool sampler takes it up a frame or so later
+    player: Entity,
+    timeline: Vec<PhonemeMark>,
+    next: usize,
+    /// since the sampler started it, `None` until then
+    elapsed_secs: Option<f32>,
+    duration_secs: f32,
+}
+
+/// what a line sounds like: every run's text, pause and bent params. the
+/// volume is left out, it's applied in the mixer node
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct VoiceKey(Vec<(String, [u32; 8], phonetic::Language)>);
+
+impl VoiceKey {
+    fn new(segments: &[SpeakSegment], params: &VoiceParams) -> Self {
+        Self(
+            segments
+                .iter()
+                .map(|segment| {
+                    let params = segment.params(params);
+                    let bits = [
+                        segment.pause_before,
+                        params.pitch_hz,
+                        params.speed,
+                        params.breathiness,
+                        params.creepiness,
+                        params.whisper_mix,
+                        params.distortion,
+                        params.reverb_mix,
+                    ]
+                    .map(f32::to_bits);
+                    (segment.text.clone(), bits, params.language)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// synthesized lines, the least recently played goes first. a cached line
+/// replays the same breath and creak noise, for a repeated greeting that's fine
+#[derive(Default)]
+struct VoiceCache {
+    lines: HashMap<VoiceKey, CachedVoice>,
+    clock: u64,
+}
+
+struct CachedVoice {
+    clip: VoiceClip,
+    last_used: u64,
+}
+
+impl VoiceCache {
+    fn contains(&self, key: &VoiceKey) -> bool {
+        self.lines.contains_key(key)
+    }
+
+    fn get(&mut self, key: &VoiceKey) -> Option<VoiceClip> {
+        self.clock += 1;
+        let line = self.lines.get_mut(key)?;
+        line.last_used = self.clock;
+        Some(line.clip.clone())
+    }
+
+    fn insert(&mut self, key: VoiceKey, clip: VoiceClip) {
+        self.clock += 1;
+        self.lines.insert(
+            key,
+            CachedVoice {
+                clip,
+                last_used: self.clock,
+            },
+        );
+        while self.lines.len() > VOICE_CACHE_LINES {
+            let Some(oldest) = self
+                .lines
+                .iter()
+                .min_by_key(|(_, line)| line.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.lines.remove(&oldest);
+        }
+    }
 }
 
 #[derive(Message, Debug, Clone)]
 pub struct Speak {
     pub text: String,
+    /// passed on to its `VoiceStarted` and `VoicePhoneme`s, so a listener can
+    /// tell its line apart from whatever else plays
+    pub id: Option<SpeakId>,
     pub target: Option<Entity>,
     pub params: VoiceParams,
+    /// styled runs of `text`, empty speaks the whole text plainly
+    pub segments: Vec<SpeakSegment>,
+    /// synthesized as it plays instead of up front, see `Speak::streamed`
+    pub streamed: bool,
+}
+
+/// names one `Speak` line, handed out by whoever sends it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpeakId(pub u64);
+
+/// a run of speech with its own rate and texture, from ratspinner markup
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakSegment {
+    pub text: String,
+    /// silence before this run, in seconds
+    pub pause_before: f32,
+    pub speed: f32,
+    pub whisper: bool,
+    pub glitch: bool,
 }
 
 #[derive(Message, Debug, Clone, Copy, Default)]
 pub struct StopVoice;
 
+/// synthesizes a line that may be said soon, so its `Speak` plays right away.
+/// the target is ignored
+#[derive(Message, Debug, Clone)]
+pub struct PrewarmVoice(pub Speak);
+
+/// a `Speak` line started playing. synthesis runs off the main thread and
+/// the sampler picks the samples up later still, so this comes a few frames
+/// after the `Speak`
+#[derive(Message, Debug, Clone, Copy)]
+pub struct VoiceStarted {
+    /// the `Speak::id`
+    pub id: Option<SpeakId>,
+    /// who says it, the `Speak::target`
+    pub target: Option<Entity>,
+    /// how long the line plays, pauses included
+    pub duration_secs: f32,
+    /// `VoicePhoneme`s follow as it plays, streamed lines don't send any
+    pub timed: bool,
+}
+
+/// the playing line reached a phoneme. mouths open by `openness`, the
+/// typewriter shows the chars it was read from. once the line is out a last
+/// one closes the mouth with `char_index` past the end of the text
+#[derive(Message, Debug, Clone, Copy)]
+pub struct VoicePhoneme {
+    pub id: Option<SpeakId>,
+    pub target: Option<Entity>,
+    pub openness: f32,
+    /// first char of `Speak::text` it was read from
+    pub char_index: usize,
+    pub char_len: usize,
+}
+
 #[derive(Component)]
 struct VoicePlayback;
 
@@ -51,92 +266,411 @@ impl Speak {
     pub fn new(text: impl Into<String>) -> Self {
         Self {
             text: text.into(),
+            id: None,
             target: None,
             params: VoiceParams::default_english(),
+            segments: Vec::new(),
+            streamed: false,
         }
     }
 
+    pub fn id(mut self, id: SpeakId) -> Self {
+        self.id = Some(id);
+        self
+    }
+
     pub fn target(mut self, entity: Entity) -> Self {
         self.target = Some(entity);
         self
     }
 
-    #[allow(dead_code)]
     pub fn params(mut self, params: VoiceParams) -> Self {
         self.params = params;
         self
     }
 
-    pub fn voice(mut self, preset: VoicePreset) -> Self {
-        self.params = preset.params();
+    pub fn segments(mut self, segments: Vec<SpeakSegment>) -> Self {
+        self.segments = segments;
         self
     }
 
-    #[allow(dead_code)]
-    pub fn language(mut self, lang: phonetic::Language) -> Self {
-        self.params.language = lang;
+    /// starts at once on the `VoiceStreamNode` and can be bent while it plays.
+    /// streamed lines aren't cached and don't follow the target around
+    pub fn streamed(mut self) -> Self {
+        self.streamed = true;
         self
     }
+
+    /// the runs to synthesize and the params they bend
+    fn synthesis(&self) -> (Vec<SpeakSegment>, VoiceParams) {
+        let segments = if self.segments.is_empty() {
+            vec![SpeakSegment::new(self.text.clone())]
+        } else {
+            self.segments.clone()
+        };
+        let mut params = self.params.clone();
+        // keep synthesis headroom, final loudnes its applied in the mixer node
+        params.volume = 1.0;
+        (segments, params)
+    }
 }
 
+impl SpeakSegment {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            pause_before: 0.0,
+            speed: 1.0,
+            whisper: false,
+            glitch: false,
+        }
+    }
+
+    pub fn pause_before(mut self, secs: f32) -> Self {
+        self.pause_before = secs;
+        self
+    }
+
+    /// the base voice bent by this run's style
+    pub fn params(&self, base: &VoiceParams) -> VoiceParams {
+        let mut params = base.clone();
+        params.speed *= self.speed;
+        if self.whisper {
+            params.whisper_mix = params.whisper_mix.max(0.9);
+            params.breathiness = params.breathiness.max(0.7);
+        }
+        if self.glitch {
+            params.distortion = params.distortion.max(0.45);
+            params.creepiness = params.creepiness.max(0.9);
+        }
+        params
+    }
+}
+
+/// the one node streamed lines play on, there from the start so a line
+/// never waits on it
+fn spawn_voice_stream(mut commands: Commands) {
+    commands
+        .spawn((Name::new("voice_stream"), VoiceStreamNode::default()))
+        .connect(WorldSfxBus);
+}
+
+/// plays the newest line from the cache, or starts synthesizing it and
+/// `finish_voice_synthesis` plays it once ready
 fn handle_speak_messages(
     mut commands: Commands,
     mut runtime: ResMut<VoiceRuntime>,
     mut messages: MessageReader<Speak>,
-    mut sample_assets: ResMut<Assets<AudioSample>>,
+    mut started: MessageWriter<VoiceStarted>,
     settings: Res<GameSettings>,
     active_voice: Query<Entity, With<VoicePlayback>>,
+    mut streams: Query<(&mut VoiceStreamNode, &mut AudioEvents)>,
 ) {
-    for ev in messages.read() {
-        let text = ev.text.trim();
-        if text.is_empty() {
+    // keep a single active tts stream to prevent stacking/noise pileup
+    let Some(ev) = messages
+        .read()
+        .filter(|ev| !ev.text.trim().is_empty())
+        .last()
+    else {
+        return;
+    };
+    for entity in &active_voice {
+        commands.entity(entity).despawn();
+    }
+    runtime.playing = None;
+
+    let (segments, synth_params) = ev.synthesis();
+    if ev.streamed {
+        runtime.pending = None;
+        if let Ok((mut node, mut events)) = streams.single_mut() {
+            node.volume = voice_gain(ev.params.volume, &settings);
+            VoiceStreamEvent::Say(stream_cues(&segments, &synth_params)).send(&mut events);
+        }
+        started.write(VoiceStarted {
+            id: ev.id,
+            target: ev.target,
+            duration_secs: estimate_segments_duration_secs(&segments, &synth_params),
+            timed: false,
+        });
+        return;
+    }
+    // one voice at a time, a sampled line cuts off a streamed one
+    if let Ok((_, mut events)) = streams.single_mut() {
+        VoiceStreamEvent::Stop.send(&mut events);
+    }
+
+    let line = VoiceLine {
+        id: ev.id,
+        target: ev.target,
+        volume: ev.params.volume,
+        chars: segments
+            .iter()
+            .map(|segment| segment.text.chars().count())
+            .sum(),
+    };
+    let key = VoiceKey::new(&segments, &synth_params);
+    if let Some(clip) = runtime.cache.get(&key) {
+        runtime.pending = None;
+        runtime.playing = Some(play_voice(&mut commands, clip, line, &settings));
+        return;
+    }
+    let task = match runtime.warming.remove(&key) {
+        Some(task) => task,
+        None => spawn_synthesis(segments, synth_params),
+    };
+    // dropping the task of the line before throws its samples away
+    runtime.pending = Some(PendingVoice { key, task, line });
+}
+
+fn handle_prewarm_messages(
+    mut runtime: ResMut<VoiceRuntime>,
+    mut messages: MessageReader<PrewarmVoice>,
+) {
+    for PrewarmVoice(ev) in messages.read() {
+        if ev.streamed || ev.text.trim().is_empty() || runtime.warming.len() >= VOICE_PREWARM_LIMIT
+        {
+            continue;
+        }
+        let (segments, synth_params) = ev.synthesis();
+        let key = VoiceKey::new(&segments, &synth_params);
+        let pending = runtime
+            .pending
+            .as_ref()
+            .is_some_and(|pending| pending.key == key);
+        if pending || runtime.cache.contains(&key) || runtime.warming.contains_key(&key) {
             continue;
         }
+        let task = spawn_synthesis(segments, synth_params);
+        runtime.warming.insert(key, task);
+    }
+}
 
-        // keep a single active tts stream to prevent stacking/noise pileup
-        for entity in &active_voice {
-            commands.entity(entity).despawn();
+/// caches lines as their samples come in and plays the pending one
+fn finish_voice_synthesis(
+    mut commands: Commands,
+    mut runtime: ResMut<VoiceRuntime>,
+    mut sample_assets: ResMut<Assets<AudioSample>>,
+    settings: Res<GameSettings>,
+    targets: Query<(), With<GlobalTransform>>,
+) {
+    let VoiceRuntime {
+        pending,
+        warming,
+        cache,
+        playing,
+        ..
+    } = &mut *runtime;
+    warming.retain(|key, task| {
+        let Some((samples, timeline)) = block_on(future::poll_once(task)) else {
+            return true;
+        };
+        cache.insert(key.clone(), add_clip(&mut sample_assets, samples, timeline));
+        false
+    });
+
+    let Some((samples, timeline)) = pending
+        .as_mut()
+        .and_then(|pending| block_on(future::poll_once(&mut pending.task)))
+    else {
+        return;
+    };
+    let Some(pending) = pending.take() else {
+        return;
+    };
+    let clip = add_clip(&mut sample_assets, samples, timeline);
+    cache.insert(pending.key, clip.clone());
+    // the speaker left while their line was being made
+    if pending
+        .line
+        .target
+        .is_some_and(|target| !targets.contains(target))
+    {
+        return;
+    }
+    *playing = Some(play_voice(&mut commands, clip, pending.line, &settings));
+}
+
+/// says when the sampler starts the playing line, then sends its phonemes as
+/// their time comes
+fn emit_voice_phonemes(
+    time: Res<Time>,
+    mut runtime: ResMut<VoiceRuntime>,
+    mut started: MessageWriter<VoiceStarted>,
+    mut phonemes: MessageWriter<VoicePhoneme>,
+    players: Query<Option<&Sampler>, With<VoicePlayback>>,
+) {
+    let Some(playing) = runtime.playing.as_mut() else {
+        return;
+    };
+    let elapsed_secs = match (playing.elapsed_secs, players.get(playing.player)) {
+        (Some(elapsed_secs), _) => elapsed_secs + time.delta_secs(),
+        (None, Ok(Some(sampler))) if sampler.is_playing() => {
+            started.write(VoiceStarted {
+                id: playing.line.id,
+                target: playing.line.target,
+                duration_secs: playing.duration_secs,
+                timed: true,
+            });
+            0.0
+        }
+        (None, Ok(_)) => return,
+        // dropped before it got a sampler, close the mouth and move on
+        (None, Err(_)) => {
+            playing.next = playing.timeline.len();
+            playing.duration_secs
         }
+    };
+    playing.elapsed_secs = Some(elapsed_secs);
+    let reached = (elapsed_secs * synth::SAMPLE_RATE as f32) as usize;
+    while let Some(mark) = playing
+        .timeline
+        .get(playing.next)
+        .filter(|mark| mark.sample_offset <= reached)
+    {
+        phonemes.write(VoicePhoneme {
+            id: playing.line.id,
+            target: playing.line.target,
+            openness: mark.phoneme.openness(),
+            char_index: mark.source_index,
+            char_len: mark.phoneme.source_len as usize,
+        });
+        playing.next += 1;
+    }
+    if elapsed_secs < playing.duration_secs {
+        return;
+    }
+    phonemes.write(VoicePhoneme {
+        id: playing.line.id,
+        target: playing.line.target,
+        openness: 0.0,
+        char_index: playing.line.chars,
+        char_len: 0,
+    });
+    runtime.playing = None;
+}
 
-        // map -> synth -> sample asset
-        let runtime_params = ev.params.clone();
-        let phonemes = runtime
-            .mapper
-            .text_to_phonemes(text, runtime_params.language);
-        let mut synth_params = runtime_params.clone();
-        // keep synthesis headroom, final loudnes its applied in the mixer node
-        synth_params.volume = 1.0;
-        let mut synth = VoiceSynth::new(synth_params);
-        let samples = synth.synthesize(&phonemes);
-        let sample_rate =
-            NonZeroU32::new(synth::SAMPLE_RATE).expect("SAMPLE_RATE must be non-zero");
-        let sample = AudioSample::new(vec![samples], sample_rate);
+fn spawn_synthesis(segments: Vec<SpeakSegment>, params: VoiceParams) -> VoiceTask {
+    AsyncComputeTaskPool::get().spawn(async move { synthesize_segments(&segments, &params) })
+}
+
+/// the samples as an asset, with how long they play
+fn add_clip(
+    assets: &mut Assets<AudioSample>,
+    samples: Vec<f32>,
+    timeline: Vec<PhonemeMark>,
+) -> VoiceClip {
+    let duration_secs = samples.len() as f32 / synth::SAMPLE_RATE as f32;
+    let sample_rate = NonZeroU32::new(synth::SAMPLE_RATE).expect("SAMPLE_RATE must be non-zero");
+    VoiceClip {
+        sample: assets.add(AudioSample::new(vec![samples], sample_rate)),
+        duration_secs,
+        timeline,
+    }
+}
+
+/// a line's own volume under the voice volume setting
+fn voice_gain(volume: f32, settings: &GameSettings) -> f32 {
+    (volume * settings.voice_volume.clamp(0.0, 1.5)).clamp(0.0, 2.0)
+}
+
+/// spawns the playback, the result tracks its phonemes once it starts
+fn play_voice(
+    commands: &mut Commands,
+    clip: VoiceClip,
+    line: VoiceLine,
+    settings: &GameSettings,
+) -> PlayingVoice {
+    // spawn playback entity
+    let mut e = commands.spawn((
+        Name::new("voice_tts"),
+        VoicePlayback,
+        SamplePlayer::new(clip.sample),
+        WorldSfxPool,
+        VolumeNode::from_linear(voice_gain(line.volume, settings)),
+        PlaybackSettings::default().despawn(),
+    ));
 
-        let handle = sample_assets.add(sample);
+    // if target exists, parent the playback so it follow the npc transform
+    if let Some(target) = line.target {
+        e.insert(ChildOf(target));
+    }
+    PlayingVoice {
+        line,
+        player: e.id(),
+        timeline: clip.timeline,
+        next: 0,
+        elapsed_secs: None,
+        duration_secs: clip.duration_secs,
+    }
+}
 
-        // spawn playback entity
-        let mut e = commands.spawn((
-            Name::new("voice_tts"),
-            VoicePlayback,
-            SamplePlayer::new(handle),
-            WorldSfxPool,
-            VolumeNode::from_linear(
-                (runtime_params.volume * settings.voice_volume.clamp(0.0, 1.5)).clamp(0.0, 2.0),
-            ),
-            PlaybackSettings::default().despawn(),
-        ));
+/// map -> synth for each run, pauses as silence in between. the timeline
+/// counts chars across all runs, like `Speak::text`
+fn synthesize_segments(
+    segments: &[SpeakSegment],
+    params: &VoiceParams,
+) -> (Vec<f32>, Vec<PhonemeMark>) {
+    let mut mapper = PhoneticMapper::default();
+    let mut samples = Vec::new();
+    let mut timeline = Vec::new();
+    let mut chars = 0;
+    for segment in segments {
+        let silence = (segment.pause_before.max(0.0) * synth::SAMPLE_RATE as f32) as usize;
+        samples.resize(samples.len() + silence, 0.0);
+        let text = segment.text.trim();
+        if !text.is_empty() {
+            let leading = segment
+                .text
+                .chars()
+                .take_while(|ch| ch.is_whitespace())
+                .count();
+            let (run, marks) = synthesize_text(&mut mapper, text, &segment.params(params));
+            timeline.extend(marks.into_iter().map(|mark| PhonemeMark {
+                sample_offset: samples.len() + mark.sample_offset,
+                source_index: chars + leading + mark.source_index,
+                ..mark
+            }));
+            samples.extend(run);
+        }
+        chars += segment.text.chars().count();
+    }
+    (samples, timeline)
+}
 
-        // if target exists, parent the playback so it follow the npc transform
-        if let Some(target) = ev.target {
-            e.insert(ChildOf(target));
+/// the runs as cues for a stream node, each switching to its own params
+fn stream_cues(segments: &[SpeakSegment], params: &VoiceParams) -> Vec<VoiceCue> {
+    let mut mapper = PhoneticMapper::default();
+    let mut cues = Vec::new();
+    for segment in segments {
+        if segment.pause_before > 0.0 {
+            cues.push(VoiceCue::Silence(segment.pause_before));
+        }
+        let text = segment.text.trim();
+        if !text.is_empty() {
+            let params = segment.params(params);
+            let phonemes = mapper.text_to_phonemes(text, params.language);
+            cues.push(VoiceCue::Params(params));
+            cues.extend(phonemes.into_iter().map(VoiceCue::Phoneme));
         }
     }
+    cues
+}
+
+fn synthesize_text(
+    mapper: &mut PhoneticMapper,
+    text: &str,
+    params: &VoiceParams,
+) -> (Vec<f32>, Vec<PhonemeMark>) {
+    let phonemes = mapper.text_to_phonemes(text, params.language);
+    VoiceSynth::new(params.clone()).synthesize(&phonemes)
 }
 
 fn handle_stop_voice_messages(
     mut commands: Commands,
+    mut runtime: ResMut<VoiceRuntime>,
     mut messages: MessageReader<StopVoice>,
     active_voice: Query<Entity, With<VoicePlayback>>,
+    mut streams: Query<&mut AudioEvents, With<VoiceStreamNode>>,
 ) {
     let mut any = false;
     for _ in messages.read() {
@@ -145,14 +679,265 @@ fn handle_stop_voice_messages(
     if !any {
         return;
     }
+    runtime.pending = None;
+    runtime.playing = None;
+    for mut events in &mut streams {
+        VoiceStreamEvent::Stop.send(&mut events);
+    }
     for entity in &active_voice {
         commands.entity(entity).despawn();
     }
 }
 
-pub fn estimate_speech_duration_secs(text: &str, preset: VoicePreset) -> f32 {
-    let params = preset.params();
+pub fn estimate_speech_duration_secs(text: &str, params: &VoiceParams) -> f32 {
     let mut mapper = PhoneticMapper::default();
     let phonemes = mapper.text_to_phonemes(text, params.language);
-    synth::estimate_duration_secs(&phonemes, &params)
+    synth::estimate_duration_secs(&phonemes, params)
+}
+
+/// like `estimate_speech_duration_secs`, honoring each run's speed and pause
+pub fn estimate_segments_duration_secs(segments: &[SpeakSegment], params: &VoiceParams) -> f32 {
+    segments
+        .iter()
+        .map(|segment| {
+            let text = segment.text.trim();
+            let speech = if text.is_empty() {
+                0.0
+            } else {
+                estimate_speech_duration_secs(text, &segment.params(params))
+            };
+            segment.pause_before.max(0.0) + speech
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ratspinner::RatMarkup,
+        voice::phonetic::{Language, Phoneme},
+    };
+
+    fn clip() -> VoiceClip {
+        VoiceClip {
+            sample: Handle::default(),
+            duration_secs: 1.0,
+            timeline: Vec::new(),
+        }
+    }
+
+    fn key(text: &str) -> VoiceKey {
+        VoiceKey::new(&[SpeakSegment::new(text)], &VoiceParams::default_english())
+    }
+
+    /// the voice systems that pick what to synthesize, nothing plays
+    fn voice_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<VoiceRuntime>()
+            .init_resource::<GameSettings>()
+            .add_message::<Speak>()
+            .add_message::<PrewarmVoice>()
+            .add_message::<VoiceStarted>()
+            .add_systems(
+                Update,
+                (handle_speak_messages, handle_prewarm_messages).chain(),
+            );
+        app
+    }
+
+    #[test]
+    fn phonemes_point_at_the_glyphs_they_were_read_from() {
+        let markup = RatMarkup::parse(
+            "{whisper}Über{/whisper} the café, {pause=0.3}İstanbul? {glitch}naïve{/glitch} ok",
+        )
+        .expect("the line should parse");
+        let glyph = |index: usize| {
+            markup.glyphs[index]
+                .ch
+                .to_lowercase()
+                .next()
+                .expect("a char lowercases to at least one")
+        };
+
+        let plain = PhoneticMapper::default().text_to_phonemes(&markup.plain(), Language::English);
+        let (_, timeline) =
+            synthesize_segments(&markup.speak_segments(), &VoiceParams::default_english());
+        assert!(!timeline.is_empty());
+        let read = |phoneme: &Phoneme| phoneme.source_len > 0;
+        assert_eq!(
+            plain.iter().filter(|p| read(p)).count(),
+            timeline.iter().filter(|mark| read(&mark.phoneme)).count()
+        );
+        for mark in timeline.iter().filter(|mark| read(&mark.phoneme)) {
+            let phoneme = &mark.phoneme;
+            for (offset, ch) in phoneme.source_chars[..phoneme.source_len as usize]
+                .iter()
+                .enumerate()
+            {
+                assert_eq!(glyph(mark.source_index + offset), *ch, "{mark:?}");
+            }
+        }
+        for phoneme in plain.iter().filter(|p| read(p)) {
+            assert_eq!(glyph(phoneme.source_index), phoneme.source_chars[0]);
+        }
+    }
+
+    #[test]
+    fn keys_follow_the_sound_not_the_volume() {
+        let params = VoiceParams::default_english();
+        let mut loud = params.clone();
+        loud.volume = 2.0;
+        let plain = [SpeakSegment::new("hello")];
+        assert_eq!(VoiceKey::new(&plain, &params), VoiceKey::new(&plain, &loud));
+
+        let whispered = [SpeakSegment {
+            whisper: true,
+            ..SpeakSegment::new("hello")
+        }];
+        let paused = [SpeakSegment::new("hello").pause_before(0.5)];
+        let split = [SpeakSegment::new("hel"), SpeakSegment::new("lo")];
+        let mut deeper = params.clone();
+        deeper.pitch_hz *= 0.5;
+        let base = VoiceKey::new(&plain, &params);
+        for other in [
+            VoiceKey::new(&whispered, &params),
+            VoiceKey::new(&paused, &params),
+            VoiceKey::new(&split, &params),
+            VoiceKey::new(&plain, &deeper),
+        ] {
+            assert_ne!(base, other);
+        }
+    }
+
+    #[test]
+    fn the_cache_drops_the_line_played_longest_ago() {
+        let mut cache = VoiceCache::default();
+        for n in 0..VOICE_CACHE_LINES {
+            cache.insert(key(&format!("line {n}")), clip());
+        }
+        // played again, the second line is now the oldest
+        assert!(cache.get(&key("line 0")).is_some());
+        cache.insert(key("one more"), clip());
+
+        assert_eq!(cache.lines.len(), VOICE_CACHE_LINES);
+        assert!(cache.contains(&key("line 0")));
+        assert!(!cache.contains(&key("line 1")));
+        assert!(cache.contains(&key("line 2")));
+        assert!(cache.contains(&key("one more")));
+        assert!(cache.get(&key("line 1")).is_none());
+    }
+
+    #[test]
+    fn prewarm_skips_known_streamed_and_blank_lines() {
+        let mut app = voice_app();
+        app.world_mut()
+            .resource_mut::<VoiceRuntime>()
+            .cache
+            .insert(key("cached"), clip());
+        for speak in [
+            Speak::new("hello"),
+            Speak::new("hello"),
+            Speak::new("cached"),
+            Speak::new("streamed").streamed(),
+            Speak::new("   "),
+        ] {
+            app.world_mut().write_message(PrewarmVoice(speak));
+        }
+        app.update();
+
+        let runtime = app.world().resource::<VoiceRuntime>();
+        assert_eq!(runtime.warming.len(), 1);
+        assert!(runtime.warming.contains_key(&key("hello")));
+    }
+
+    #[test]
+    fn prewarm_stops_at_its_limit() {
+        let mut app = voice_app();
+        for n in 0..VOICE_PREWARM_LIMIT + 3 {
+            app.world_mut()
+                .write_message(PrewarmVoice(Speak::new(format!("line {n}"))));
+        }
+        app.update();
+        let runtime = app.world().resource::<VoiceRuntime>();
+        assert_eq!(runtime.warming.len(), VOICE_PREWARM_LIMIT);
+    }
+
+    #[test]
+    fn phonemes_wait_for_the_sampler() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<VoiceRuntime>()
+            .add_message::<VoiceStarted>()
+            .add_message::<VoicePhoneme>()
+            .add_systems(Update, emit_voice_phonemes);
+        let player = app.world_mut().spawn(VoicePlayback).id();
+        let (_, timeline) = synthesize_segments(
+            &[SpeakSegment::new("hello")],
+            &VoiceParams::default_english(),
+        );
+        app.world_mut().resource_mut::<VoiceRuntime>().playing = Some(PlayingVoice {
+            line: VoiceLine {
+                id: Some(SpeakId(7)),
+                target: None,
+                volume: 1.0,
+                chars: 5,
+            },
+            player,
+            timeline,
+            next: 0,
+            elapsed_secs: None,
+            duration_secs: 0.0,
+        });
+        let sent = |app: &App| {
+            let started = app
+                .world()
+                .resource::<bevy::ecs::message::Messages<VoiceStarted>>();
+            let phonemes = app
+                .world()
+                .resource::<bevy::ecs::message::Messages<VoicePhoneme>>();
+            (
+                started.get_cursor().read(started).count(),
+                phonemes
+                    .get_cursor()
+                    .read(phonemes)
+                    .map(|phoneme| (phoneme.id, phoneme.char_index))
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        // no sampler has it yet, so nothing is said
+        app.update();
+        assert_eq!(sent(&app), (0, Vec::new()));
+
+        // a player dropped unheard closes the mouth without reading anything
+        app.world_mut().despawn(player);
+        app.update();
+        assert_eq!(sent(&app), (0, vec![(Some(SpeakId(7)), 5)]));
+        assert!(app.world().resource::<VoiceRuntime>().playing.is_none());
+    }
+
+    #[test]
+    fn speaking_a_warming_line_takes_its_task_over() {
+        let mut app = voice_app();
+        app.world_mut()
+            .write_message(PrewarmVoice(Speak::new("see you")));
+        app.update();
+        assert!(
+            app.world()
+                .resource::<VoiceRuntime>()
+                .warming
+                .contains_key(&key("see you"))
+        );
+
+        app.world_mut().write_message(Speak::new("see you"));
+        app.update();
+        let runtime = app.world().resource::<VoiceRuntime>();
+        assert!(runtime.warming.is_empty());
+        assert_eq!(
+            runtime.pending.as_ref().map(|pending| &pending.key),
+            Some(&key("see you"))
+        );
+    }
 }
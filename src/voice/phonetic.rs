@@ -1,7 +1,7 @@
 use rand::RngExt;
 
 /// primary language selection.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Language {
     English,
     #[allow(dead_code)]
@@ -39,14 +39,32 @@ pub struct Phoneme {
     pub stressed: bool,
     #[allow(dead_code)]
     pub source_chars: [char; 2], // small & cheap (up to digraph)
-    #[allow(dead_code)]
     pub source_len: u8, // 1 or 2
-    #[allow(dead_code)]
     pub source_index: usize,
     pub pitch_mod: f32,
 }
 
 impl Phoneme {
+    /// how far a mouth saying this is open, 0 shut to 1 wide
+    pub fn openness(&self) -> f32 {
+        match self.ty {
+            PhonemeType::Pause | PhonemeType::Breath => 0.0,
+            // a higher first formant is a lower jaw
+            PhonemeType::Vowel => self
+                .formants
+                .map_or(0.6, |f| ((f[0] - 250.0) / 600.0).clamp(0.25, 1.0)),
+            PhonemeType::Consonant => match self.consonant {
+                Some(
+                    ConsonantClass::PlosiveVoiced
+                    | ConsonantClass::PlosiveUnvoiced
+                    | ConsonantClass::Nasal,
+                ) => 0.05,
+                Some(ConsonantClass::Liquid | ConsonantClass::Lateral) => 0.35,
+                _ => 0.2,
+            },
+        }
+    }
+
     fn pause(source_index: usize, dur: f32) -> Self {
         Self {
             ty: PhonemeType::Pause,
@@ -230,8 +248,11 @@ fn text_to_phonemes_english(text: &str) -> Vec<Phoneme> {
             while i < chars.len() && (chars[i].is_alphabetic() || chars[i] == '\'') {
                 i += 1;
             }
-            let word: String = chars[start..i].iter().collect();
-            let lower = word.to_lowercase();
+            // a char each, `İ` lowercases to two and would shift the indices
+            let lower: String = chars[start..i]
+                .iter()
+                .map(|ch| ch.to_lowercase().next().unwrap_or(*ch))
+                .collect();
 
             // prosody curve across sentence-ish: slight rise in questions is handled by '?'
             // already, here we do a gentle declination across words.
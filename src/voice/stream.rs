@@ -0,0 +1,160 @@
+//! the formant synth as a firewheel node, speaking phonemes as the audio
+//! thread asks for samples
+//!
+//! lines go in as `VoiceStreamEvent`s on the node's `AudioEvents`, the
+//! node's fields bend the voice live: a hostile entity can glitch halfway
+//! through a word by raising `distortion` on its `VoiceStreamNode`.
+
+use bevy::prelude::*;
+use bevy_seedling::{
+    firewheel::{
+        channel_config::{ChannelConfig, ChannelCount},
+        diff::{Diff, Patch},
+        event::{NodeEventType, ProcEvents},
+        node::{
+            AudioNode, AudioNodeInfo, AudioNodeProcessor, ConstructProcessorContext, EmptyConfig,
+            ProcBuffers, ProcExtra, ProcInfo, ProcessStatus,
+        },
+    },
+    prelude::AudioEvents,
+};
+
+use super::synth::{SAMPLE_RATE, VoiceBend, VoiceCue, VoiceParams, VoiceStream};
+
+/// live changes on top of whatever line is streaming
+#[derive(Diff, Patch, Debug, Clone, Copy, PartialEq, Component)]
+pub struct VoiceStreamNode {
+    pub pitch_scale: f32,
+    /// added to the line's creepiness
+    pub creepiness: f32,
+    /// added to the line's distortion
+    pub distortion: f32,
+    pub volume: f32,
+}
+
+impl Default for VoiceStreamNode {
+    fn default() -> Self {
+        Self {
+            pitch_scale: 1.0,
+            creepiness: 0.0,
+            distortion: 0.0,
+            volume: 1.0,
+        }
+    }
+}
+
+impl VoiceStreamNode {
+    fn bend(&self) -> VoiceBend {
+        VoiceBend {
+            pitch_scale: self.pitch_scale,
+            creepiness: self.creepiness,
+            distortion: self.distortion,
+        }
+    }
+}
+
+/// what a stream node is told, sent as a custom node event
+#[derive(Debug)]
+pub enum VoiceStreamEvent {
+    /// cuts off the line playing and starts on these
+    Say(Vec<VoiceCue>),
+    /// cuts off the line playing, mid-word if need be
+    Stop,
+}
+
+impl VoiceStreamEvent {
+    pub fn send(self, events: &mut AudioEvents) {
+        events.push(NodeEventType::custom(self));
+    }
+}
+
+impl AudioNode for VoiceStreamNode {
+    type Configuration = EmptyConfig;
+
+    fn info(&self, _config: &Self::Configuration) -> AudioNodeInfo {
+        AudioNodeInfo::new()
+            .debug_name("voice_stream")
+            .channel_config(ChannelConfig {
+                num_inputs: ChannelCount::ZERO,
+                num_outputs: ChannelCount::STEREO,
+            })
+    }
+
+    fn construct_processor(
+        &self,
+        _config: &Self::Configuration,
+        cx: ConstructProcessorContext,
+    ) -> impl AudioNodeProcessor {
+        let mut stream = VoiceStream::new(VoiceParams::default_english());
+        stream.set_bend(self.bend());
+        VoiceStreamProcessor {
+            node: *self,
+            stream,
+            step: SAMPLE_RATE as f32 / cx.stream_info.sample_rate.get() as f32,
+            phase: 0.0,
+            prev: 0.0,
+            next: 0.0,
+        }
+    }
+}
+
+struct VoiceStreamProcessor {
+    node: VoiceStreamNode,
+    stream: VoiceStream,
+    // the synth runs at `SAMPLE_RATE`, the device may not
+    step: f32,
+    phase: f32,
+    prev: f32,
+    next: f32,
+}
+
+impl AudioNodeProcessor for VoiceStreamProcessor {
+    fn process(
+        &mut self,
+        info: &ProcInfo,
+        buffers: ProcBuffers,
+        events: &mut ProcEvents,
+        _extra: &mut ProcExtra,
+    ) -> ProcessStatus {
+        for mut event in events.drain() {
+            if let Some(patch) = VoiceStreamNode::patch_event(&event) {
+                self.node.apply(patch);
+                self.stream.set_bend(self.node.bend());
+                continue;
+            }
+            let NodeEventType::Custom(custom) = &mut event else {
+                continue;
+            };
+            // the line swapped out rides the event back, it's freed off the
+            // audio thread along with it
+            match custom.downcast_mut::<VoiceStreamEvent>() {
+                Some(VoiceStreamEvent::Say(cues)) => self.stream.say(cues),
+                Some(VoiceStreamEvent::Stop) => self.stream.stop(),
+                None => {}
+            }
+        }
+
+        if !self.stream.is_active() {
+            self.prev = 0.0;
+            self.next = 0.0;
+            return ProcessStatus::ClearAllOutputs;
+        }
+
+        let (left, right) = buffers.outputs.split_at_mut(1);
+        let frames = left[0][..info.frames]
+            .iter_mut()
+            .zip(&mut right[0][..info.frames]);
+        for (out_left, out_right) in frames {
+            while self.phase >= 1.0 {
+                self.phase -= 1.0;
+                self.prev = self.next;
+                self.next = self.stream.next_sample();
+            }
+            let sample = (self.prev + (self.next - self.prev) * self.phase) * self.node.volume;
+            *out_left = sample;
+            *out_right = sample;
+            self.phase += self.step;
+        }
+        ProcessStatus::OutputsModified
+    }
+}
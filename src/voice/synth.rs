@@ -1,10 +1,14 @@
 use std::{f32::consts::PI, str::FromStr};
 
-use rand::RngExt;
+use rand::{RngExt, SeedableRng, rngs::StdRng};
 
 use super::phonetic::{ConsonantClass, Language, Phoneme, PhonemeType};
 
 pub const SAMPLE_RATE: u32 = 44_100;
+const JOIN_FADE_SAMPLES: usize = 192;
+/// no phoneme is held longer, however slow the voice, so a stream can size
+/// its buffers up front
+const MAX_PHONEME_SECS: f32 = 1.0;
 
 #[derive(Debug, Clone)]
 pub struct VoiceParams {
@@ -36,7 +40,7 @@ impl VoiceParams {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VoicePreset {
     HostileEntity,
     LostChild,
@@ -45,6 +49,16 @@ pub enum VoicePreset {
 }
 
 impl VoicePreset {
+    /// the name `voice:` lines and `from_str` take
+    pub fn name(self) -> &'static str {
+        match self {
+            VoicePreset::HostileEntity => "hostile_entity",
+            VoicePreset::LostChild => "lost_child",
+            VoicePreset::CorruptedTransmission => "corrupted_transmission",
+            VoicePreset::NeutralNpc => "neutral_npc",
+        }
+    }
+
     pub fn params(self) -> VoiceParams {
         match self {
             VoicePreset::HostileEntity => VoiceParams {
@@ -94,16 +108,26 @@ impl FromStr for VoicePreset {
             "lost_child" | "child" => Ok(VoicePreset::LostChild),
             "corrupted_transmission" | "glitch" => Ok(VoicePreset::CorruptedTransmission),
             "neutral_npc" | "neutral" | "npc" => Ok(VoicePreset::NeutralNpc),
-            _ => Ok(VoicePreset::NeutralNpc),
+            _ => Err(()),
         }
     }
 }
 
 // --------- synthesis core ---------
 
+/// where a phoneme starts in a synthesized line
+#[derive(Debug, Clone)]
+pub struct PhonemeMark {
+    pub sample_offset: usize,
+    pub phoneme: Phoneme,
+    /// char of the synthesized text it was read from
+    pub source_index: usize,
+}
+
 pub struct VoiceSynth {
     params: VoiceParams,
-    rng: rand::rngs::ThreadRng,
+    // seeded once so the synth can move to the audio thread
+    rng: StdRng,
 
     // state for continuity
     glottal_phase: f32,
@@ -127,7 +151,7 @@ impl VoiceSynth {
     pub fn new(params: VoiceParams) -> Self {
         Self {
             params,
-            rng: rand::rng(),
+            rng: StdRng::from_rng(&mut rand::rng()),
             glottal_phase: 0.0,
             prev_formants: None,
             pitch_drift_hz: 0.0,
@@ -146,50 +170,25 @@ impl VoiceSynth {
         self.f3.reset_state();
     }
 
-    pub fn synthesize(&mut self, phonemes: &[Phoneme]) -> Vec<f32> {
+    /// the line's samples and where each phoneme starts in them
+    pub fn synthesize(&mut self, phonemes: &[Phoneme]) -> (Vec<f32>, Vec<PhonemeMark>) {
         let mut samples: Vec<f32> = Vec::new();
-        const JOIN_FADE_SAMPLES: usize = 192;
-
+        let mut timeline = Vec::with_capacity(phonemes.len());
+        let mut seg = Vec::new();
         for (idx, p) in phonemes.iter().enumerate() {
-            match p.ty {
-                PhonemeType::Pause | PhonemeType::Breath => {
-                    self.clear_filters();
-                    let duration_mod = if matches!(p.ty, PhonemeType::Breath) {
-                        0.5
-                    } else {
-                        1.0
-                    };
-                    let dur_s = (0.080 * p.duration * duration_mod) / self.params.speed.max(0.05);
-                    let mut seg = Vec::new();
-                    push_silence(&mut seg, dur_s);
-                    append_with_crossfade(&mut samples, &seg, JOIN_FADE_SAMPLES);
-                }
-                PhonemeType::Vowel => {
-                    let dur_s =
-                        (if p.stressed { 0.095 } else { 0.070 }) * p.duration / self.params.speed;
-                    let next = lookahead_formants(phonemes, idx);
-                    let mut seg = self.synth_vowel(p, dur_s, next);
-                    apply_segment_edges(&mut seg, 36);
-                    append_with_crossfade(&mut samples, &seg, JOIN_FADE_SAMPLES);
-                    self.prev_formants = p.formants;
-                }
-                PhonemeType::Consonant => {
-                    self.clear_filters();
-                    let dur_s = consonant_duration(
-                        p.consonant.unwrap_or(ConsonantClass::FricativeUnvoiced),
-                        p.duration,
-                    ) / self.params.speed.max(0.05);
-                    let next = lookahead_formants(phonemes, idx);
-                    let mut seg = self.synth_consonant(p, dur_s, next);
-                    apply_segment_edges(&mut seg, 24);
-                    append_with_crossfade(&mut samples, &seg, JOIN_FADE_SAMPLES);
-                }
-            }
+            self.render(p, lookahead_formants(&phonemes[idx + 1..]), &mut seg);
+            // the crossfade pulls the phoneme back into the one before
+            let overlap = JOIN_FADE_SAMPLES.min(samples.len()).min(seg.len());
+            timeline.push(PhonemeMark {
+                sample_offset: samples.len() - overlap,
+                phoneme: p.clone(),
+                source_index: p.source_index,
+            });
+            append_with_crossfade(&mut samples, &seg, JOIN_FADE_SAMPLES);
         }
 
         // add a tail so effects do not end abruptly.
-        let tail_s = 0.10 + self.params.reverb_mix.clamp(0.0, 1.0) * 0.14;
-        push_silence(&mut samples, tail_s);
+        push_silence(&mut samples, self.tail_secs());
 
         // effects: distortion + reverb mix
         if self.params.distortion > 0.02 {
@@ -208,12 +207,51 @@ impl VoiceSynth {
         normalize(&mut samples, self.params.volume);
         apply_soft_limiter(&mut samples, 1.05);
         clamp_peak(&mut samples, 0.98);
-        samples
+        (samples, timeline)
     }
 
-    fn synth_vowel(&mut self, p: &Phoneme, dur_s: f32, next: Option<[f32; 3]>) -> Vec<f32> {
+    /// one phoneme with its edges smoothed into `out`, `next` being the
+    /// formants of the vowel coming up
+    fn render(&mut self, p: &Phoneme, next: Option<[f32; 3]>, out: &mut Vec<f32>) {
+        out.clear();
+        match p.ty {
+            PhonemeType::Pause | PhonemeType::Breath => {
+                self.clear_filters();
+                let duration_mod = if matches!(p.ty, PhonemeType::Breath) {
+                    0.5
+                } else {
+                    1.0
+                };
+                let dur_s = (0.080 * p.duration * duration_mod) / self.params.speed.max(0.05);
+                push_silence(out, dur_s.min(MAX_PHONEME_SECS));
+            }
+            PhonemeType::Vowel => {
+                let dur_s =
+                    (if p.stressed { 0.095 } else { 0.070 }) * p.duration / self.params.speed;
+                self.synth_vowel(p, dur_s.min(MAX_PHONEME_SECS), next, out);
+                apply_segment_edges(out, 36);
+                self.prev_formants = p.formants;
+            }
+            PhonemeType::Consonant => {
+                self.clear_filters();
+                let dur_s = consonant_duration(
+                    p.consonant.unwrap_or(ConsonantClass::FricativeUnvoiced),
+                    p.duration,
+                ) / self.params.speed.max(0.05);
+                self.synth_consonant(p, dur_s.min(MAX_PHONEME_SECS), next, out);
+                apply_segment_edges(out, 24);
+            }
+        }
+    }
+
+    /// silence left after the last phoneme so the reverb rings out
+    fn tail_secs(&self) -> f32 {
+        0.10 + self.params.reverb_mix.clamp(0.0, 1.0) * 0.14
+    }
+
+    fn synth_vowel(&mut self, p: &Phoneme, dur_s: f32, next: Option<[f32; 3]>, out: &mut Vec<f32>) {
         let n = (dur_s * SAMPLE_RATE as f32).max(1.0) as usize;
-        let mut out = Vec::with_capacity(n);
+        out.reserve(n);
 
         let target = p.formants.unwrap_or([500.0, 1500.0, 2500.0]);
 
@@ -293,13 +331,17 @@ impl VoiceSynth {
             let env = adsr(t, 0.08, 0.0, 1.0, 0.15);
             out.push((y1 + y2 + y3 + wy) * env);
         }
-
-        out
     }
 
-    fn synth_consonant(&mut self, p: &Phoneme, dur_s: f32, next: Option<[f32; 3]>) -> Vec<f32> {
+    fn synth_consonant(
+        &mut self,
+        p: &Phoneme,
+        dur_s: f32,
+        next: Option<[f32; 3]>,
+        out: &mut Vec<f32>,
+    ) {
         let n = (dur_s * SAMPLE_RATE as f32).max(1.0) as usize;
-        let mut out = Vec::with_capacity(n);
+        out.reserve(n);
         let class = p.consonant.unwrap_or(ConsonantClass::FricativeUnvoiced);
         let noise_amp = 0.3f32;
 
@@ -443,8 +485,6 @@ impl VoiceSynth {
                 }
             }
         }
-
-        out
     }
 
     /// rosenberg-ish glottal pulse: cheap but effective for "voicey" source.
@@ -481,15 +521,12 @@ impl VoiceSynth {
     }
 }
 
-fn lookahead_formants(phonemes: &[Phoneme], idx: usize) -> Option<[f32; 3]> {
-    for j in (idx + 1)..phonemes.len().min(idx + 4) {
-        if phonemes[j].ty == PhonemeType::Vowel {
-            if let Some(f) = phonemes[j].formants {
-                return Some(f);
-            }
-        }
-    }
-    None
+fn lookahead_formants<'a>(upcoming: impl IntoIterator<Item = &'a Phoneme>) -> Option<[f32; 3]> {
+    upcoming
+        .into_iter()
+        .take(3)
+        .filter(|p| p.ty == PhonemeType::Vowel)
+        .find_map(|p| p.formants)
 }
 
 fn consonant_duration(class: ConsonantClass, mult: f32) -> f32 {
@@ -593,12 +630,16 @@ fn normalize(samples: &mut [f32], volume: f32) {
 }
 
 fn apply_soft_limiter(samples: &mut [f32], drive: f32) {
-    let k = drive.max(1.0);
     for s in samples.iter_mut() {
-        *s = (*s * k).tanh() / k.tanh();
+        *s = soft_limit(*s, drive);
     }
 }
 
+fn soft_limit(x: f32, drive: f32) -> f32 {
+    let k = drive.max(1.0);
+    (x * k).tanh() / k.tanh()
+}
+
 fn clamp_peak(samples: &mut [f32], max_abs: f32) {
     let lim = max_abs.clamp(0.0, 1.0);
     for s in samples.iter_mut() {
@@ -607,13 +648,16 @@ fn clamp_peak(samples: &mut [f32], max_abs: f32) {
 }
 
 fn apply_distortion(samples: &mut [f32], amount: f32) {
-    let drive = (1.0 + amount * 12.0).min(30.0);
     for s in samples.iter_mut() {
-        let x = (*s * drive).clamp(-3.0, 3.0);
-        *s = x.tanh();
+        *s = distort(*s, amount);
     }
 }
 
+fn distort(x: f32, amount: f32) -> f32 {
+    let drive = (1.0 + amount * 12.0).min(30.0);
+    (x * drive).clamp(-3.0, 3.0).tanh()
+}
+
 fn apply_reverb(samples: &mut [f32], mix: f32, comb: &mut Comb) {
     let wet = mix.clamp(0.0, 0.95);
     let dry = 1.0 - wet;
@@ -720,6 +764,240 @@ fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a + (b - a) * t.clamp(0.0, 1.0)
 }
 
+// --------- streaming ---------
+
+/// what a `VoiceStream` works through, in order
+#[derive(Debug, Clone)]
+pub enum VoiceCue {
+    /// the voice for everything after it, a styled run switches it mid-line
+    Params(VoiceParams),
+    Phoneme(Phoneme),
+    /// seconds of silence
+    Silence(f32),
+}
+
+/// live changes on top of a streamed line's own voice
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoiceBend {
+    pub pitch_scale: f32,
+    /// added to the line's creepiness
+    pub creepiness: f32,
+    /// added to the line's distortion
+    pub distortion: f32,
+}
+
+impl Default for VoiceBend {
+    fn default() -> Self {
+        Self {
+            pitch_scale: 1.0,
+            creepiness: 0.0,
+            distortion: 0.0,
+        }
+    }
+}
+
+impl VoiceBend {
+    fn apply(&self, params: &VoiceParams) -> VoiceParams {
+        let mut params = params.clone();
+        params.pitch_hz *= self.pitch_scale.max(0.05);
+        params.creepiness = (params.creepiness + self.creepiness).clamp(0.0, 1.0);
+        params.distortion = (params.distortion + self.distortion).clamp(0.0, 1.0);
+        params
+    }
+}
+
+/// `VoiceSynth` a sample at a time: each phoneme is rendered when it's reached
+/// and the effect chain runs per sample, without the whole-line normalize.
+/// pitch changes land on the next phoneme, distortion on the next sample.
+/// buffers are sized up front, nothing allocates once it's on the audio thread
+pub struct VoiceStream {
+    synth: VoiceSynth,
+    /// the line's own voice, before the bend
+    base: VoiceParams,
+    bend: VoiceBend,
+    /// the line's cues, `next_cue` on are still to come
+    cues: Vec<VoiceCue>,
+    next_cue: usize,
+    /// rendered samples, `read` on are still to play
+    ready: Vec<f32>,
+    read: usize,
+    /// what the next phoneme renders into before it joins `ready`
+    segment: Vec<f32>,
+    /// samples left of a `VoiceCue::Silence`, nothing renders meanwhile
+    silence: usize,
+    /// silent samples still run through the effects so the reverb rings out
+    tail: usize,
+    fx: StreamFx,
+}
+
+/// samples a cut-off line fades out over
+const STREAM_STOP_FADE_SAMPLES: usize = 256;
+/// samples the longest phoneme renders to
+const STREAM_SEGMENT_SAMPLES: usize = (MAX_PHONEME_SECS * SAMPLE_RATE as f32) as usize + 1;
+
+impl VoiceStream {
+    pub fn new(params: VoiceParams) -> Self {
+        Self {
+            synth: VoiceSynth::new(params.clone()),
+            base: params,
+            bend: VoiceBend::default(),
+            cues: Vec::new(),
+            next_cue: 0,
+            ready: Vec::with_capacity(STREAM_SEGMENT_SAMPLES + JOIN_FADE_SAMPLES),
+            read: 0,
+            segment: Vec::with_capacity(STREAM_SEGMENT_SAMPLES),
+            silence: 0,
+            tail: 0,
+            fx: StreamFx::default(),
+        }
+    }
+
+    /// cuts off whatever is playing and starts on `cues`. the line before's
+    /// cues are swapped out into `cues`, for whoever sent them to drop
+    pub fn say(&mut self, cues: &mut Vec<VoiceCue>) {
+        self.stop();
+        std::mem::swap(&mut self.cues, cues);
+        self.next_cue = 0;
+    }
+
+    /// drops the rest of the line mid-word, with a short fade so it doesn't click
+    pub fn stop(&mut self) {
+        self.next_cue = self.cues.len();
+        self.silence = 0;
+        self.ready
+            .truncate((self.read + STREAM_STOP_FADE_SAMPLES).min(self.ready.len()));
+        let fade = &mut self.ready[self.read..];
+        let len = fade.len();
+        for (i, s) in fade.iter_mut().enumerate() {
+            *s *= 1.0 - i as f32 / len as f32;
+        }
+    }
+
+    pub fn set_bend(&mut self, bend: VoiceBend) {
+        self.bend = bend;
+        self.synth.params = bend.apply(&self.base);
+    }
+
+    /// false once the line and its reverb tail are out
+    pub fn is_active(&self) -> bool {
+        self.next_cue < self.cues.len()
+            || self.read < self.ready.len()
+            || self.silence > 0
+            || self.tail > 0
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        // keep a crossfade's worth ahead so phonemes join like offline
+        while self.silence == 0
+            && self.ready.len() - self.read <= JOIN_FADE_SAMPLES
+            && self.render_next()
+        {}
+        let dry = if let Some(&sample) = self.ready.get(self.read) {
+            self.read += 1;
+            sample
+        } else if self.silence > 0 {
+            self.silence -= 1;
+            0.0
+        } else if self.tail > 0 {
+            self.tail -= 1;
+            0.0
+        } else {
+            return 0.0;
+        };
+        self.fx
+            .process(dry, &self.synth.params, &mut self.synth.rvb)
+    }
+
+    /// works through the next cue, a phoneme goes onto `ready`. false once
+    /// none are left
+    fn render_next(&mut self) -> bool {
+        let Some(cue) = self.cues.get(self.next_cue).cloned() else {
+            return false;
+        };
+        self.next_cue += 1;
+        // played samples go, the unplayed ones move to the front in place
+        self.ready.drain(..self.read);
+        self.read = 0;
+        match cue {
+            VoiceCue::Params(params) => {
+                self.base = params;
+                self.synth.params = self.bend.apply(&self.base);
+            }
+            VoiceCue::Silence(secs) => {
+                self.synth.clear_filters();
+                // the line fades into the gap like it would into silent samples
+                let overlap = JOIN_FADE_SAMPLES.min(self.ready.len());
+                let start = self.ready.len() - overlap;
+                for (i, s) in self.ready[start..].iter_mut().enumerate() {
+                    *s *= (1.0 - i as f32 / overlap as f32).sqrt();
+                }
+                self.silence = (secs.max(0.0) * SAMPLE_RATE as f32) as usize;
+            }
+            VoiceCue::Phoneme(p) => {
+                let next = lookahead_formants(self.cues[self.next_cue..].iter().filter_map(
+                    |cue| match cue {
+                        VoiceCue::Phoneme(p) => Some(p),
+                        _ => None,
+                    },
+                ));
+                self.synth.render(&p, next, &mut self.segment);
+                let overlap = JOIN_FADE_SAMPLES
+                    .min(self.ready.len())
+                    .min(self.segment.len());
+                let start = self.ready.len() - overlap;
+                for i in 0..overlap {
+                    let a = i as f32 / overlap.max(1) as f32;
+                    self.ready[start + i] =
+                        self.ready[start + i] * (1.0 - a).sqrt() + self.segment[i] * a.sqrt();
+                }
+                self.ready.extend_from_slice(&self.segment[overlap..]);
+                self.tail = (self.synth.tail_secs() * SAMPLE_RATE as f32) as usize;
+            }
+        }
+        true
+    }
+}
+
+/// the offline effect chain as running state, one sample at a time
+#[derive(Default)]
+struct StreamFx {
+    lowpass: f32,
+    highpass: f32,
+    highpass_x1: f32,
+    guard: f32,
+    dc_x1: f32,
+    dc_y1: f32,
+}
+
+impl StreamFx {
+    fn process(&mut self, x: f32, params: &VoiceParams, rvb: &mut Comb) -> f32 {
+        let dt = 1.0 / SAMPLE_RATE as f32;
+        let mut s = x;
+        if params.distortion > 0.02 {
+            s = distort(s, params.distortion);
+        }
+        if params.reverb_mix > 0.001 {
+            let wet = params.reverb_mix.clamp(0.0, 0.95);
+            s = s * (1.0 - wet) + rvb.process(s) * wet;
+        }
+
+        // lowpass at 6800hz, highpass at 38hz
+        let rc = 1.0 / (2.0 * PI * 6800.0);
+        self.lowpass += dt / (rc + dt) * (s - self.lowpass);
+        let rc = 1.0 / (2.0 * PI * 38.0);
+        self.highpass = rc / (rc + dt) * (self.highpass + self.lowpass - self.highpass_x1);
+        self.highpass_x1 = self.lowpass;
+
+        // transient guard, then dc removal
+        self.guard += (self.highpass - self.guard).clamp(-0.075, 0.075);
+        let y = self.guard - self.dc_x1 + 0.995 * self.dc_y1;
+        self.dc_x1 = self.guard;
+        self.dc_y1 = y;
+
+        soft_limit(y * params.volume, 1.05).clamp(-0.98, 0.98)
+    }
+}
+
 // --------- filters ---------
 
 #[derive(Clone, Copy, Debug)]
@@ -809,3 +1087,65 @@ impl Comb {
         y
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::phonetic::PhoneticMapper;
+
+    fn cues(text: &str, params: &VoiceParams) -> Vec<VoiceCue> {
+        let phonemes = PhoneticMapper::default().text_to_phonemes(text, params.language);
+        std::iter::once(VoiceCue::Params(params.clone()))
+            .chain(std::iter::once(VoiceCue::Silence(0.05)))
+            .chain(phonemes.into_iter().map(VoiceCue::Phoneme))
+            .collect()
+    }
+
+    #[test]
+    fn streams_keep_their_buffers() {
+        let mut slow = VoiceParams::default_english();
+        // slow enough that phonemes hit `MAX_PHONEME_SECS`
+        slow.speed = 0.05;
+        let mut stream = VoiceStream::new(slow.clone());
+        let buffers = |stream: &VoiceStream| {
+            (
+                stream.ready.as_ptr(),
+                stream.ready.capacity(),
+                stream.segment.as_ptr(),
+                stream.segment.capacity(),
+            )
+        };
+        let before = buffers(&stream);
+
+        let mut line = cues("who's there? speak up.", &slow);
+        let sent = line.len();
+        stream.say(&mut line);
+        assert!(line.is_empty(), "the stream had no line to give back");
+        let mut samples = 0;
+        while stream.is_active() {
+            let sample = stream.next_sample();
+            assert!(sample.is_finite() && sample.abs() <= 0.98);
+            samples += 1;
+        }
+        assert!(samples > SAMPLE_RATE as usize);
+        assert_eq!(buffers(&stream), before);
+
+        let mut next = cues("go away.", &slow);
+        stream.say(&mut next);
+        assert_eq!(next.len(), sent, "the played line comes back out");
+    }
+
+    #[test]
+    fn stopping_fades_out_within_a_moment() {
+        let params = VoiceParams::default_english();
+        let mut stream = VoiceStream::new(params.clone());
+        stream.say(&mut cues("a long line that never gets to finish", &params));
+        for _ in 0..SAMPLE_RATE / 2 {
+            stream.next_sample();
+        }
+        stream.stop();
+        let left = std::iter::from_fn(|| stream.is_active().then(|| stream.next_sample())).count();
+        let tail = (stream.synth.tail_secs() * SAMPLE_RATE as f32) as usize;
+        assert!(left <= STREAM_STOP_FADE_SAMPLES + tail, "{left}");
+    }
+}
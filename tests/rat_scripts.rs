@@ -0,0 +1,51 @@
+//! plays `tests/rat/*.rat.test` against the shipped dialogue scripts
+
+use std::path::{Path, PathBuf};
+
+use feverish::ratspinner::{RatHarness, RatScriptTest};
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn rat_test_paths() -> Vec<PathBuf> {
+    let dir = manifest_dir().join("tests/rat");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|error| panic!("failed to read {}: {error}", dir.display()))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(".rat.test"))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// plays one file in a fresh app, so variables and visits don't leak between
+/// files
+fn run_rat_test(path: &Path) -> Result<(), String> {
+    let name = path
+        .strip_prefix(manifest_dir())
+        .unwrap_or(path)
+        .display()
+        .to_string();
+    let content =
+        std::fs::read_to_string(path).map_err(|error| format!("failed to read {name}: {error}"))?;
+    let test = RatScriptTest::parse(name, &content)?;
+    let mut harness = RatHarness::from_assets(&manifest_dir().join("assets"))?;
+    test.run(&mut harness)
+}
+
+#[test]
+fn rat_test_files_pass() {
+    let paths = rat_test_paths();
+    assert!(!paths.is_empty(), "no .rat.test files in tests/rat");
+
+    let failures: Vec<String> = paths
+        .iter()
+        .filter_map(|path| run_rat_test(path).err())
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}